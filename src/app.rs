@@ -1,108 +1,987 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
-use ratatui::prelude::Rect;
-use std::path::PathBuf;
+use ratatui::prelude::{Position, Rect};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::mpsc;
 
-use crate::terminal::TerminalPane;
+use crate::bell::{Bell, BellMode};
+use crate::copy_mode::{self, CopyMode, Pos, SelectionKind};
+use crate::export::CopyFormat;
+use crate::graphics::Placement;
+use crate::process::{self, ProcessInfo, ProcessMonitor, ProcessSampler};
+use crate::profile::Profile;
+use crate::screenshot::ScreenshotFormat;
+use crate::search::{Search, SearchMode};
+use crate::tabs::{Split, SplitDirection, Tabs};
+use crate::terminal::{TerminalPane, TerminalSettings};
+use crate::transcript::{self, TranscriptFormat, TranscriptOptions};
 use crate::tree::FileTree;
+use crate::ui;
+use crate::vterm::CursorStyle;
 
+/// Startup options parsed from the command line
+pub struct Options {
+    pub path: PathBuf,
+    pub tree_width: u16,
+    pub show_hidden: bool,
+    pub depth: usize,
+    pub terminal: TerminalSettings,
+    pub bell_mode: BellMode,
+    pub notify_command: Option<String>,
+    pub copy_format: CopyFormat,
+    /// `--screenshot`: capture the screen to this file after the delay, then quit
+    pub screenshot: Option<PathBuf>,
+    pub screenshot_delay: Duration,
+    /// `--transcript`: write the session transcript here on exit
+    pub transcript: Option<PathBuf>,
+    /// Split Markdown transcripts into one section per prompt
+    pub transcript_sections: bool,
+    /// Command for split panes; the user's shell when unset
+    pub split_command: Option<String>,
+    /// Key that suspends cltree (`--suspend-key`); None disables it
+    pub suspend_key: Option<KeyBinding>,
+    /// Key that starts a cltree command (`--prefix-key`); None disables it
+    pub prefix_key: Option<KeyBinding>,
+    /// Stop the children too while cltree is suspended
+    pub suspend_children: bool,
+    /// `--profile`: what runs in each tab
+    pub profile: Profile,
+    /// Passed to the profile's command after its own arguments
+    pub command_args: Vec<String>,
+}
+
+/// A key with modifiers, as given on the command line
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl Default for KeyBinding {
+    /// Ctrl+Z, like a shell's suspend key
+    fn default() -> Self {
+        Self {
+            code: KeyCode::Char('z'),
+            modifiers: KeyModifiers::CONTROL,
+        }
+    }
+}
+
+impl KeyBinding {
+    /// Ctrl plus a letter
+    pub fn ctrl(c: char) -> Self {
+        Self {
+            code: KeyCode::Char(c),
+            modifiers: KeyModifiers::CONTROL,
+        }
+    }
+
+    /// `ctrl+z`, `alt+s`, `ctrl+shift+f12`, ... (`C-z` and `M-s` work too)
+    pub fn parse(spec: &str) -> Option<Self> {
+        let spec = spec.to_ascii_lowercase();
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = spec.as_str();
+        loop {
+            let (modifier, tail) = match rest.split_once(['+', '-']) {
+                Some((head, tail)) if !tail.is_empty() => (head, tail),
+                _ => break,
+            };
+            modifiers |= match modifier {
+                "ctrl" | "control" | "c" => KeyModifiers::CONTROL,
+                "alt" | "meta" | "m" => KeyModifiers::ALT,
+                "shift" | "s" => KeyModifiers::SHIFT,
+                _ => return None,
+            };
+            rest = tail;
+        }
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => KeyCode::F(rest.strip_prefix('f')?.parse().ok()?),
+        };
+        Some(Self { code, modifiers })
+    }
+
+    pub fn matches(&self, key: &KeyEvent) -> bool {
+        // Terminals report Shift+letter as the capital letter
+        let (code, modifiers) = match key.code {
+            KeyCode::Char(c) if c.is_ascii_uppercase() => (
+                KeyCode::Char(c.to_ascii_lowercase()),
+                key.modifiers | KeyModifiers::SHIFT,
+            ),
+            code => (code, key.modifiers),
+        };
+        code == self.code && modifiers == self.modifiers
+    }
+}
+
+impl std::fmt::Display for KeyBinding {
+    /// Emacs style: `C-x`, `M-s`, `C-S-F12`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (modifier, label) in [
+            (KeyModifiers::CONTROL, "C-"),
+            (KeyModifiers::ALT, "M-"),
+            (KeyModifiers::SHIFT, "S-"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(label)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::F(n) => write!(f, "F{n}"),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
+
+/// Mouse or output-block selection, anchored to buffer lines so it stays on
+/// the same text while the view scrolls or output arrives
 pub struct Selection {
-    pub start: (u16, u16), // (col, row) terminal-local coordinates
-    pub end: (u16, u16),
+    pub start: Pos,
+    pub end: Pos,
+}
+
+impl Selection {
+    /// Start and end in reading order
+    pub fn ordered(&self) -> (Pos, Pos) {
+        copy_mode::ordered(self.start, self.end)
+    }
+
+    pub fn contains(&self, line: usize, col: usize) -> bool {
+        let (start, end) = self.ordered();
+        start <= (line, col) && (line, col) <= end
+    }
+}
+
+/// The process inspector's selection. It follows the selected PID, so rows
+/// moving between samples never shift it onto another process.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Inspector {
+    pub pid: Option<u32>,
+    /// The PID's row, where the selection stays if the process exits
+    pub row: usize,
+}
+
+impl Inspector {
+    /// Find the selected PID in a new sample
+    pub fn follow(&mut self, processes: &[ProcessInfo]) {
+        match self
+            .pid
+            .and_then(|pid| processes.iter().position(|p| p.pid == pid))
+        {
+            Some(row) => self.row = row,
+            None => self.select(self.row, processes),
+        }
+    }
+
+    /// Select row `row`, or the last row if there are fewer
+    pub fn select(&mut self, row: usize, processes: &[ProcessInfo]) {
+        self.row = row.min(processes.len().saturating_sub(1));
+        self.pid = processes.get(self.row).map(|p| p.pid);
+    }
 }
 
+/// Two clicks closer together than this extend a double/triple click
+const MULTI_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+/// How long a status message stays on the terminal pane's border
+const STATUS_DURATION: Duration = Duration::from_secs(3);
+/// How often the active session's process tree is read again
+const PROCESS_SCAN_INTERVAL: Duration = Duration::from_secs(1);
+
 pub struct App {
     pub tree: FileTree,
-    pub terminal: TerminalPane,
+    /// Claude sessions; the tree follows the active one
+    pub tabs: Tabs,
+    /// Tab name being typed after `Ctrl+X ,`
+    pub rename: Option<String>,
     pub tree_width_percent: u16,
     pub tree_loading: bool,
     pub tree_area: Option<Rect>,
+    /// Area shared by the active tab's panes, borders included
+    pub panes_area: Option<Rect>,
+    /// Inner area of the focused terminal pane
     pub terminal_area: Option<Rect>,
+    /// Inner area of the unfocused pane when the active tab is split
+    pub other_pane_area: Option<Rect>,
     pub selection: Option<Selection>,
     pub last_auto_scroll_cwd: Option<PathBuf>,
+    pub settings: TerminalSettings,
+    /// Whether the host terminal window currently has focus
+    pub host_focused: bool,
+    /// Cursor style the last draw asked the host terminal for
+    pub cursor_style: CursorStyle,
+    /// Cursor style last written to the host terminal (None until first draw)
+    pub applied_cursor_style: Option<CursorStyle>,
+    pub bell: Bell,
+    /// Set after the prefix key; the next key is a cltree command
+    pub prefix_pending: bool,
+    /// Show what each CWD detector found over the Claude pane
+    pub cwd_debug: bool,
+    /// The active tab's processes (Claude's, then the split's) and their
+    /// descendants
+    pub processes: Vec<ProcessInfo>,
+    process_monitor: ProcessMonitor,
+    /// The process inspector's selection, while it is open
+    pub inspector: Option<Inspector>,
+    /// Scrollback search, while the search bar is open
+    pub search: Option<Search>,
+    /// Keyboard-driven cursor and selection over the scrollback
+    pub copy_mode: Option<CopyMode>,
+    /// Clipboard format for mouse selections and copy-mode yanks
+    pub copy_format: CopyFormat,
+    /// File to write a screenshot of the next frame to
+    pub screenshot_request: Option<PathBuf>,
+    /// Set by the suspend key; the main loop suspends cltree
+    pub suspend_requested: bool,
+    /// Stop the children too while suspended (`--suspend-children`)
+    pub suspend_children: bool,
+    suspend_key: Option<KeyBinding>,
+    pub prefix_key: Option<KeyBinding>,
+    /// Quit once the pending screenshot has been written (`--screenshot`)
+    pub exit_after_screenshot: bool,
+    /// Images the host terminal should draw itself, found by the last draw
+    pub image_overlays: Vec<(Position, Placement)>,
+    /// Position and serial of the images currently drawn by the host
+    pub shown_overlays: Vec<(Position, u64)>,
+    /// Scheduled `--screenshot` capture
+    screenshot_at: Option<(PathBuf, Instant)>,
+    /// Transcript file written on exit (`--transcript`)
+    transcript_on_exit: Option<PathBuf>,
+    transcript_sections: bool,
+    /// Short message for the terminal pane's border, and when it was set
+    status: Option<(String, Instant)>,
+    /// Time, position and count of the last left click (for double/triple click)
+    last_click: Option<(Instant, Pos, u8)>,
+    /// Lines to scroll per tick while a drag is held past the pane's top
+    /// (negative) or bottom (positive) edge
+    drag_scroll: isize,
+    /// What new tabs are started with
+    profile: Profile,
+    command_args: Vec<String>,
+    /// `--split-command`, run in split panes instead of the user's shell
+    split_command: Option<String>,
+    pty_tx: mpsc::UnboundedSender<()>,
 }
 
 impl App {
-    pub fn new(
-        path: PathBuf,
-        tree_width: u16,
-        show_hidden: bool,
-        max_depth: usize,
-        claude_args: Vec<String>,
-        pty_tx: mpsc::UnboundedSender<()>,
-    ) -> Result<Self> {
-        let canonical_path = path.canonicalize().unwrap_or(path);
+    pub fn new(options: Options, pty_tx: mpsc::UnboundedSender<()>) -> Result<Self> {
+        let canonical_path = options.path.canonicalize().unwrap_or(options.path);
 
         Ok(Self {
-            tree: FileTree::new(&canonical_path, show_hidden, max_depth)?,
-            terminal: TerminalPane::new(&canonical_path, &claude_args, pty_tx)?,
-            tree_width_percent: tree_width.clamp(10, 50),
+            tree: FileTree::new(&canonical_path, options.show_hidden, options.depth)?,
+            tabs: Tabs::new(TerminalPane::new(
+                &canonical_path,
+                &options.profile,
+                &options.command_args,
+                &options.terminal,
+                pty_tx.clone(),
+            )?),
+            rename: None,
+            tree_width_percent: options.tree_width.clamp(10, 50),
             tree_loading: true,
             tree_area: None,
+            panes_area: None,
             terminal_area: None,
+            other_pane_area: None,
             selection: None,
             last_auto_scroll_cwd: None,
+            settings: options.terminal,
+            host_focused: true,
+            cursor_style: CursorStyle::default(),
+            applied_cursor_style: None,
+            bell: Bell::new(options.bell_mode, options.notify_command),
+            prefix_pending: false,
+            cwd_debug: false,
+            processes: Vec::new(),
+            process_monitor: ProcessMonitor::spawn(PROCESS_SCAN_INTERVAL),
+            inspector: None,
+            search: None,
+            copy_mode: None,
+            copy_format: options.copy_format,
+            screenshot_request: None,
+            exit_after_screenshot: false,
+            suspend_requested: false,
+            suspend_children: options.suspend_children,
+            suspend_key: options.suspend_key,
+            prefix_key: options.prefix_key,
+            image_overlays: Vec::new(),
+            shown_overlays: Vec::new(),
+            screenshot_at: options
+                .screenshot
+                .map(|path| (path, Instant::now() + options.screenshot_delay)),
+            transcript_on_exit: options.transcript,
+            transcript_sections: options.transcript_sections,
+            status: None,
+            last_click: None,
+            drag_scroll: 0,
+            profile: options.profile,
+            command_args: options.command_args,
+            split_command: options.split_command,
+            pty_tx,
         })
     }
 
-    pub fn tick(&mut self) -> bool {
-        self.terminal.tick();
-        // Process clipboard requests from vterm (OSC 52)
-        {
-            let requests = self.terminal.vterm_lock().take_clipboard_requests();
-            for text in requests {
-                copy_to_clipboard(&text);
+    /// The active tab's focused terminal: Claude, or the split pane
+    pub fn terminal(&self) -> &TerminalPane {
+        self.tabs.active().focused()
+    }
+
+    pub fn terminal_mut(&mut self) -> &mut TerminalPane {
+        self.tabs.active_mut().focused_mut()
+    }
+
+    /// Start another session of the profile in a new tab, in the active tab's CWD
+    fn open_tab(&mut self) {
+        let settings = TerminalSettings {
+            // Only the first session is recorded
+            record: None,
+            ..self.settings.clone()
+        };
+        let cwd = self.tabs.active().pane.cwd().to_path_buf();
+        match TerminalPane::new(
+            &cwd,
+            &self.profile,
+            &self.command_args,
+            &settings,
+            self.pty_tx.clone(),
+        ) {
+            Ok(pane) => {
+                self.tabs.open(pane);
+                self.tab_switched();
             }
+            Err(e) => self.set_status(format!(" {e:#} ")),
+        }
+    }
+
+    /// Forget view state that belongs to the previously active tab
+    fn tab_switched(&mut self) {
+        self.selection = None;
+        self.search = None;
+        self.copy_mode = None;
+        self.drag_scroll = 0;
+        self.last_click = None;
+        // The tree shows the new tab's subprocesses once they are sampled
+        self.processes.clear();
+    }
+
+    pub fn tick(&mut self) {
+        let active = self.tabs.active_index();
+        for (index, tab) in self.tabs.iter_mut().enumerate() {
+            let mut output = false;
+            for pane in tab.panes_mut() {
+                pane.tick();
+                output |= pane.take_new_output();
+                // Process clipboard requests from vterm (OSC 52)
+                let requests = pane.vterm_lock().take_clipboard_requests();
+                for text in requests {
+                    copy_to_clipboard(&text);
+                }
+                // Process BEL and desktop notifications (OSC 9 / OSC 777)
+                let (bell, notifications) = {
+                    let mut vt = pane.vterm_lock();
+                    (vt.take_bell(), vt.take_notifications())
+                };
+                if bell {
+                    self.bell.ring();
+                }
+                for notification in &notifications {
+                    self.bell.notify(notification, self.host_focused);
+                }
+            }
+            // Light up background tabs that produce output
+            if output && index != active {
+                tab.activity = true;
+            }
+        }
+        self.close_exited_splits();
+        self.process_monitor.watch(self.process_roots());
+        if let Some(processes) = self.process_monitor.latest() {
+            self.processes = processes;
+            if let Some(inspector) = self.inspector.as_mut() {
+                inspector.follow(&self.processes);
+            }
+        }
+        if self.drag_scroll != 0 {
+            self.auto_scroll_selection();
+        }
+        if self
+            .screenshot_at
+            .as_ref()
+            .is_some_and(|(_, at)| Instant::now() >= *at)
+        {
+            self.screenshot_request = self.screenshot_at.take().map(|(path, _)| path);
+            self.exit_after_screenshot = true;
         }
         if self.tree_loading {
             self.tree_loading = false;
         }
-        self.terminal.is_process_exited()
+    }
+
+    /// Pass `signal` on to every tab's and split's child
+    pub fn signal_children(&self, signal: i32) {
+        for pane in self.tabs.iter().flat_map(|tab| tab.panes()) {
+            pane.signal(signal);
+        }
+    }
+
+    /// The children of the active tab's panes
+    fn process_roots(&self) -> Vec<u32> {
+        self.tabs
+            .active()
+            .panes()
+            .filter_map(TerminalPane::child_pid)
+            .collect()
+    }
+
+    /// Sample the process trees now rather than at the next interval
+    fn refresh_processes(&mut self) {
+        let roots = self.process_roots();
+        self.process_monitor.refresh(roots);
+    }
+
+    /// Whether every child has exited
+    pub fn children_exited(&self) -> bool {
+        self.tabs
+            .iter()
+            .flat_map(|tab| tab.panes())
+            .all(|pane| pane.is_process_exited())
+    }
+
+    /// The session whose exit status cltree passes on: the first tab's
+    /// main pane. That is the one started with the command line's
+    /// arguments, and unlike the active tab it does not depend on where
+    /// the user happened to be when everything exited.
+    pub fn primary_session(&self) -> &TerminalPane {
+        &self.tabs.first().pane
+    }
+
+    /// Close split panes whose command has exited; Claude keeps the tab
+    fn close_exited_splits(&mut self) {
+        let active = self.tabs.active_index();
+        let mut refocused = false;
+        for (index, tab) in self.tabs.iter_mut().enumerate() {
+            if tab
+                .split
+                .as_ref()
+                .is_some_and(|split| split.pane.is_process_exited())
+            {
+                refocused |= index == active && tab.split.take().is_some_and(|s| s.focused);
+            }
+        }
+        if refocused {
+            self.tab_switched();
+        }
+    }
+
+    /// Open a split pane running `--split-command` (or the user's shell) in
+    /// Claude's CWD. With a split already open, move it to `direction`.
+    fn open_split(&mut self, direction: SplitDirection) {
+        if let Some(split) = self.tabs.active_mut().split.as_mut() {
+            split.direction = direction;
+            return;
+        }
+        let shell = std::env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
+        let mut profile = Profile::command(&shell);
+        if let Some(command) = self.split_command.as_ref() {
+            profile.title = command.clone();
+            profile.args = vec!["-c".to_string(), command.clone()];
+        }
+        let title = profile.title.clone();
+        let settings = TerminalSettings {
+            record: None,
+            ..self.settings.clone()
+        };
+        let cwd = self.tabs.active().pane.cwd().to_path_buf();
+        match TerminalPane::new(&cwd, &profile, &[], &settings, self.pty_tx.clone()) {
+            Ok(pane) => {
+                self.terminal_mut().send_focus_event(false);
+                self.tabs.active_mut().split = Some(Split::new(pane, direction, title));
+                self.tab_switched();
+            }
+            Err(e) => self.set_status(format!(" {e:#} ")),
+        }
+    }
+
+    /// Close the active tab's split pane. Dropping the pane hangs up its
+    /// command's process group, as closing a tab does.
+    fn close_split(&mut self) {
+        if self.tabs.active_mut().split.take().is_some() {
+            self.tab_switched();
+        }
+    }
+
+    /// Move keyboard focus between Claude and the split pane
+    fn toggle_split_focus(&mut self) {
+        let host_focused = self.host_focused;
+        let Some(split) = self.tabs.active_mut().split.as_mut() else {
+            return;
+        };
+        split.focused = !split.focused;
+        // The pane losing focus sees a focus-out, as if it were its own window
+        if host_focused {
+            let tab = self.tabs.active_mut();
+            if let Some(pane) = tab.unfocused_mut() {
+                pane.send_focus_event(false);
+            }
+            tab.focused_mut().send_focus_event(true);
+        }
+        self.tab_switched();
     }
 
     pub fn handle_key(&mut self, key: KeyEvent) -> bool {
         self.selection = None;
+        if self.prefix_pending {
+            self.prefix_pending = false;
+            self.handle_prefix_command(key);
+            return false;
+        }
+        if self.rename.is_some() {
+            self.handle_rename_key(key);
+            return false;
+        }
+        if self.copy_mode.is_some() {
+            self.handle_copy_key(key);
+            return false;
+        }
+        if self.inspector.is_some() {
+            self.handle_inspector_key(key);
+            return false;
+        }
+        if self.search.is_some() && self.handle_search_key(key) {
+            return false;
+        }
         match (key.code, key.modifiers) {
+            _ if self.prefix_key.is_some_and(|k| k.matches(&key)) => {
+                self.prefix_pending = true;
+                false
+            }
             (KeyCode::Char('c'), KeyModifiers::CONTROL) => {
-                self.terminal.send_interrupt();
+                self.terminal_mut().send_interrupt();
                 false
             }
             (KeyCode::Char('q'), KeyModifiers::CONTROL) => true,
+            _ if self.suspend_key.is_some_and(|k| k.matches(&key)) => {
+                self.suspend_requested = true;
+                false
+            }
+            _ if self.terminal().is_process_exited() => self.handle_exited_key(key),
             _ => {
-                self.terminal.handle_key(key);
+                self.terminal_mut().handle_key(key);
                 false
             }
         }
     }
 
+    /// Keys once the focused session has exited: restart it as it was,
+    /// continuing the last session or picking one to resume (when its
+    /// profile can), or quit. Quitting closes just the tab while there are
+    /// others. Returns true to quit cltree.
+    fn handle_exited_key(&mut self, key: KeyEvent) -> bool {
+        let profile = self.terminal().profile();
+        let session_args = match (key.code, key.modifiers) {
+            (KeyCode::Char('r'), KeyModifiers::NONE) => None,
+            (KeyCode::Char('c'), KeyModifiers::NONE) if !profile.continue_args.is_empty() => {
+                Some(profile.continue_args.clone())
+            }
+            (KeyCode::Char('R'), _) if !profile.resume_args.is_empty() => {
+                Some(profile.resume_args.clone())
+            }
+            (KeyCode::Char('q'), KeyModifiers::NONE) => {
+                if !self.tabs.close(self.tabs.active_index()) {
+                    return true;
+                }
+                self.tab_switched();
+                return false;
+            }
+            _ => return false,
+        };
+
+        let mut args = self.terminal().args().to_vec();
+        if let Some(session_args) = session_args {
+            args = self.terminal().profile().without_session_flags(&args);
+            args.extend(session_args);
+        }
+        match self.terminal_mut().restart(args) {
+            Ok(()) => {
+                self.tab_switched();
+                self.terminal().vterm_lock().set_scroll_offset(0);
+            }
+            Err(e) => self.set_status(format!(" {e:#} ")),
+        }
+        false
+    }
+
+    /// Commands available after the prefix key (Ctrl+X by default)
+    fn handle_prefix_command(&mut self, key: KeyEvent) {
+        match (key.code, key.modifiers) {
+            // The prefix twice sends it to the child
+            _ if self.prefix_key.is_some_and(|k| k.matches(&key)) => {
+                self.terminal_mut().handle_key(key)
+            }
+            (KeyCode::Up, _) | (KeyCode::Char('k'), KeyModifiers::NONE) => {
+                self.terminal_mut().jump_to_prompt(false)
+            }
+            (KeyCode::Down, _) | (KeyCode::Char('j'), KeyModifiers::NONE) => {
+                self.terminal_mut().jump_to_prompt(true)
+            }
+            (KeyCode::Char('o'), KeyModifiers::NONE) => {
+                if let Some((selection, text)) = self.terminal_mut().select_output_block() {
+                    self.selection = Some(selection);
+                    copy_to_clipboard(&text);
+                }
+            }
+            (KeyCode::Char('y'), KeyModifiers::NONE) => {
+                if let Some(text) = self.terminal().last_output_text() {
+                    copy_to_clipboard(&text);
+                }
+            }
+            (KeyCode::Char('/'), KeyModifiers::NONE) => self.search = Some(Search::new()),
+            (KeyCode::Char('s'), KeyModifiers::NONE) => {
+                self.request_screenshot(ScreenshotFormat::Svg)
+            }
+            (KeyCode::Char('S'), _) => self.request_screenshot(ScreenshotFormat::Html),
+            (KeyCode::Char('t'), KeyModifiers::NONE) => {
+                let path = timestamped_path(TranscriptFormat::Markdown.extension());
+                let message = match self.save_transcript(&path) {
+                    Ok(()) => format!(" Saved {} ", path.display()),
+                    Err(e) => format!(" {e:#} "),
+                };
+                self.set_status(message);
+            }
+            (KeyCode::Char('d'), KeyModifiers::NONE) => self.cwd_debug = !self.cwd_debug,
+            (KeyCode::Char('P'), _) => {
+                self.search = None;
+                self.copy_mode = None;
+                self.refresh_processes();
+                let mut inspector = Inspector::default();
+                inspector.select(0, &self.processes);
+                self.inspector = Some(inspector);
+            }
+            (KeyCode::Char('['), KeyModifiers::NONE) => {
+                self.search = None;
+                self.copy_mode = Some(CopyMode::new(&self.tabs.active().focused().vterm_lock()));
+            }
+            // Tabs
+            (KeyCode::Char('c'), KeyModifiers::NONE) => self.open_tab(),
+            (KeyCode::Char('n'), KeyModifiers::NONE) => {
+                self.tabs.cycle(true);
+                self.tab_switched();
+            }
+            (KeyCode::Char('p'), KeyModifiers::NONE) => {
+                self.tabs.cycle(false);
+                self.tab_switched();
+            }
+            (KeyCode::Char(c @ '1'..='9'), KeyModifiers::NONE) => {
+                let index = c as usize - '1' as usize;
+                if index != self.tabs.active_index() && self.tabs.select(index) {
+                    self.tab_switched();
+                }
+            }
+            (KeyCode::Char(','), KeyModifiers::NONE) => {
+                self.rename = Some(self.tabs.active().name().unwrap_or_default().to_string());
+            }
+            // Split pane
+            (KeyCode::Char('%'), _) => self.open_split(SplitDirection::Right),
+            (KeyCode::Char('"'), _) => self.open_split(SplitDirection::Below),
+            (KeyCode::Tab, _) => self.toggle_split_focus(),
+            (KeyCode::Char(c @ ('+' | '=' | '-')), _) => {
+                if let Some(split) = self.tabs.active_mut().split.as_mut() {
+                    // Grow or shrink whichever pane has focus
+                    let delta = if (c == '-') == split.focused { -5 } else { 5 };
+                    split.resize(delta);
+                }
+            }
+            (KeyCode::Char('x'), KeyModifiers::NONE) => self.close_split(),
+            (KeyCode::Char('X'), _) => {
+                if self.tabs.close(self.tabs.active_index()) {
+                    self.tab_switched();
+                } else {
+                    self.set_status(" Last tab: Ctrl+Q quits ".to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Keys while typing a tab name: Enter applies it (blank names the tab
+    /// after its CWD again), Esc cancels
+    fn handle_rename_key(&mut self, key: KeyEvent) {
+        let Some(name) = self.rename.as_mut() else {
+            return;
+        };
+        match (key.code, key.modifiers) {
+            (KeyCode::Enter, _) => {
+                if let Some(name) = self.rename.take() {
+                    self.tabs.active_mut().rename(&name);
+                }
+            }
+            (KeyCode::Esc, _) | (KeyCode::Char('c'), KeyModifiers::CONTROL) => self.rename = None,
+            (KeyCode::Backspace, _) => {
+                name.pop();
+            }
+            (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => name.push(c),
+            _ => {}
+        }
+    }
+
+    /// Keys while the process inspector is open: move the selection, send
+    /// SIGINT (`i`) or SIGTERM (`t`) to the selected process, or close it
+    fn handle_inspector_key(&mut self, key: KeyEvent) {
+        let Some(mut inspector) = self.inspector else {
+            return;
+        };
+        let signal = match (key.code, key.modifiers) {
+            (KeyCode::Up, _) | (KeyCode::Char('k'), KeyModifiers::NONE) => {
+                inspector.select(inspector.row.saturating_sub(1), &self.processes);
+                self.inspector = Some(inspector);
+                return;
+            }
+            (KeyCode::Down, _) | (KeyCode::Char('j'), KeyModifiers::NONE) => {
+                inspector.select(inspector.row + 1, &self.processes);
+                self.inspector = Some(inspector);
+                return;
+            }
+            (KeyCode::Char('i'), KeyModifiers::NONE) => ("SIGINT", process::SIGINT),
+            (KeyCode::Char('t'), KeyModifiers::NONE) => ("SIGTERM", process::SIGTERM),
+            (KeyCode::Esc, _)
+            | (KeyCode::Char('q'), KeyModifiers::NONE)
+            | (KeyCode::Char('c'), KeyModifiers::CONTROL) => {
+                self.inspector = None;
+                return;
+            }
+            _ => return,
+        };
+
+        let Some(pid) = inspector.pid else {
+            return;
+        };
+        let (name, signal) = signal;
+        // The list can be a second old and PIDs get reused: only signal the
+        // process if it is still in the session's tree right now
+        let current = ProcessSampler::default().sample(&self.process_roots());
+        let message = match current.iter().find(|p| p.pid == pid) {
+            Some(target) => match process::send_signal(pid, signal) {
+                Ok(()) => format!(" Sent {name} to {pid} ({}) ", target.name),
+                Err(e) => format!(" {name} to {pid} failed: {e} "),
+            },
+            None => format!(" {pid} is no longer in this session; nothing sent "),
+        };
+        self.set_status(message);
+        // Show the result without waiting for the next scan
+        self.refresh_processes();
+    }
+
+    /// Capture the next frame to `cltree-<unix time>.<ext>` in the working
+    /// directory
+    fn request_screenshot(&mut self, format: ScreenshotFormat) {
+        self.screenshot_request = Some(timestamped_path(format.extension()));
+    }
+
+    /// Write the scrollback and screen to `path`; the format follows the
+    /// file extension
+    pub fn save_transcript(&self, path: &Path) -> Result<()> {
+        let options = TranscriptOptions {
+            format: TranscriptFormat::from_path(path),
+            sections: self.transcript_sections,
+        };
+        let text = transcript::render(&self.tabs.active().pane.vterm_lock(), options);
+        std::fs::write(path, text)
+            .with_context(|| format!("cannot write transcript {}", path.display()))
+    }
+
+    /// Save the `--transcript` file, if one was requested. Returns its path.
+    pub fn save_transcript_on_exit(&self) -> Result<Option<&Path>> {
+        match self.transcript_on_exit.as_deref() {
+            Some(path) => self.save_transcript(path).map(|()| Some(path)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_status(&mut self, message: String) {
+        self.status = Some((message, Instant::now()));
+    }
+
+    /// Status message, while it is still fresh
+    pub fn status(&self) -> Option<&str> {
+        self.status
+            .as_ref()
+            .filter(|(_, at)| at.elapsed() < STATUS_DURATION)
+            .map(|(message, _)| message.as_str())
+    }
+
+    /// Keys in copy mode. Everything is captured until the mode is left.
+    fn handle_copy_key(&mut self, key: KeyEvent) {
+        let Some(copy) = self.copy_mode.as_mut() else {
+            return;
+        };
+        let vt = self.tabs.active().focused().vterm_lock();
+        let half_page = (vt.rows() / 2).max(1) as isize;
+        let page = vt.rows().max(1) as isize;
+        copy.clamp(&vt);
+        let mut exit = false;
+        match (key.code, key.modifiers) {
+            (KeyCode::Char('h'), KeyModifiers::NONE) | (KeyCode::Left, _) => {
+                copy.move_by(&vt, -1, 0)
+            }
+            (KeyCode::Char('l'), KeyModifiers::NONE) | (KeyCode::Right, _) => {
+                copy.move_by(&vt, 1, 0)
+            }
+            (KeyCode::Char('k'), KeyModifiers::NONE) | (KeyCode::Up, _) => copy.move_by(&vt, 0, -1),
+            (KeyCode::Char('j'), KeyModifiers::NONE) | (KeyCode::Down, _) => {
+                copy.move_by(&vt, 0, 1)
+            }
+            (KeyCode::Char('u'), KeyModifiers::CONTROL) => copy.move_by(&vt, 0, -half_page),
+            (KeyCode::Char('d'), KeyModifiers::CONTROL) => copy.move_by(&vt, 0, half_page),
+            (KeyCode::Char('b'), KeyModifiers::CONTROL) | (KeyCode::PageUp, _) => {
+                copy.move_by(&vt, 0, -page)
+            }
+            (KeyCode::Char('f'), KeyModifiers::CONTROL) | (KeyCode::PageDown, _) => {
+                copy.move_by(&vt, 0, page)
+            }
+            (KeyCode::Char('w'), KeyModifiers::NONE) => copy.word_forward(&vt),
+            (KeyCode::Char('b'), KeyModifiers::NONE) => copy.word_backward(&vt),
+            (KeyCode::Char('e'), KeyModifiers::NONE) => copy.word_end(&vt),
+            (KeyCode::Char('0'), KeyModifiers::NONE) | (KeyCode::Home, _) => copy.line_start(),
+            (KeyCode::Char('^'), _) => copy.first_non_blank(&vt),
+            (KeyCode::Char('$'), _) | (KeyCode::End, _) => copy.line_end(&vt),
+            (KeyCode::Char('g'), KeyModifiers::NONE) => copy.buffer_top(&vt),
+            (KeyCode::Char('G'), _) => copy.buffer_bottom(&vt),
+            (KeyCode::Char('v'), KeyModifiers::NONE) | (KeyCode::Char(' '), KeyModifiers::NONE) => {
+                copy.toggle_selection(SelectionKind::Char)
+            }
+            (KeyCode::Char('V'), _) => copy.toggle_selection(SelectionKind::Line),
+            (KeyCode::Char('v'), KeyModifiers::CONTROL) => {
+                copy.toggle_selection(SelectionKind::Block)
+            }
+            // Yank the selection and leave copy mode; A/H yank with ANSI
+            // styling or as HTML regardless of --copy-format
+            (KeyCode::Char('y'), KeyModifiers::NONE)
+            | (KeyCode::Enter, _)
+            | (KeyCode::Char('A'), _)
+            | (KeyCode::Char('H'), _) => {
+                let format = match key.code {
+                    KeyCode::Char('A') => CopyFormat::Ansi,
+                    KeyCode::Char('H') => CopyFormat::Html,
+                    _ => self.copy_format,
+                };
+                if let Some(text) = copy.selected_text(&vt, format) {
+                    copy_to_clipboard(&text);
+                }
+                exit = true;
+            }
+            // Esc clears the selection first, then leaves
+            (KeyCode::Esc, _) if copy.anchor.is_some() => copy.anchor = None,
+            (KeyCode::Esc, _)
+            | (KeyCode::Char('q'), KeyModifiers::NONE)
+            | (KeyCode::Char('c'), KeyModifiers::CONTROL) => exit = true,
+            _ => {}
+        }
+        let cursor_line = copy.cursor_index(&vt);
+        drop(vt);
+
+        if exit {
+            self.copy_mode = None;
+            self.terminal().vterm_lock().set_scroll_offset(0);
+        } else {
+            self.terminal_mut().keep_line_visible(cursor_line);
+        }
+    }
+
+    /// Keys while the search bar is open. Returns false for keys that close
+    /// the search and should still be handled normally.
+    fn handle_search_key(&mut self, key: KeyEvent) -> bool {
+        let Some(search) = self.search.as_mut() else {
+            return false;
+        };
+        let mut requery = false;
+        if search.editing {
+            match (key.code, key.modifiers) {
+                (KeyCode::Esc, _) => {
+                    self.search = None;
+                    return true;
+                }
+                (KeyCode::Enter, _) => search.editing = false,
+                (KeyCode::Up, _) => search.step(true),
+                (KeyCode::Down, _) => search.step(false),
+                (KeyCode::Backspace, _) => {
+                    search.query.pop();
+                    requery = true;
+                }
+                // Ctrl+R toggles between plain text and regex matching
+                (KeyCode::Char('r'), KeyModifiers::CONTROL) => {
+                    search.mode = match search.mode {
+                        SearchMode::Plain => SearchMode::Regex,
+                        SearchMode::Regex => SearchMode::Plain,
+                    };
+                    requery = true;
+                }
+                (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                    search.query.push(c);
+                    requery = true;
+                }
+                _ => {}
+            }
+        } else {
+            match (key.code, key.modifiers) {
+                (KeyCode::Char('n'), KeyModifiers::NONE) => search.step(true),
+                (KeyCode::Char('N'), _) => search.step(false),
+                (KeyCode::Char('/'), KeyModifiers::NONE) => search.editing = true,
+                (KeyCode::Esc, _) | (KeyCode::Char('q'), KeyModifiers::NONE) => {
+                    self.search = None;
+                    return true;
+                }
+                _ => {
+                    self.search = None;
+                    return false;
+                }
+            }
+        }
+
+        if requery {
+            search.update(&self.tabs.active().focused().vterm_lock());
+        }
+        let evicted = self.tabs.active().focused().vterm_lock().evicted_lines();
+        // A match whose line has left the scrollback can't be shown
+        let line = search
+            .current_match()
+            .and_then(|m| m.start.0.checked_sub(evicted));
+        if let Some(line) = line {
+            self.terminal_mut().reveal_line(line);
+        }
+        true
+    }
+
+    pub fn handle_focus(&mut self, gained: bool) {
+        self.host_focused = gained;
+        self.terminal_mut().send_focus_event(gained);
+    }
+
     pub fn handle_paste(&mut self, text: String) {
         self.selection = None;
-        self.terminal.handle_paste(text);
+        self.terminal_mut().handle_paste(text);
     }
 
     pub fn handle_mouse(&mut self, event: MouseEvent) {
-        let in_tree = self.tree_area.is_some_and(|area| {
-            event.column >= area.x
-                && event.column < area.x + area.width
-                && event.row >= area.y
-                && event.row < area.y + area.height
-        });
-
-        let in_terminal = self.terminal_area.is_some_and(|area| {
-            event.column >= area.x
-                && event.column < area.x + area.width
-                && event.row >= area.y
-                && event.row < area.y + area.height
-        });
+        let hit = |area: Option<Rect>| {
+            area.is_some_and(|area| area.contains(Position::new(event.column, event.row)))
+        };
+        let in_tree = hit(self.tree_area);
+        let mut in_terminal = hit(self.terminal_area);
+        let in_other_pane = hit(self.other_pane_area);
 
         match event.kind {
             MouseEventKind::ScrollUp => {
                 if in_tree {
                     let offset = self.tree.offset();
                     self.tree.set_offset(offset.saturating_sub(3));
+                } else if in_other_pane {
+                    if let Some(pane) = self.tabs.active_mut().unfocused_mut() {
+                        pane.scroll_up();
+                    }
                 } else {
-                    self.terminal.scroll_up();
+                    self.terminal_mut().scroll_up();
                 }
             }
             MouseEventKind::ScrollDown => {
@@ -111,43 +990,82 @@ impl App {
                     let max_offset = self.tree.nodes().len().saturating_sub(visible_height);
                     let offset = (self.tree.offset() + 3).min(max_offset);
                     self.tree.set_offset(offset);
+                } else if in_other_pane {
+                    if let Some(pane) = self.tabs.active_mut().unfocused_mut() {
+                        pane.scroll_down();
+                    }
                 } else {
-                    self.terminal.scroll_down();
+                    self.terminal_mut().scroll_down();
                 }
             }
             MouseEventKind::Down(MouseButton::Left) => {
+                // Clicking the other pane of a split focuses it
+                if in_other_pane {
+                    self.toggle_split_focus();
+                    if let Some(area) = self.panes_area {
+                        let (focused, other) = ui::focus_areas(self.tabs.active(), area);
+                        self.terminal_area = Some(focused);
+                        self.other_pane_area = other;
+                    }
+                    in_terminal = true;
+                }
                 if in_terminal {
-                    let area = self.terminal_area.unwrap();
+                    let Some(area) = self.terminal_area else {
+                        return;
+                    };
                     let col = event.column.saturating_sub(area.x);
                     let row = event.row.saturating_sub(area.y);
-                    self.selection = Some(Selection {
-                        start: (col, row),
-                        end: (col, row),
-                    });
+                    let pos = self.terminal().buffer_pos(col, row);
+                    let now = Instant::now();
+                    let clicks = match self.last_click {
+                        Some((at, last_pos, n))
+                            if last_pos == pos && now.duration_since(at) < MULTI_CLICK_INTERVAL =>
+                        {
+                            n % 3 + 1
+                        }
+                        _ => 1,
+                    };
+                    self.last_click = Some((now, pos, clicks));
+                    let (start, end) = match clicks {
+                        2 => copy_mode::word_bounds(&self.terminal().vterm_lock(), pos),
+                        3 => copy_mode::line_bounds(&self.terminal().vterm_lock(), pos),
+                        _ => (pos, pos),
+                    };
+                    self.selection = Some(Selection { start, end });
                 } else {
                     self.selection = None;
                 }
             }
             MouseEventKind::Drag(MouseButton::Left) => {
-                if let Some(ref mut sel) = self.selection {
-                    if let Some(area) = self.terminal_area {
-                        let col = event
-                            .column
-                            .saturating_sub(area.x)
-                            .min(area.width.saturating_sub(1));
-                        let row = event
-                            .row
-                            .saturating_sub(area.y)
-                            .min(area.height.saturating_sub(1));
-                        sel.end = (col, row);
+                if let (Some(area), true) = (self.terminal_area, self.selection.is_some()) {
+                    let col = event
+                        .column
+                        .saturating_sub(area.x)
+                        .min(area.width.saturating_sub(1));
+                    // Past the top or bottom edge: keep scrolling on each tick
+                    self.drag_scroll = if event.row < area.y {
+                        -3
+                    } else if event.row >= area.y + area.height {
+                        3
+                    } else {
+                        0
+                    };
+                    let row = event
+                        .row
+                        .saturating_sub(area.y)
+                        .min(area.height.saturating_sub(1));
+                    let pos = self.terminal().buffer_pos(col, row);
+                    if let Some(ref mut sel) = self.selection {
+                        sel.end = pos;
                     }
                 }
             }
             MouseEventKind::Up(MouseButton::Left) => {
+                self.drag_scroll = 0;
                 if let Some(sel) = self.selection.as_ref() {
                     // Only copy if the selection spans more than a single point
                     if sel.start != sel.end {
-                        let text = self.terminal.extract_text(sel.start, sel.end);
+                        let text = self.terminal().extract_text(sel, self.copy_format);
                         if !text.is_empty() {
                             copy_to_clipboard(&text);
                         }
@@ -158,6 +1076,27 @@ impl App {
         }
     }
 
+    /// Scroll while a drag is held past the pane edge, extending the
+    /// selection to the newly exposed top or bottom line
+    fn auto_scroll_selection(&mut self) {
+        let (Some(sel), Some(area)) = (self.selection.as_mut(), self.terminal_area) else {
+            self.drag_scroll = 0;
+            return;
+        };
+        let mut vt = self.tabs.active().focused().vterm_lock();
+        let offset = vt.scroll_offset().saturating_add_signed(-self.drag_scroll);
+        vt.set_scroll_offset(offset);
+        let top = vt.evicted_lines() + vt.view_top();
+        sel.end = if self.drag_scroll < 0 {
+            (top, 0)
+        } else {
+            (
+                top + area.height.saturating_sub(1) as usize,
+                area.width.saturating_sub(1) as usize,
+            )
+        };
+    }
+
     pub fn handle_file_change(&mut self, path: PathBuf) {
         // Refresh tree if file changed
         if path.starts_with(self.tree.root_path()) {
@@ -166,6 +1105,14 @@ impl App {
     }
 }
 
+/// `cltree-<unix time>.<ext>`, relative to the working directory
+fn timestamped_path(extension: &str) -> PathBuf {
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    PathBuf::from(format!("cltree-{stamp}.{extension}"))
+}
+
 pub(crate) fn copy_to_clipboard(text: &str) -> bool {
     #[cfg(target_os = "macos")]
     {
@@ -207,3 +1154,176 @@ fn try_clipboard_cmd(program: &str, args: &[&str], text: &str) -> bool {
         Err(_) => false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    /// An app in /tmp whose tabs run `profile` with `args`
+    fn app(profile: Profile, args: &[&str]) -> App {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let options = Options {
+            path: PathBuf::from("/tmp"),
+            tree_width: 30,
+            show_hidden: false,
+            depth: 1,
+            terminal: TerminalSettings::default(),
+            bell_mode: BellMode::None,
+            notify_command: None,
+            copy_format: CopyFormat::default(),
+            screenshot: None,
+            screenshot_delay: Duration::ZERO,
+            transcript: None,
+            transcript_sections: false,
+            split_command: None,
+            suspend_key: None,
+            prefix_key: None,
+            suspend_children: false,
+            profile,
+            command_args: args.iter().map(|arg| arg.to_string()).collect(),
+        };
+        App::new(options, tx).unwrap()
+    }
+
+    /// Wait for `pid` to exit and be reaped
+    #[cfg(unix)]
+    fn wait_until_gone(pid: u32) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        // SAFETY: signal 0 only checks that the process exists
+        while unsafe { libc::kill(pid as libc::pid_t, 0) } == 0 {
+            assert!(Instant::now() < deadline, "process {pid} still running");
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    fn processes(pids: &[u32]) -> Vec<ProcessInfo> {
+        pids.iter()
+            .map(|&pid| ProcessInfo {
+                pid,
+                ppid: 1,
+                depth: 0,
+                name: format!("p{pid}"),
+                cmdline: Vec::new(),
+                cwd: None,
+                cpu_percent: 0.0,
+                rss: 0,
+                elapsed: Duration::ZERO,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_inspector_follows_the_selected_pid() {
+        let mut inspector = Inspector::default();
+        inspector.select(1, &processes(&[10, 20, 30]));
+        assert_eq!(inspector.pid, Some(20));
+
+        // A process starting above it moves the row, not the selection
+        inspector.follow(&processes(&[10, 15, 20, 30]));
+        assert_eq!((inspector.pid, inspector.row), (Some(20), 2));
+
+        // When it exits, the row stays and takes the next process
+        inspector.follow(&processes(&[10, 15, 30]));
+        assert_eq!((inspector.pid, inspector.row), (Some(30), 2));
+        inspector.follow(&processes(&[10]));
+        assert_eq!((inspector.pid, inspector.row), (Some(10), 0));
+        inspector.follow(&[]);
+        assert_eq!(inspector.pid, None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_close_split_ends_its_command() {
+        let mut app = app(Profile::command("/nonexistent/cltree-test"), &[]);
+        // Even a command that ignores the hangup is killed after the grace
+        app.split_command = Some("trap '' HUP; echo ready; sleep 30".to_string());
+        app.open_split(SplitDirection::Right);
+        let pane = &app.tabs.active().split.as_ref().unwrap().pane;
+        let pid = pane.child_pid().unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !pane.take_new_output() {
+            assert!(Instant::now() < deadline, "split never started");
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        app.close_split();
+        assert!(app.tabs.active().split.is_none());
+        wait_until_gone(pid);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exit_status_comes_from_the_first_tab() {
+        use crate::terminal::ChildExit;
+
+        let mut app = app(Profile::command("sh"), &["-c", "exit 3"]);
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let args = ["-c".to_string(), "exit 0".to_string()];
+        let settings = TerminalSettings::default();
+        let second = TerminalPane::new(Path::new("/tmp"), &app.profile, &args, &settings, tx);
+        app.tabs.open(second.unwrap());
+        assert_eq!(app.tabs.active_index(), 1);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !app.children_exited() {
+            assert!(Instant::now() < deadline, "sessions still running");
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(
+            app.tabs.active().pane.exit_status(),
+            Some(ChildExit::Code(0))
+        );
+        assert_eq!(
+            app.primary_session().exit_status(),
+            Some(ChildExit::Code(3))
+        );
+    }
+
+    #[test]
+    fn test_key_binding_parse() {
+        let parse = |spec| KeyBinding::parse(spec).unwrap();
+        assert_eq!(parse("ctrl+z"), KeyBinding::default());
+        assert_eq!(parse("Control+Z"), KeyBinding::default());
+        assert_eq!(parse("C-x"), KeyBinding::ctrl('x'));
+        assert_eq!(parse("M-s").to_string(), "M-s");
+        assert_eq!(parse("alt+shift+f12").to_string(), "M-S-F12");
+        assert_eq!(parse("ctrl-shift-a").to_string(), "C-S-a");
+        assert_eq!(parse("f5").to_string(), "F5");
+        assert_eq!(parse("f").to_string(), "f");
+        // A lone `-` or `+` is a key, not a separator
+        assert_eq!(parse("ctrl+-").to_string(), "C--");
+        for spec in ["", "hyper+x", "ctrl+", "ctrl+xy", "fx"] {
+            assert_eq!(KeyBinding::parse(spec), None, "{spec:?}");
+        }
+    }
+
+    #[test]
+    fn test_key_binding_matches() {
+        let ctrl_x = KeyBinding::ctrl('x');
+        assert!(ctrl_x.matches(&key(KeyCode::Char('x'), KeyModifiers::CONTROL)));
+        assert!(!ctrl_x.matches(&key(KeyCode::Char('x'), KeyModifiers::NONE)));
+        assert!(!ctrl_x.matches(&key(
+            KeyCode::Char('x'),
+            KeyModifiers::CONTROL | KeyModifiers::ALT
+        )));
+
+        let alt_s = KeyBinding::parse("alt+s").unwrap();
+        assert!(alt_s.matches(&key(KeyCode::Char('s'), KeyModifiers::ALT)));
+
+        let f12 = KeyBinding::parse("shift+f12").unwrap();
+        assert!(f12.matches(&key(KeyCode::F(12), KeyModifiers::SHIFT)));
+        assert!(!f12.matches(&key(KeyCode::F(12), KeyModifiers::NONE)));
+
+        // Terminals report Shift+letter as the capital, with or without SHIFT
+        let shift_a = KeyBinding::parse("ctrl+shift+a").unwrap();
+        assert!(shift_a.matches(&key(KeyCode::Char('A'), KeyModifiers::CONTROL)));
+        assert!(shift_a.matches(&key(
+            KeyCode::Char('A'),
+            KeyModifiers::CONTROL | KeyModifiers::SHIFT
+        )));
+        assert!(!shift_a.matches(&key(KeyCode::Char('a'), KeyModifiers::CONTROL)));
+    }
+}
@@ -0,0 +1,226 @@
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Writes the PTY output stream and resizes to an asciicast v2 file
+/// (<https://docs.asciinema.org/manual/asciicast/v2/>).
+pub struct Recorder {
+    inner: Mutex<RecorderState>,
+    start: Instant,
+}
+
+struct RecorderState {
+    out: BufWriter<File>,
+    /// Trailing bytes of an incomplete UTF-8 sequence, kept for the next chunk
+    pending: Vec<u8>,
+    /// Header waiting for the pane's first size; events are held until then
+    header: Option<Value>,
+    held: Vec<Value>,
+    size: Option<(u16, u16)>,
+}
+
+impl Recorder {
+    /// Start a recording of a pane running with `term` as its TERM. The
+    /// header is written once the first [`Recorder::resize`] gives the size.
+    pub fn create(path: &Path, term: &str) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("cannot create recording {}", path.display()))?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let header = json!({
+            "version": 2,
+            "timestamp": timestamp,
+            "env": {
+                "TERM": term,
+                "SHELL": std::env::var("SHELL").unwrap_or_default(),
+            },
+        });
+        Ok(Self {
+            inner: Mutex::new(RecorderState {
+                out: BufWriter::new(file),
+                pending: Vec::new(),
+                header: Some(header),
+                held: Vec::new(),
+                size: None,
+            }),
+            start: Instant::now(),
+        })
+    }
+
+    /// Record a chunk of PTY output. Chunks may split UTF-8 sequences; the
+    /// tail is held back until the rest arrives.
+    pub fn output(&self, bytes: &[u8]) {
+        let mut state = self.lock();
+        state.pending.extend_from_slice(bytes);
+        let valid = match std::str::from_utf8(&state.pending) {
+            Ok(_) => state.pending.len(),
+            // Incomplete sequence at the end: wait for more bytes
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => state.pending.len(),
+        };
+        if valid == 0 {
+            return;
+        }
+        let chunk: Vec<u8> = state.pending.drain(..valid).collect();
+        let text = String::from_utf8_lossy(&chunk);
+        self.write_event(&mut state, "o", &text);
+    }
+
+    /// Record the pane's size. The first call sets the header's size;
+    /// repeats of the current size are ignored.
+    pub fn resize(&self, cols: u16, rows: u16) {
+        let mut state = self.lock();
+        if state.size == Some((cols, rows)) {
+            return;
+        }
+        state.size = Some((cols, rows));
+        if state.header.is_some() {
+            let _ = state.write_header(cols, rows);
+        } else {
+            self.write_event(&mut state, "r", &format!("{cols}x{rows}"));
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, RecorderState> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write_event(&self, state: &mut RecorderState, code: &str, data: &str) {
+        let time = self.start.elapsed().as_secs_f64();
+        let event = json!([(time * 1e6).round() / 1e6, code, data]);
+        if state.header.is_some() {
+            state.held.push(event);
+            return;
+        }
+        // Flush every event so a crash still leaves a playable file
+        let _ = writeln!(state.out, "{event}").and_then(|_| state.out.flush());
+    }
+}
+
+impl RecorderState {
+    fn write_header(&mut self, cols: u16, rows: u16) -> std::io::Result<()> {
+        let Some(mut header) = self.header.take() else {
+            return Ok(());
+        };
+        header["width"] = cols.into();
+        header["height"] = rows.into();
+        writeln!(self.out, "{header}")?;
+        for event in std::mem::take(&mut self.held) {
+            writeln!(self.out, "{event}")?;
+        }
+        self.out.flush()
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        // Never sized: fall back to the vterm's default so the file still plays
+        let _ = self.lock().write_header(80, 24);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EventKind {
+    Output(String),
+    Resize(u16, u16),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    /// Seconds since the start of the recording
+    pub time: f64,
+    pub kind: EventKind,
+}
+
+#[derive(Clone, Debug)]
+pub struct Cast {
+    pub width: u16,
+    pub height: u16,
+    /// Longest pause kept during playback (`idle_time_limit` in the header)
+    pub idle_time_limit: Option<f64>,
+    pub events: Vec<Event>,
+}
+
+impl Cast {
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("cannot open recording {}", path.display()))?;
+        Self::parse(BufReader::new(file))
+    }
+
+    /// Parse an asciicast v2 stream. Input (`i`) and marker (`m`) events are
+    /// skipped.
+    pub fn parse(reader: impl BufRead) -> Result<Self> {
+        let mut lines = reader.lines();
+        let header: Value = match lines.next() {
+            Some(line) => serde_json::from_str(&line?).context("invalid asciicast header")?,
+            None => bail!("empty recording"),
+        };
+        if header["version"] != 2 {
+            bail!("unsupported asciicast version: {}", header["version"]);
+        }
+        let dimension = |key: &str| -> Result<u16> {
+            header[key]
+                .as_u64()
+                .and_then(|v| u16::try_from(v).ok())
+                .with_context(|| format!("asciicast header is missing {key}"))
+        };
+        let mut cast = Cast {
+            width: dimension("width")?,
+            height: dimension("height")?,
+            idle_time_limit: header["idle_time_limit"].as_f64(),
+            events: Vec::new(),
+        };
+
+        for (n, line) in lines.enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let event: Value = serde_json::from_str(&line)
+                .with_context(|| format!("invalid asciicast event on line {}", n + 2))?;
+            let (Some(time), Some(code), Some(data)) =
+                (event[0].as_f64(), event[1].as_str(), event[2].as_str())
+            else {
+                bail!("invalid asciicast event on line {}", n + 2);
+            };
+            let kind = match code {
+                "o" => EventKind::Output(data.to_string()),
+                "r" => match data.split_once('x').map(|(c, r)| (c.parse(), r.parse())) {
+                    Some((Ok(cols), Ok(rows))) => EventKind::Resize(cols, rows),
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            cast.events.push(Event { time, kind });
+        }
+
+        if let Some(limit) = cast.idle_time_limit {
+            cast.compress_idle(limit);
+        }
+        Ok(cast)
+    }
+
+    /// Cap every pause between events at `limit` seconds
+    fn compress_idle(&mut self, limit: f64) {
+        let mut shift = 0.0;
+        let mut prev = 0.0;
+        for event in &mut self.events {
+            let gap = event.time - prev;
+            prev = event.time;
+            if gap > limit {
+                shift += gap - limit;
+            }
+            event.time -= shift;
+        }
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.events.last().map_or(0.0, |e| e.time)
+    }
+}
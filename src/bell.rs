@@ -0,0 +1,247 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::vterm::Notification;
+
+/// How long the terminal pane border stays highlighted after a BEL
+const FLASH_DURATION: Duration = Duration::from_millis(300);
+/// Minimum gap between two audible bells forwarded to the host
+const AUDIBLE_INTERVAL: Duration = Duration::from_millis(500);
+/// Minimum gap between two desktop notifications
+const NOTIFY_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BellMode {
+    #[default]
+    Both,
+    Visual,
+    Audible,
+    None,
+}
+
+impl BellMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "both" => Some(Self::Both),
+            "visual" => Some(Self::Visual),
+            "audible" => Some(Self::Audible),
+            "none" | "off" => Some(Self::None),
+            _ => None,
+        }
+    }
+
+    fn visual(self) -> bool {
+        matches!(self, Self::Both | Self::Visual)
+    }
+
+    fn audible(self) -> bool {
+        matches!(self, Self::Both | Self::Audible)
+    }
+}
+
+/// Turns the child's BEL and OSC 9/777 notifications into a flashing border,
+/// a forwarded host bell and rate-limited desktop notifications.
+pub struct Bell {
+    mode: BellMode,
+    notify_command: Option<String>,
+    flash_until: Option<Instant>,
+    last_audible: Option<Instant>,
+    last_notification: Option<Instant>,
+}
+
+impl Bell {
+    pub fn new(mode: BellMode, notify_command: Option<String>) -> Self {
+        Self {
+            mode,
+            notify_command,
+            flash_until: None,
+            last_audible: None,
+            last_notification: None,
+        }
+    }
+
+    pub fn ring(&mut self) {
+        if self.ring_at(Instant::now()) {
+            let mut stdout = std::io::stdout();
+            let _ = stdout.write_all(b"\x07");
+            let _ = stdout.flush();
+        }
+    }
+
+    /// Start the flash; true when the host's bell should sound as well
+    fn ring_at(&mut self, now: Instant) -> bool {
+        if self.mode.visual() {
+            self.flash_until = Some(now + FLASH_DURATION);
+        }
+        if self.mode.audible()
+            && self
+                .last_audible
+                .is_none_or(|last| now.duration_since(last) >= AUDIBLE_INTERVAL)
+        {
+            self.last_audible = Some(now);
+            return true;
+        }
+        false
+    }
+
+    /// Whether the terminal border should currently be drawn highlighted
+    pub fn is_flashing(&self) -> bool {
+        self.is_flashing_at(Instant::now())
+    }
+
+    fn is_flashing_at(&self, now: Instant) -> bool {
+        self.flash_until.is_some_and(|until| now < until)
+    }
+
+    /// Show a desktop notification, unless the user is already looking at
+    /// cltree or we sent one very recently.
+    pub fn notify(&mut self, notification: &Notification, focused: bool) {
+        let Some(mut cmd) = self.notify_at(notification, focused, Instant::now()) else {
+            return;
+        };
+        let spawned = cmd
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        if let Ok(mut child) = spawned {
+            // Reap in the background so the notifier never becomes a zombie
+            std::thread::spawn(move || child.wait());
+        }
+    }
+
+    /// The command that shows `notification`, if one should be shown now
+    fn notify_at(
+        &mut self,
+        notification: &Notification,
+        focused: bool,
+        now: Instant,
+    ) -> Option<Command> {
+        if focused {
+            return None;
+        }
+        if self
+            .last_notification
+            .is_some_and(|last| now.duration_since(last) < NOTIFY_INTERVAL)
+        {
+            return None;
+        }
+        self.last_notification = Some(now);
+
+        let title = notification.title.as_deref().unwrap_or("cltree");
+        match self.notify_command {
+            // User command: title and body are passed as $1 and $2
+            Some(ref command) => {
+                let mut cmd = Command::new("sh");
+                cmd.args(["-c", command, "cltree", title, &notification.body]);
+                Some(cmd)
+            }
+            None => default_notify_command(title, &notification.body),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn default_notify_command(title: &str, body: &str) -> Option<Command> {
+    let quote = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+    let mut cmd = Command::new("osascript");
+    cmd.arg("-e").arg(format!(
+        "display notification \"{}\" with title \"{}\"",
+        quote(body),
+        quote(title)
+    ));
+    Some(cmd)
+}
+
+#[cfg(target_os = "linux")]
+fn default_notify_command(title: &str, body: &str) -> Option<Command> {
+    let mut cmd = Command::new("notify-send");
+    // The child picks both strings; neither may pass for an option
+    cmd.arg("--").arg(title).arg(body);
+    Some(cmd)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn default_notify_command(_title: &str, _body: &str) -> Option<Command> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notification(body: &str) -> Notification {
+        Notification {
+            title: Some("Claude".to_string()),
+            body: body.to_string(),
+        }
+    }
+
+    fn args(cmd: &Command) -> Vec<String> {
+        cmd.get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_bell_mode_parse() {
+        assert_eq!(BellMode::parse("Both"), Some(BellMode::Both));
+        assert_eq!(BellMode::parse("visual"), Some(BellMode::Visual));
+        assert_eq!(BellMode::parse("AUDIBLE"), Some(BellMode::Audible));
+        assert_eq!(BellMode::parse("none"), Some(BellMode::None));
+        assert_eq!(BellMode::parse("off"), Some(BellMode::None));
+        assert_eq!(BellMode::parse("loud"), None);
+    }
+
+    #[test]
+    fn test_audible_bell_is_rate_limited() {
+        let start = Instant::now();
+        let mut bell = Bell::new(BellMode::Both, None);
+        assert!(bell.ring_at(start));
+        assert!(!bell.ring_at(start + AUDIBLE_INTERVAL / 2));
+        assert!(bell.ring_at(start + AUDIBLE_INTERVAL));
+
+        let mut visual = Bell::new(BellMode::Visual, None);
+        assert!(!visual.ring_at(start));
+        assert!(visual.is_flashing_at(start + FLASH_DURATION / 2));
+        assert!(!visual.is_flashing_at(start + FLASH_DURATION));
+
+        let mut audible = Bell::new(BellMode::Audible, None);
+        assert!(audible.ring_at(start));
+        assert!(!audible.is_flashing_at(start));
+    }
+
+    #[test]
+    fn test_notifications_are_rate_limited_and_skipped_while_focused() {
+        let start = Instant::now();
+        let mut bell = Bell::new(BellMode::Both, Some("true".to_string()));
+        assert!(bell.notify_at(&notification("a"), true, start).is_none());
+        // A suppressed notification does not start the interval
+        assert!(bell.notify_at(&notification("b"), false, start).is_some());
+        let soon = start + NOTIFY_INTERVAL - Duration::from_millis(1);
+        assert!(bell.notify_at(&notification("c"), false, soon).is_none());
+        let later = start + NOTIFY_INTERVAL;
+        assert!(bell.notify_at(&notification("d"), false, later).is_some());
+    }
+
+    #[test]
+    fn test_notify_command_gets_title_and_body_as_arguments() {
+        let mut bell = Bell::new(BellMode::Both, Some("echo \"$1\"".to_string()));
+        let cmd = bell
+            .notify_at(&notification("-done"), false, Instant::now())
+            .unwrap();
+        assert_eq!(cmd.get_program(), "sh");
+        assert_eq!(
+            args(&cmd),
+            ["-c", "echo \"$1\"", "cltree", "Claude", "-done"]
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_default_notify_command_ends_options() {
+        let cmd = default_notify_command("-t", "--urgency=critical").unwrap();
+        assert_eq!(args(&cmd), ["--", "-t", "--urgency=critical"]);
+    }
+}
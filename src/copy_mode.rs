@@ -0,0 +1,355 @@
+use crate::export::{self, CopyFormat};
+use crate::vterm::{trim_trailing_blanks, Cell, VirtualTerminal};
+
+/// A `(line, col)` buffer position. `line` is a [`VirtualTerminal::line`]
+/// index plus [`VirtualTerminal::evicted_lines`], so positions keep pointing
+/// at the same text while the child keeps writing.
+pub type Pos = (usize, usize);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SelectionKind {
+    #[default]
+    Char,
+    Line,
+    Block,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CharClass {
+    Blank,
+    Word,
+    Punct,
+}
+
+/// vi-style cursor and selection over the scrollback and screen
+#[derive(Clone, Debug)]
+pub struct CopyMode {
+    pub cursor: Pos,
+    /// Where the selection started, if one is active
+    pub anchor: Option<Pos>,
+    pub kind: SelectionKind,
+}
+
+impl CopyMode {
+    /// Start at the child's cursor, or at the bottom of the view when
+    /// scrolled back.
+    pub fn new(vt: &VirtualTerminal) -> Self {
+        let cursor = if vt.scroll_offset() == 0 {
+            let c = vt.cursor();
+            (
+                vt.scrollback().len() + c.y,
+                c.x.min(vt.cols().saturating_sub(1)),
+            )
+        } else {
+            (vt.view_top() + vt.rows().saturating_sub(1), 0)
+        };
+        Self {
+            cursor: (cursor.0 + vt.evicted_lines(), cursor.1),
+            anchor: None,
+            kind: SelectionKind::Char,
+        }
+    }
+
+    /// [`VirtualTerminal::line`] index of the cursor
+    pub fn cursor_index(&self, vt: &VirtualTerminal) -> usize {
+        to_index(vt, self.cursor.0)
+    }
+
+    /// Pull the cursor and anchor back inside the buffer, e.g. after lines
+    /// were evicted or the pane was resized.
+    pub fn clamp(&mut self, vt: &VirtualTerminal) {
+        self.cursor = clamp_pos(vt, self.cursor);
+        self.anchor = self.anchor.map(|a| clamp_pos(vt, a));
+    }
+
+    pub fn move_by(&mut self, vt: &VirtualTerminal, dx: isize, dy: isize) {
+        let (line, col) = self.cursor;
+        self.cursor = clamp_pos(
+            vt,
+            (
+                line.saturating_add_signed(dy),
+                col.saturating_add_signed(dx),
+            ),
+        );
+        self.skip_continuation(vt);
+    }
+
+    pub fn line_start(&mut self) {
+        self.cursor.1 = 0;
+    }
+
+    /// Last non-blank column of the line (`$`)
+    pub fn line_end(&mut self, vt: &VirtualTerminal) {
+        let text_end = (0..vt.cols())
+            .rev()
+            .find(|&col| class_at(vt, (self.cursor.0, col)) != CharClass::Blank);
+        self.cursor.1 = text_end.unwrap_or(0);
+    }
+
+    /// First non-blank column of the line (`^`)
+    pub fn first_non_blank(&mut self, vt: &VirtualTerminal) {
+        let start =
+            (0..vt.cols()).find(|&col| class_at(vt, (self.cursor.0, col)) != CharClass::Blank);
+        self.cursor.1 = start.unwrap_or(0);
+    }
+
+    /// Oldest line in the scrollback (`g`)
+    pub fn buffer_top(&mut self, vt: &VirtualTerminal) {
+        self.cursor = (vt.evicted_lines(), 0);
+    }
+
+    /// Last line of the screen (`G`)
+    pub fn buffer_bottom(&mut self, vt: &VirtualTerminal) {
+        self.cursor = (vt.evicted_lines() + vt.total_lines().saturating_sub(1), 0);
+    }
+
+    /// Start of the next word (`w`)
+    pub fn word_forward(&mut self, vt: &VirtualTerminal) {
+        let mut pos = self.cursor;
+        let start_class = class_at(vt, pos);
+        // Leave the current word; a line break also ends it
+        loop {
+            let Some(next) = next_pos(vt, pos) else {
+                return;
+            };
+            let crossed = next.0 != pos.0;
+            pos = next;
+            if crossed || class_at(vt, pos) != start_class {
+                break;
+            }
+        }
+        while class_at(vt, pos) == CharClass::Blank {
+            match next_pos(vt, pos) {
+                Some(next) => pos = next,
+                None => break,
+            }
+        }
+        self.cursor = pos;
+    }
+
+    /// Start of the current or previous word (`b`)
+    pub fn word_backward(&mut self, vt: &VirtualTerminal) {
+        let mut pos = self.cursor;
+        loop {
+            let Some(prev) = prev_pos(vt, pos) else {
+                return;
+            };
+            pos = prev;
+            if class_at(vt, pos) != CharClass::Blank {
+                break;
+            }
+        }
+        while let Some(prev) = prev_pos(vt, pos) {
+            if prev.0 != pos.0 || class_at(vt, prev) != class_at(vt, pos) {
+                break;
+            }
+            pos = prev;
+        }
+        self.cursor = pos;
+    }
+
+    /// End of the current or next word (`e`)
+    pub fn word_end(&mut self, vt: &VirtualTerminal) {
+        let mut pos = self.cursor;
+        loop {
+            let Some(next) = next_pos(vt, pos) else {
+                return;
+            };
+            pos = next;
+            if class_at(vt, pos) != CharClass::Blank {
+                break;
+            }
+        }
+        while let Some(next) = next_pos(vt, pos) {
+            if next.0 != pos.0 || class_at(vt, next) != class_at(vt, pos) {
+                break;
+            }
+            pos = next;
+        }
+        self.cursor = pos;
+    }
+
+    /// Start a selection of `kind` at the cursor. Pressing the same kind
+    /// again clears it; another kind switches the active selection over.
+    pub fn toggle_selection(&mut self, kind: SelectionKind) {
+        if self.anchor.is_some() && self.kind == kind {
+            self.anchor = None;
+        } else {
+            self.anchor.get_or_insert(self.cursor);
+            self.kind = kind;
+        }
+    }
+
+    /// Whether the cell at `(line, col)` is selected (`line` as in [`Pos`])
+    pub fn contains(&self, line: usize, col: usize) -> bool {
+        let Some(anchor) = self.anchor else {
+            return false;
+        };
+        let (start, end) = ordered(anchor, self.cursor);
+        match self.kind {
+            SelectionKind::Char => start <= (line, col) && (line, col) <= end,
+            SelectionKind::Line => start.0 <= line && line <= end.0,
+            SelectionKind::Block => {
+                let (left, right) = (anchor.1.min(self.cursor.1), anchor.1.max(self.cursor.1));
+                start.0 <= line && line <= end.0 && left <= col && col <= right
+            }
+        }
+    }
+
+    /// The active selection rendered in `format`. Character and line
+    /// selections join soft-wrapped rows; block selections keep one line per
+    /// row.
+    pub fn selected_text(&self, vt: &VirtualTerminal, format: CopyFormat) -> Option<String> {
+        let anchor = self.anchor?;
+        let (start, end) = ordered(anchor, self.cursor);
+        let last_col = vt.cols().saturating_sub(1);
+        let lines = match self.kind {
+            SelectionKind::Char => range_lines(vt, start, end),
+            SelectionKind::Line => range_lines(vt, (start.0, 0), (end.0, last_col)),
+            SelectionKind::Block => {
+                let (left, right) = (anchor.1.min(self.cursor.1), anchor.1.max(self.cursor.1));
+                (start.0..=end.0)
+                    .map(|line| {
+                        let mut cells: Vec<Cell> = line
+                            .checked_sub(vt.evicted_lines())
+                            .and_then(|index| vt.line(index))
+                            .map(|row| {
+                                row.iter()
+                                    .skip(left)
+                                    .take(right + 1 - left)
+                                    .cloned()
+                                    .collect()
+                            })
+                            .unwrap_or_default();
+                        trim_trailing_blanks(&mut cells);
+                        cells
+                    })
+                    .collect()
+            }
+        };
+        Some(export::render(vt, &lines, format))
+    }
+
+    /// Wide characters occupy two cells; keep the cursor on the first
+    fn skip_continuation(&mut self, vt: &VirtualTerminal) {
+        if self.cursor.1 > 0 && cell_text(vt, self.cursor).is_some_and(str::is_empty) {
+            self.cursor.1 -= 1;
+        }
+    }
+}
+
+/// First and last cell of the word under `pos`, within its row. Blanks and
+/// punctuation runs count as words too, as in vi.
+pub fn word_bounds(vt: &VirtualTerminal, pos: Pos) -> (Pos, Pos) {
+    let class = class_at(vt, pos);
+    let (mut start, mut end) = (pos, pos);
+    while let Some(prev) = prev_pos(vt, start) {
+        if prev.0 != pos.0 || class_at(vt, prev) != class {
+            break;
+        }
+        start = prev;
+    }
+    while let Some(next) = next_pos(vt, end) {
+        if next.0 != pos.0 || class_at(vt, next) != class {
+            break;
+        }
+        end = next;
+    }
+    // Include the trailing cell of a wide character
+    if cell_text(vt, (end.0, end.1 + 1)) == Some("") {
+        end.1 += 1;
+    }
+    (start, end)
+}
+
+/// First and last cell of the row under `pos`, as a triple click selects
+pub fn line_bounds(vt: &VirtualTerminal, pos: Pos) -> (Pos, Pos) {
+    ((pos.0, 0), (pos.0, vt.cols().saturating_sub(1)))
+}
+
+/// Logical lines between two buffer positions (inclusive). Lines evicted
+/// from the scrollback since the positions were taken are skipped.
+pub fn range_lines(vt: &VirtualTerminal, start: Pos, end: Pos) -> Vec<Vec<Cell>> {
+    let evicted = vt.evicted_lines();
+    let Some(end_line) = end.0.checked_sub(evicted) else {
+        return Vec::new();
+    };
+    let start = match start.0.checked_sub(evicted) {
+        Some(line) => (line, start.1),
+        None => (0, 0),
+    };
+    vt.logical_lines(start, (end_line, end.1))
+}
+
+pub(crate) fn ordered(a: Pos, b: Pos) -> (Pos, Pos) {
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+fn to_index(vt: &VirtualTerminal, line: usize) -> usize {
+    line.saturating_sub(vt.evicted_lines())
+        .min(vt.total_lines().saturating_sub(1))
+}
+
+fn clamp_pos(vt: &VirtualTerminal, (line, col): Pos) -> Pos {
+    (
+        to_index(vt, line) + vt.evicted_lines(),
+        col.min(vt.cols().saturating_sub(1)),
+    )
+}
+
+fn cell_text(vt: &VirtualTerminal, (line, col): Pos) -> Option<&str> {
+    let index = line.checked_sub(vt.evicted_lines())?;
+    vt.line(index)?.get(col).map(|c| c.ch.as_str())
+}
+
+fn class_at(vt: &VirtualTerminal, pos: Pos) -> CharClass {
+    let Some(ch) = cell_text(vt, pos).and_then(|s| s.chars().next()) else {
+        return CharClass::Blank;
+    };
+    if ch.is_whitespace() {
+        CharClass::Blank
+    } else if ch.is_alphanumeric() || ch == '_' {
+        CharClass::Word
+    } else {
+        CharClass::Punct
+    }
+}
+
+/// Next cell in reading order, skipping wide-char continuation cells
+fn next_pos(vt: &VirtualTerminal, (mut line, mut col): Pos) -> Option<Pos> {
+    let last_line = vt.evicted_lines() + vt.total_lines().checked_sub(1)?;
+    loop {
+        if col + 1 < vt.cols() {
+            col += 1;
+        } else if line < last_line {
+            line += 1;
+            col = 0;
+        } else {
+            return None;
+        }
+        if cell_text(vt, (line, col)) != Some("") {
+            return Some((line, col));
+        }
+    }
+}
+
+/// Previous cell in reading order, skipping wide-char continuation cells
+fn prev_pos(vt: &VirtualTerminal, (mut line, mut col): Pos) -> Option<Pos> {
+    loop {
+        if col > 0 {
+            col -= 1;
+        } else if line > vt.evicted_lines() {
+            line -= 1;
+            col = vt.cols().saturating_sub(1);
+        } else {
+            return None;
+        }
+        if cell_text(vt, (line, col)) != Some("") {
+            return Some((line, col));
+        }
+    }
+}
@@ -0,0 +1,359 @@
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use std::path::{Path, PathBuf};
+
+use crate::profile::CwdHints;
+use crate::vterm::VirtualTerminal;
+
+/// Names of the detectors a profile can list, in their default priority
+pub const DETECTORS: &[&str] = &["osc7", "process", "screen", "regex"];
+
+/// Ticks (about 250ms each) a guessed, shallower directory must keep being
+/// seen before it is followed, so stray paths don't make the tree flicker
+pub const SETTLE_TICKS: u32 = 8;
+
+/// What detectors can look at
+pub struct PaneState<'a> {
+    pub vterm: &'a VirtualTerminal,
+    pub pid: Option<u32>,
+}
+
+/// One way of finding out which directory a pane's program is working in
+pub trait CwdDetector: Send {
+    /// The name profiles and the debug overlay use
+    fn name(&self) -> &'static str;
+
+    fn detect(&self, pane: &PaneState) -> Option<PathBuf>;
+
+    /// Whether the result is a guess. A guess is followed when it's deeper
+    /// than the current directory, after settling when it's shallower, and
+    /// never at the same depth. Anything else is followed unless it's
+    /// shallower, in which case lower-priority detectors get their turn.
+    fn guesses(&self) -> bool {
+        false
+    }
+}
+
+/// The directory the program reported with OSC 7
+pub struct Osc7Detector;
+
+impl CwdDetector for Osc7Detector {
+    fn name(&self) -> &'static str {
+        "osc7"
+    }
+
+    fn detect(&self, pane: &PaneState) -> Option<PathBuf> {
+        pane.vterm.reported_cwd().map(Path::to_path_buf)
+    }
+}
+
+/// The child process's own working directory
+pub struct ProcessDetector;
+
+impl CwdDetector for ProcessDetector {
+    fn name(&self) -> &'static str {
+        "process"
+    }
+
+    fn detect(&self, pane: &PaneState) -> Option<PathBuf> {
+        process_cwd(pane.pid?)
+    }
+}
+
+/// The deepest existing directory among path-like text in the top rows,
+/// for programs that show their directory but never `cd` (Claude Code)
+pub struct ScreenDetector {
+    pub rows: usize,
+}
+
+impl CwdDetector for ScreenDetector {
+    fn name(&self) -> &'static str {
+        "screen"
+    }
+
+    fn detect(&self, pane: &PaneState) -> Option<PathBuf> {
+        header_rows(pane.vterm, self.rows)
+            .filter_map(|text| {
+                let start = text.find("~/").or_else(|| text.find('/'))?;
+                let path: String = text[start..]
+                    .chars()
+                    .take_while(|c| {
+                        c.is_alphanumeric() || matches!(*c, '~' | '/' | '.' | '-' | '_' | '+' | '@')
+                    })
+                    .collect();
+                resolve(&path)
+            })
+            .max_by_key(|path| path.components().count())
+    }
+
+    fn guesses(&self) -> bool {
+        true
+    }
+}
+
+/// The first existing directory matched by `pattern` in the top rows: its
+/// first capture group, or the whole match without one
+pub struct RegexDetector {
+    pub pattern: Regex,
+    pub rows: usize,
+}
+
+impl CwdDetector for RegexDetector {
+    fn name(&self) -> &'static str {
+        "regex"
+    }
+
+    fn detect(&self, pane: &PaneState) -> Option<PathBuf> {
+        header_rows(pane.vterm, self.rows).find_map(|text| {
+            let captures = self.pattern.captures(&text)?;
+            let path = captures.get(1).or_else(|| captures.get(0))?;
+            resolve(path.as_str().trim())
+        })
+    }
+
+    fn guesses(&self) -> bool {
+        true
+    }
+}
+
+/// The top `rows` of the screen, while they still hold the program's own
+/// header. Once output has scrolled off they hold the conversation, whose
+/// paths say nothing about where the program is.
+fn header_rows(vterm: &VirtualTerminal, rows: usize) -> impl Iterator<Item = String> + '_ {
+    let rows = if vterm.scrollback().is_empty() {
+        rows.min(vterm.rows())
+    } else {
+        0
+    };
+    (0..rows).map(|row| vterm.row_text(row))
+}
+
+/// An absolute or `~/` path, if it names an existing directory
+fn resolve(text: &str) -> Option<PathBuf> {
+    let text = text.trim_end_matches('/');
+    let path = match text.strip_prefix("~/") {
+        Some(rest) if !rest.is_empty() => dirs::home_dir()?.join(rest),
+        Some(_) => return None,
+        None if text.starts_with('/') => PathBuf::from(text),
+        None => return None,
+    };
+    path.is_dir().then_some(path)
+}
+
+/// What a detector made of its reading on the last tick
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// Found nothing
+    Nothing,
+    /// Agrees with the current directory
+    Same,
+    /// Its directory is now the current one
+    Followed,
+    /// Seen this many ticks in a row; followed at the settle count
+    Settling(u32),
+    /// Passed over: shallower, or a guess at the same depth
+    Ignored,
+    /// A higher-priority detector already moved the directory
+    Skipped,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reading {
+    pub detector: &'static str,
+    pub path: Option<PathBuf>,
+    pub verdict: Verdict,
+}
+
+/// Runs a pane's detectors in priority order and decides which directory
+/// the pane is in
+pub struct CwdTracker {
+    detectors: Vec<Box<dyn CwdDetector>>,
+    settle_ticks: u32,
+    /// A shallower guess being settled, and how many ticks it has been seen
+    pending: Option<(PathBuf, u32)>,
+    readings: Vec<Reading>,
+    /// The detector whose directory was followed last
+    winner: Option<&'static str>,
+}
+
+impl CwdTracker {
+    pub fn new(detectors: Vec<Box<dyn CwdDetector>>, settle_ticks: u32) -> Self {
+        Self {
+            detectors,
+            settle_ticks,
+            pending: None,
+            readings: Vec::new(),
+            winner: None,
+        }
+    }
+
+    /// The detectors a profile asks for
+    pub fn from_hints(hints: &CwdHints) -> Result<Self> {
+        let mut detectors: Vec<Box<dyn CwdDetector>> = Vec::new();
+        for name in &hints.detectors {
+            detectors.push(match name.as_str() {
+                "osc7" => Box::new(Osc7Detector),
+                "process" => Box::new(ProcessDetector),
+                "screen" => Box::new(ScreenDetector {
+                    rows: hints.screen_rows,
+                }),
+                "regex" => {
+                    let pattern = hints
+                        .pattern
+                        .as_deref()
+                        .context("the regex CWD detector needs a pattern")?;
+                    Box::new(RegexDetector {
+                        pattern: Regex::new(pattern)
+                            .with_context(|| format!("invalid CWD pattern '{pattern}'"))?,
+                        rows: hints.screen_rows,
+                    })
+                }
+                _ => bail!(
+                    "unknown CWD detector '{name}' (available: {})",
+                    DETECTORS.join(", ")
+                ),
+            });
+        }
+        Ok(Self::new(detectors, hints.settle_ticks))
+    }
+
+    /// Ask every detector where the pane is. Returns the new directory when
+    /// it moved away from `current`.
+    pub fn tick(&mut self, current: &Path, pane: &PaneState) -> Option<PathBuf> {
+        let mut moved = None;
+        self.readings.clear();
+        for detector in &self.detectors {
+            let path = detector.detect(pane);
+            let verdict = match path.as_deref() {
+                None => Verdict::Nothing,
+                Some(_) if moved.is_some() => Verdict::Skipped,
+                Some(path) if path == current => {
+                    if detector.guesses() {
+                        self.pending = None;
+                    }
+                    Verdict::Same
+                }
+                Some(path) => {
+                    let depth = path.components().count();
+                    let current_depth = current.components().count();
+                    let guesses = detector.guesses();
+                    if depth > current_depth || (depth == current_depth && !guesses) {
+                        Verdict::Followed
+                    } else if depth == current_depth || !guesses {
+                        Verdict::Ignored
+                    } else {
+                        // Shallower guess: follow it only once it has held still
+                        let seen = match self.pending.take() {
+                            Some((pending, seen)) if pending == path => seen + 1,
+                            _ => 1,
+                        };
+                        if seen >= self.settle_ticks {
+                            Verdict::Followed
+                        } else {
+                            self.pending = Some((path.to_path_buf(), seen));
+                            Verdict::Settling(seen)
+                        }
+                    }
+                }
+            };
+            if verdict == Verdict::Followed {
+                moved = path.clone();
+                self.pending = None;
+                self.winner = Some(detector.name());
+            }
+            self.readings.push(Reading {
+                detector: detector.name(),
+                path,
+                verdict,
+            });
+        }
+        moved
+    }
+
+    /// What each detector found on the last tick, highest priority first
+    pub fn readings(&self) -> &[Reading] {
+        &self.readings
+    }
+
+    pub fn winner(&self) -> Option<&'static str> {
+        self.winner
+    }
+
+    pub fn settle_ticks(&self) -> u32 {
+        self.settle_ticks
+    }
+}
+
+/// Get the current working directory of a process by PID.
+/// Uses macOS `proc_pidinfo` API or Linux `/proc/PID/cwd`.
+#[cfg(target_os = "macos")]
+pub fn process_cwd(pid: u32) -> Option<PathBuf> {
+    use std::ffi::{c_int, c_void};
+    use std::mem;
+
+    const PROC_PIDVNODEPATHINFO: c_int = 9;
+    const MAXPATHLEN: usize = 1024;
+
+    #[repr(C)]
+    struct VnodeInfoPath {
+        // struct vnode_info (see Darwin sys/proc_info.h: vnode_info is 152 bytes)
+        _vip_vi: [u8; 152],
+        vip_path: [u8; MAXPATHLEN],
+    }
+
+    #[repr(C)]
+    struct ProcVnodePathInfo {
+        pvi_cdir: VnodeInfoPath,
+        _pvi_rdir: VnodeInfoPath,
+    }
+
+    extern "C" {
+        fn proc_pidinfo(
+            pid: c_int,
+            flavor: c_int,
+            arg: u64,
+            buffer: *mut c_void,
+            buffersize: c_int,
+        ) -> c_int;
+    }
+
+    unsafe {
+        let mut info: ProcVnodePathInfo = mem::zeroed();
+        let size = mem::size_of::<ProcVnodePathInfo>() as c_int;
+
+        let ret = proc_pidinfo(
+            pid as c_int,
+            PROC_PIDVNODEPATHINFO,
+            0,
+            &mut info as *mut _ as *mut c_void,
+            size,
+        );
+
+        if ret != size {
+            return None;
+        }
+
+        let path_bytes = &info.pvi_cdir.vip_path;
+        let len = path_bytes
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(MAXPATHLEN);
+        let path_str = std::str::from_utf8(&path_bytes[..len]).ok()?;
+
+        if path_str.is_empty() {
+            None
+        } else {
+            Some(PathBuf::from(path_str))
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn process_cwd(pid: u32) -> Option<PathBuf> {
+    std::fs::read_link(format!("/proc/{}/cwd", pid)).ok()
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+pub fn process_cwd(_pid: u32) -> Option<PathBuf> {
+    None
+}
@@ -18,7 +18,51 @@ pub enum Event {
     FocusLost,
     FileChange(PathBuf),
     PtyOutput,
-    Signal,
+    /// SIGHUP, SIGTERM or SIGINT arrived; carries the signal number
+    Signal(i32),
+}
+
+/// Signals that end cltree, after being passed on to the children
+#[cfg(unix)]
+struct ShutdownSignals {
+    hangup: tokio::signal::unix::Signal,
+    terminate: tokio::signal::unix::Signal,
+    interrupt: tokio::signal::unix::Signal,
+}
+
+#[cfg(unix)]
+impl ShutdownSignals {
+    fn new() -> Self {
+        use tokio::signal::unix::{signal, SignalKind};
+        let register = |kind| signal(kind).expect("failed to register signal handler");
+        Self {
+            hangup: register(SignalKind::hangup()),
+            terminate: register(SignalKind::terminate()),
+            interrupt: register(SignalKind::interrupt()),
+        }
+    }
+
+    async fn recv(&mut self) -> i32 {
+        tokio::select! {
+            _ = self.hangup.recv() => libc::SIGHUP,
+            _ = self.terminate.recv() => libc::SIGTERM,
+            _ = self.interrupt.recv() => libc::SIGINT,
+        }
+    }
+}
+
+#[cfg(not(unix))]
+struct ShutdownSignals;
+
+#[cfg(not(unix))]
+impl ShutdownSignals {
+    fn new() -> Self {
+        Self
+    }
+
+    async fn recv(&mut self) -> i32 {
+        std::future::pending().await
+    }
 }
 
 pub struct EventHandler {
@@ -42,6 +86,7 @@ impl EventHandler {
             let mut crossterm_events = EventStream::new();
             let mut pty_rx = pty_rx;
             let mut tick_interval = tokio::time::interval(tick_rate);
+            let mut signals = ShutdownSignals::new();
 
             loop {
                 tokio::select! {
@@ -105,19 +150,9 @@ impl EventHandler {
                             break;
                         }
                     }
-                    // SIGTERM handler (Unix only)
-                    _ = async {
-                        #[cfg(unix)]
-                        {
-                            let mut sig = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()).expect("failed to register SIGTERM handler");
-                            sig.recv().await;
-                        }
-                        #[cfg(not(unix))]
-                        {
-                            std::future::pending::<()>().await;
-                        }
-                    } => {
-                        let _ = tx_clone.send(Event::Signal);
+                    // Signals that end cltree (Unix only)
+                    signal = signals.recv() => {
+                        let _ = tx_clone.send(Event::Signal(signal));
                     }
                 }
             }
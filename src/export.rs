@@ -0,0 +1,202 @@
+use ratatui::style::{Color, Modifier, Style};
+
+use crate::palette::{ansi_index, Rgb};
+use crate::vterm::{cells_text, Cell, VirtualTerminal};
+
+/// How copied terminal text is put on the clipboard
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CopyFormat {
+    #[default]
+    Plain,
+    /// Text with SGR escape sequences, for pasting into another terminal
+    Ansi,
+    /// A self-contained `<pre>` block, for pasting into issue reports
+    Html,
+}
+
+impl CopyFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "plain" | "text" => Some(Self::Plain),
+            "ansi" => Some(Self::Ansi),
+            "html" => Some(Self::Html),
+            _ => None,
+        }
+    }
+}
+
+/// Render logical lines (see [`VirtualTerminal::logical_lines`]) in `format`
+pub fn render(vt: &VirtualTerminal, lines: &[Vec<Cell>], format: CopyFormat) -> String {
+    match format {
+        CopyFormat::Plain => lines
+            .iter()
+            .map(|line| cells_text(line))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        CopyFormat::Ansi => to_ansi(vt, lines),
+        CopyFormat::Html => to_html(vt, lines),
+    }
+}
+
+/// Split a line into runs of cells sharing one (resolved) style
+fn style_runs(vt: &VirtualTerminal, line: &[Cell]) -> Vec<(Style, String)> {
+    let mut runs: Vec<(Style, String)> = Vec::new();
+    for cell in line {
+        let style = vt.resolve_style(cell.style);
+        match runs.last_mut() {
+            Some((last, text)) if *last == style => text.push_str(&cell.ch),
+            _ => runs.push((style, cell.ch.clone())),
+        }
+    }
+    runs
+}
+
+fn to_ansi(vt: &VirtualTerminal, lines: &[Vec<Cell>]) -> String {
+    let mut out = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        let mut styled = false;
+        for (style, text) in style_runs(vt, line) {
+            let params = sgr_params(style);
+            if styled || !params.is_empty() {
+                out.push_str(&format!("\x1b[0{}m", params));
+            }
+            styled = !params.is_empty();
+            out.push_str(&text);
+        }
+        // Never let a style leak past the end of a line
+        if styled {
+            out.push_str("\x1b[0m");
+        }
+    }
+    out
+}
+
+/// SGR parameters for a style, each prefixed with `;`
+fn sgr_params(style: Style) -> String {
+    const MODIFIERS: [(Modifier, u8); 8] = [
+        (Modifier::BOLD, 1),
+        (Modifier::DIM, 2),
+        (Modifier::ITALIC, 3),
+        (Modifier::UNDERLINED, 4),
+        (Modifier::SLOW_BLINK, 5),
+        (Modifier::REVERSED, 7),
+        (Modifier::HIDDEN, 8),
+        (Modifier::CROSSED_OUT, 9),
+    ];
+    let mut params = String::new();
+    for (modifier, code) in MODIFIERS {
+        if style.add_modifier.contains(modifier) {
+            params.push_str(&format!(";{code}"));
+        }
+    }
+    for (color, base) in [(style.fg, 30u8), (style.bg, 40u8)] {
+        let Some(color) = color.filter(|c| *c != Color::Reset) else {
+            continue;
+        };
+        match (color, ansi_index(color)) {
+            (_, Some(idx)) if idx < 8 => params.push_str(&format!(";{}", base + idx)),
+            (_, Some(idx)) if idx < 16 => params.push_str(&format!(";{}", base + 60 + idx - 8)),
+            (_, Some(idx)) => params.push_str(&format!(";{};5;{idx}", base + 8)),
+            (Color::Rgb(r, g, b), None) => params.push_str(&format!(";{};2;{r};{g};{b}", base + 8)),
+            _ => {}
+        }
+    }
+    params
+}
+
+pub(crate) fn css_color((r, g, b): Rgb) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+/// Inline CSS for a style, relative to the default colours of the `<pre>`.
+/// `rgb` resolves a style colour (`None` = default) for the foreground or
+/// background.
+pub(crate) fn css_style(style: Style, rgb: impl Fn(Option<Color>, bool) -> Rgb) -> String {
+    let mut fg = style.fg.filter(|c| *c != Color::Reset);
+    let mut bg = style.bg.filter(|c| *c != Color::Reset);
+    if style.add_modifier.contains(Modifier::REVERSED) {
+        // Swap through concrete colours so the defaults swap too
+        let (f, b) = (rgb(fg, true), rgb(bg, false));
+        fg = Some(Color::Rgb(b.0, b.1, b.2));
+        bg = Some(Color::Rgb(f.0, f.1, f.2));
+    }
+    let mut css = Vec::new();
+    if let Some(color) = fg {
+        css.push(format!("color:{}", css_color(rgb(Some(color), true))));
+    }
+    if let Some(color) = bg {
+        css.push(format!("background:{}", css_color(rgb(Some(color), false))));
+    }
+    if style.add_modifier.contains(Modifier::BOLD) {
+        css.push("font-weight:bold".to_string());
+    }
+    if style.add_modifier.contains(Modifier::DIM) {
+        css.push("opacity:0.6".to_string());
+    }
+    if style.add_modifier.contains(Modifier::ITALIC) {
+        css.push("font-style:italic".to_string());
+    }
+    if let Some(decoration) = text_decoration(style) {
+        css.push(format!("text-decoration:{decoration}"));
+    }
+    if style.add_modifier.contains(Modifier::HIDDEN) {
+        css.push("visibility:hidden".to_string());
+    }
+    css.join(";")
+}
+
+/// CSS/SVG `text-decoration` value for underline and strike-through
+pub(crate) fn text_decoration(style: Style) -> Option<&'static str> {
+    match (
+        style.add_modifier.contains(Modifier::UNDERLINED),
+        style.add_modifier.contains(Modifier::CROSSED_OUT),
+    ) {
+        (true, true) => Some("underline line-through"),
+        (true, false) => Some("underline"),
+        (false, true) => Some("line-through"),
+        (false, false) => None,
+    }
+}
+
+fn to_html(vt: &VirtualTerminal, lines: &[Vec<Cell>]) -> String {
+    let mut out = format!(
+        "<pre style=\"background:{};color:{};font-family:monospace\">",
+        css_color(vt.color_rgb(None, false)),
+        css_color(vt.color_rgb(None, true))
+    );
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        for (style, text) in style_runs(vt, line) {
+            let css = css_style(style, |color, fg| vt.color_rgb(color, fg));
+            if css.is_empty() {
+                out.push_str(&escape_html(&text));
+            } else {
+                out.push_str(&format!(
+                    "<span style=\"{css}\">{}</span>",
+                    escape_html(&text)
+                ));
+            }
+        }
+    }
+    out.push_str("</pre>");
+    out
+}
@@ -0,0 +1,643 @@
+use std::sync::Arc;
+
+use crate::palette::Rgb;
+
+/// Largest image side we decode, in pixels; bigger payloads are clipped
+const MAX_SIDE: usize = 4096;
+
+/// A decoded image, 8-bit RGBA, row-major
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub rgba: Vec<u8>,
+}
+
+impl Image {
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        let i = (y * self.width + x) * 4;
+        self.rgba
+            .get(i..i + 4)
+            .map_or([0; 4], |p| [p[0], p[1], p[2], p[3]])
+    }
+
+    /// Pixels covered by cell `(col, row)` of the image scaled to
+    /// `cols` x `rows` cells, as `(x range, y range)`. Never empty.
+    fn cell_box(
+        &self,
+        cols: usize,
+        rows: usize,
+        col: usize,
+        row: usize,
+    ) -> (std::ops::Range<usize>, std::ops::Range<usize>) {
+        let span = |index: usize, count: usize, size: usize| {
+            let start = (index * size / count.max(1)).min(size.saturating_sub(1));
+            let end = ((index + 1) * size / count.max(1)).clamp(start + 1, size.max(1));
+            start..end
+        };
+        (span(col, cols, self.width), span(row, rows, self.height))
+    }
+
+    /// Mean colour of the pixels in a box, blended over `background`.
+    /// `None` when the box is mostly transparent.
+    fn average(
+        &self,
+        xs: std::ops::Range<usize>,
+        ys: std::ops::Range<usize>,
+        background: Rgb,
+    ) -> Option<Rgb> {
+        let (mut sum, mut alpha, mut count) = ([0u64; 3], 0u64, 0u64);
+        for y in ys {
+            for x in xs.clone() {
+                let [r, g, b, a] = self.pixel(x, y);
+                let a = a as u64;
+                sum[0] += r as u64 * a + background.0 as u64 * (255 - a);
+                sum[1] += g as u64 * a + background.1 as u64 * (255 - a);
+                sum[2] += b as u64 * a + background.2 as u64 * (255 - a);
+                alpha += a;
+                count += 1;
+            }
+        }
+        if count == 0 || alpha < count * 128 {
+            return None;
+        }
+        let channel = |s: u64| (s / (count * 255)) as u8;
+        Some((channel(sum[0]), channel(sum[1]), channel(sum[2])))
+    }
+}
+
+/// Which protocol an image arrived through
+#[derive(Clone, Debug)]
+pub enum ImageSource {
+    /// DCS sixel, with the complete original sequence for passthrough
+    /// (empty if it was too long to keep)
+    Sixel(Arc<[u8]>),
+    /// Kitty graphics protocol, with the client's image id (0 if none)
+    Kitty(u32),
+}
+
+/// An image shown on the screen or in the scrollback
+#[derive(Clone, Debug)]
+pub struct Placement {
+    pub image: Arc<Image>,
+    pub source: ImageSource,
+    /// Buffer line of the top row: a [`crate::vterm::VirtualTerminal::line`]
+    /// index plus `evicted_lines()`, so it stays put while output scrolls
+    pub line: usize,
+    pub col: usize,
+    /// Size in cells
+    pub cols: usize,
+    pub rows: usize,
+    /// Unique per placement, for telling redraws apart
+    pub serial: u64,
+}
+
+/// How images are drawn in the terminal pane
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ImageMode {
+    /// Two pixels per cell with `▀`, foreground over background colour
+    #[default]
+    HalfBlock,
+    /// 2x4 dots per cell with braille patterns in the foreground colour
+    Braille,
+    /// Re-send sixel images to a host terminal that understands sixel
+    Sixel,
+    /// Send every image to a host terminal speaking the kitty protocol
+    Kitty,
+    /// Don't draw images
+    None,
+}
+
+impl ImageMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "halfblock" | "half-block" | "blocks" => Some(Self::HalfBlock),
+            "braille" => Some(Self::Braille),
+            "sixel" => Some(Self::Sixel),
+            "kitty" => Some(Self::Kitty),
+            "none" | "off" => Some(Self::None),
+            _ => None,
+        }
+    }
+
+    /// Whether `placement` is handed to the host terminal rather than
+    /// approximated with text
+    pub fn passes_through(self, placement: &Placement) -> bool {
+        match self {
+            Self::Kitty => true,
+            Self::Sixel => matches!(&placement.source, ImageSource::Sixel(raw) if !raw.is_empty()),
+            _ => false,
+        }
+    }
+}
+
+/// Top and bottom colour of a `▀` cell; `None` halves are transparent
+pub fn half_block(
+    image: &Image,
+    cols: usize,
+    rows: usize,
+    col: usize,
+    row: usize,
+    background: Rgb,
+) -> (Option<Rgb>, Option<Rgb>) {
+    let (xs, ys) = image.cell_box(cols, rows, col, row);
+    let mid = ys.start + (ys.len() / 2).max(1).min(ys.len());
+    let top = image.average(xs.clone(), ys.start..mid, background);
+    let bottom = if mid < ys.end {
+        image.average(xs, mid..ys.end, background)
+    } else {
+        top
+    };
+    (top, bottom)
+}
+
+/// Braille pattern and dot colour for a cell. A dot is raised where the
+/// image stands out from `background`; `None` when no dot is.
+pub fn braille(
+    image: &Image,
+    cols: usize,
+    rows: usize,
+    col: usize,
+    row: usize,
+    background: Rgb,
+) -> Option<(char, Rgb)> {
+    // Dot bit for sub-cell (x, y), per the Unicode braille layout
+    const BITS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+    let (xs, ys) = image.cell_box(cols, rows, col, row);
+    let luma = |(r, g, b): Rgb| (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000;
+    let background_luma = luma(background);
+
+    let (mut bits, mut sum, mut dots) = (0u32, [0u32; 3], 0u32);
+    for (dy, bit_row) in BITS.iter().enumerate() {
+        for (dx, bit) in bit_row.iter().enumerate() {
+            let sub = |range: &std::ops::Range<usize>, i: usize, n: usize| {
+                let start = range.start + i * range.len() / n;
+                start..(range.start + (i + 1) * range.len() / n).max(start + 1)
+            };
+            let Some(color) = image.average(sub(&xs, dx, 2), sub(&ys, dy, 4), background) else {
+                continue;
+            };
+            if luma(color).abs_diff(background_luma) > 40 {
+                bits |= bit;
+                sum[0] += color.0 as u32;
+                sum[1] += color.1 as u32;
+                sum[2] += color.2 as u32;
+                dots += 1;
+            }
+        }
+    }
+    let ch = char::from_u32(0x2800 + bits)?;
+    (dots > 0).then(|| {
+        (
+            ch,
+            (
+                (sum[0] / dots) as u8,
+                (sum[1] / dots) as u8,
+                (sum[2] / dots) as u8,
+            ),
+        )
+    })
+}
+
+/// Cells needed for `image` at the given cell size in pixels
+pub fn cell_extent(image: &Image, (cell_width, cell_height): (usize, usize)) -> (usize, usize) {
+    (
+        image.width.div_ceil(cell_width.max(1)).max(1),
+        image.height.div_ceil(cell_height.max(1)).max(1),
+    )
+}
+
+/// Escape sequence that draws `placement` at the host cursor with the
+/// host's own image support: the original sixel, or a kitty transmission
+/// scaled to the placement's cells.
+pub fn passthrough_sequence(placement: &Placement, mode: ImageMode) -> Vec<u8> {
+    match (&placement.source, mode) {
+        (ImageSource::Sixel(raw), ImageMode::Sixel) => raw.to_vec(),
+        _ => {
+            let image = &placement.image;
+            let data = base64_encode(&image.rgba);
+            let mut out = Vec::with_capacity(data.len() + data.len() / 4096 * 16 + 64);
+            let mut chunks = data.as_bytes().chunks(4096).peekable();
+            let mut first = true;
+            while let Some(chunk) = chunks.next() {
+                let more = u8::from(chunks.peek().is_some());
+                if first {
+                    out.extend_from_slice(
+                        format!(
+                            "\x1b_Ga=T,f=32,s={},v={},c={},r={},C=1,q=2,m={more};",
+                            image.width, image.height, placement.cols, placement.rows
+                        )
+                        .as_bytes(),
+                    );
+                    first = false;
+                } else {
+                    out.extend_from_slice(format!("\x1b_Gm={more};").as_bytes());
+                }
+                out.extend_from_slice(chunk);
+                out.extend_from_slice(b"\x1b\\");
+            }
+            out
+        }
+    }
+}
+
+fn base64_encode(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+// ---------------------------------------------------------------------------
+// Sixel
+
+/// Sixel control function whose numeric parameters are being read
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SixelCommand {
+    None,
+    /// `"` Pan ; Pad ; Ph ; Pv
+    Raster,
+    /// `#` Pc [; Pu ; Px ; Py ; Pz]
+    Color,
+    /// `!` Pn
+    Repeat,
+}
+
+/// Incremental decoder for the data of a sixel DCS (`ESC P ... q`)
+pub struct SixelDecoder {
+    palette: Vec<Rgb>,
+    color: usize,
+    command: SixelCommand,
+    params: Vec<u32>,
+    repeat: usize,
+    x: usize,
+    y: usize,
+    /// Size declared by raster attributes
+    declared: (usize, usize),
+    /// Unset pixels stay transparent (P2 = 1) rather than colour 0
+    transparent: bool,
+    rows: Vec<Vec<Option<Rgb>>>,
+}
+
+impl SixelDecoder {
+    /// Start decoding; `params` are the DCS parameters (P1 ; P2 ; P3)
+    pub fn new(params: &[u16]) -> Self {
+        Self {
+            palette: default_sixel_palette(),
+            color: 0,
+            command: SixelCommand::None,
+            params: Vec::new(),
+            repeat: 1,
+            x: 0,
+            y: 0,
+            declared: (0, 0),
+            transparent: params.get(1) == Some(&1),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn put(&mut self, byte: u8) {
+        if self.command != SixelCommand::None {
+            match byte {
+                b'0'..=b'9' => {
+                    if let Some(last) = self.params.last_mut() {
+                        *last = last.saturating_mul(10).saturating_add((byte - b'0') as u32);
+                    }
+                    return;
+                }
+                b';' => {
+                    self.params.push(0);
+                    return;
+                }
+                _ => self.end_command(),
+            }
+        }
+
+        match byte {
+            b'"' => self.start_command(SixelCommand::Raster),
+            b'#' => self.start_command(SixelCommand::Color),
+            b'!' => self.start_command(SixelCommand::Repeat),
+            // Graphics carriage return
+            b'$' => self.x = 0,
+            // Graphics new line: next band of six pixel rows
+            b'-' => {
+                self.x = 0;
+                self.y += 6;
+            }
+            b'?'..=b'~' => {
+                let bits = byte - b'?';
+                let count = std::mem::replace(&mut self.repeat, 1);
+                let color = self.palette[self.color];
+                for dy in 0..6 {
+                    if bits & (1 << dy) != 0 {
+                        self.set_run(self.y + dy, self.x, count, color);
+                    }
+                }
+                self.x = self.x.saturating_add(count);
+            }
+            _ => {}
+        }
+    }
+
+    fn start_command(&mut self, command: SixelCommand) {
+        self.command = command;
+        self.params.clear();
+        self.params.push(0);
+    }
+
+    fn end_command(&mut self) {
+        let p = std::mem::take(&mut self.params);
+        match self.command {
+            SixelCommand::Raster => {
+                let width = p.get(2).copied().unwrap_or(0) as usize;
+                let height = p.get(3).copied().unwrap_or(0) as usize;
+                self.declared = (width.min(MAX_SIDE), height.min(MAX_SIDE));
+            }
+            SixelCommand::Color => {
+                let index = p[0] as usize % self.palette.len();
+                if let [_, space, a, b, c, ..] = p[..] {
+                    match space {
+                        1 => self.palette[index] = hls_to_rgb(a, b, c),
+                        2 => self.palette[index] = (percent(a), percent(b), percent(c)),
+                        _ => {}
+                    }
+                }
+                self.color = index;
+            }
+            SixelCommand::Repeat => self.repeat = (p[0] as usize).clamp(1, MAX_SIDE),
+            SixelCommand::None => {}
+        }
+        self.command = SixelCommand::None;
+    }
+
+    fn set_run(&mut self, y: usize, x: usize, count: usize, color: Rgb) {
+        if y >= MAX_SIDE || x >= MAX_SIDE {
+            return;
+        }
+        if self.rows.len() <= y {
+            self.rows.resize(y + 1, Vec::new());
+        }
+        let row = &mut self.rows[y];
+        let end = (x + count).min(MAX_SIDE);
+        if row.len() < end {
+            row.resize(end, None);
+        }
+        row[x..end].fill(Some(color));
+    }
+
+    /// The decoded image, or `None` if nothing was drawn or declared
+    pub fn finish(mut self) -> Option<Image> {
+        self.end_command();
+        let width = self
+            .rows
+            .iter()
+            .map(Vec::len)
+            .max()
+            .unwrap_or(0)
+            .max(self.declared.0);
+        let height = self.rows.len().max(self.declared.1);
+        if width == 0 || height == 0 {
+            return None;
+        }
+        let fill = if self.transparent {
+            [0; 4]
+        } else {
+            let (r, g, b) = self.palette[0];
+            [r, g, b, 255]
+        };
+        let mut rgba = Vec::with_capacity(width * height * 4);
+        for y in 0..height {
+            let row = self.rows.get(y).map(Vec::as_slice).unwrap_or_default();
+            for x in 0..width {
+                match row.get(x).copied().flatten() {
+                    Some((r, g, b)) => rgba.extend_from_slice(&[r, g, b, 255]),
+                    None => rgba.extend_from_slice(&fill),
+                }
+            }
+        }
+        Some(Image {
+            width,
+            height,
+            rgba,
+        })
+    }
+}
+
+fn percent(value: u32) -> u8 {
+    (value.min(100) * 255 / 100) as u8
+}
+
+/// The VT340's default 16 colours, then black
+fn default_sixel_palette() -> Vec<Rgb> {
+    const VT340: [(u32, u32, u32); 16] = [
+        (0, 0, 0),
+        (20, 20, 80),
+        (80, 13, 13),
+        (20, 80, 20),
+        (80, 20, 80),
+        (20, 80, 80),
+        (80, 80, 20),
+        (53, 53, 53),
+        (26, 26, 26),
+        (33, 33, 60),
+        (60, 26, 26),
+        (33, 60, 33),
+        (60, 33, 60),
+        (33, 60, 60),
+        (60, 60, 33),
+        (80, 80, 80),
+    ];
+    let mut palette: Vec<Rgb> = VT340
+        .iter()
+        .map(|&(r, g, b)| (percent(r), percent(g), percent(b)))
+        .collect();
+    palette.resize(256, (0, 0, 0));
+    palette
+}
+
+/// Sixel HLS (hue 0-360 with blue at 0, lightness and saturation 0-100)
+fn hls_to_rgb(hue: u32, lightness: u32, saturation: u32) -> Rgb {
+    let h = ((hue + 240) % 360) as f64 / 360.0;
+    let l = lightness.min(100) as f64 / 100.0;
+    let s = saturation.min(100) as f64 / 100.0;
+    if s == 0.0 {
+        let v = (l * 255.0).round() as u8;
+        return (v, v, v);
+    }
+    let q = if l < 0.5 {
+        l * (1.0 + s)
+    } else {
+        l + s - l * s
+    };
+    let p = 2.0 * l - q;
+    let channel = |t: f64| {
+        let t = t.rem_euclid(1.0);
+        let v = if t < 1.0 / 6.0 {
+            p + (q - p) * 6.0 * t
+        } else if t < 0.5 {
+            q
+        } else if t < 2.0 / 3.0 {
+            p + (q - p) * (2.0 / 3.0 - t) * 6.0
+        } else {
+            p
+        };
+        (v * 255.0).round() as u8
+    };
+    (channel(h + 1.0 / 3.0), channel(h), channel(h - 1.0 / 3.0))
+}
+
+// ---------------------------------------------------------------------------
+// Kitty graphics protocol
+
+/// The control keys of a kitty graphics command (`ESC _ G <keys> ; <data> ESC \`)
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct KittyCommand {
+    /// `a`: t(ransmit), T (transmit and display), p(ut), q(uery), d(elete)
+    pub action: u8,
+    /// `f`: 24 (RGB), 32 (RGBA) or 100 (PNG)
+    pub format: u32,
+    /// `t`: transmission medium; only d(irect) is supported
+    pub medium: u8,
+    /// `o`: compression (z = zlib, unsupported)
+    pub compression: Option<u8>,
+    /// `s`, `v`: pixel size of raw data
+    pub width: usize,
+    pub height: usize,
+    /// `i`: client image id
+    pub id: u32,
+    /// `m=1`: more chunks follow
+    pub more: bool,
+    /// `q`: 1 suppresses OK replies, 2 also errors
+    pub quiet: u8,
+    /// `c`, `r`: display size in cells (0 = from the pixel size)
+    pub cols: usize,
+    pub rows: usize,
+    /// `C=1`: leave the cursor where it is
+    pub hold_cursor: bool,
+    /// `d`: what to delete
+    pub delete: u8,
+}
+
+impl KittyCommand {
+    /// Parse the control data (the part between `G` and `;`)
+    pub fn parse(control: &[u8]) -> Self {
+        let mut cmd = Self {
+            action: b't',
+            format: 32,
+            medium: b'd',
+            delete: b'a',
+            ..Self::default()
+        };
+        for pair in control.split(|&b| b == b',') {
+            let [key, b'=', value @ ..] = pair else {
+                continue;
+            };
+            let number = || {
+                std::str::from_utf8(value)
+                    .ok()
+                    .and_then(|v| v.parse::<u32>().ok())
+                    .unwrap_or(0)
+            };
+            let letter = value.first().copied().unwrap_or(0);
+            match key {
+                b'a' => cmd.action = letter,
+                b'f' => cmd.format = number(),
+                b't' => cmd.medium = letter,
+                b'o' => cmd.compression = Some(letter),
+                b's' => cmd.width = number() as usize,
+                b'v' => cmd.height = number() as usize,
+                b'i' => cmd.id = number(),
+                b'm' => cmd.more = number() == 1,
+                b'q' => cmd.quiet = number() as u8,
+                b'c' => cmd.cols = number() as usize,
+                b'r' => cmd.rows = number() as usize,
+                b'C' => cmd.hold_cursor = number() == 1,
+                b'd' => cmd.delete = letter,
+                _ => {}
+            }
+        }
+        cmd
+    }
+
+    /// Build the image from the decoded payload, or an error code and
+    /// message for the reply
+    pub fn decode(&self, data: &[u8]) -> Result<Image, String> {
+        if self.medium != b'd' {
+            return Err("EINVAL:only direct transmission is supported".to_string());
+        }
+        if self.compression.is_some() {
+            return Err("EINVAL:compression is not supported".to_string());
+        }
+        match self.format {
+            24 | 32 => {
+                let channels = self.format as usize / 8;
+                let (width, height) = (self.width, self.height);
+                if width == 0 || height == 0 || width > MAX_SIDE || height > MAX_SIDE {
+                    return Err("EINVAL:bad image size".to_string());
+                }
+                if data.len() < width * height * channels {
+                    return Err("ENODATA:insufficient image data".to_string());
+                }
+                let rgba = if channels == 4 {
+                    data[..width * height * 4].to_vec()
+                } else {
+                    data.chunks_exact(3)
+                        .take(width * height)
+                        .flat_map(|p| [p[0], p[1], p[2], 255])
+                        .collect()
+                };
+                Ok(Image {
+                    width,
+                    height,
+                    rgba,
+                })
+            }
+            100 => decode_png(data).map_err(|e| format!("EBADPNG:{e}")),
+            _ => Err("EINVAL:unknown format".to_string()),
+        }
+    }
+}
+
+fn decode_png(data: &[u8]) -> Result<Image, png::DecodingError> {
+    let mut decoder = png::Decoder::new(data);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+    let (width, height) = (info.width as usize, info.height as usize);
+    if width > MAX_SIDE || height > MAX_SIDE {
+        return Err(png::DecodingError::LimitsExceeded);
+    }
+    let pixels = &buf[..info.buffer_size()];
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => pixels.to_vec(),
+        png::ColorType::Rgb => pixels
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => pixels
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        _ => pixels.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+    };
+    Ok(Image {
+        width,
+        height,
+        rgba,
+    })
+}
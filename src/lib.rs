@@ -1 +1,14 @@
+pub mod asciicast;
+pub mod copy_mode;
+pub mod cwd;
+pub mod export;
+pub mod graphics;
+pub mod palette;
+pub mod process;
+pub mod profile;
+pub mod screenshot;
+pub mod search;
+pub mod snapshot;
+pub mod transcript;
+pub mod unicode;
 pub mod vterm;
@@ -1,12 +1,29 @@
 mod app;
+mod asciicast;
+mod bell;
+mod copy_mode;
+mod cwd;
 mod event;
+mod export;
+mod graphics;
+mod palette;
+mod process;
+mod profile;
+mod replay;
+mod screenshot;
+mod search;
+mod session;
+mod tabs;
 mod terminal;
+mod transcript;
 mod tree;
 mod ui;
+mod unicode;
 pub mod vterm;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use crossterm::{
+    cursor::SetCursorStyle,
     event::{
         DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste,
         EnableFocusChange, EnableMouseCapture,
@@ -18,33 +35,61 @@ use ratatui::prelude::*;
 use std::io;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
-use app::App;
+use app::{App, KeyBinding, Options};
+use bell::BellMode;
 use event::EventHandler;
-
-struct Args {
-    path: PathBuf,
-    tree_width: u16,
-    show_hidden: bool,
-    depth: usize,
-    claude_args: Vec<String>,
-}
+use export::CopyFormat;
+use graphics::ImageMode;
+use profile::Profiles;
+use screenshot::ScreenshotFormat;
+use terminal::TerminalSettings;
+use unicode::AmbiguousWidth;
 
 /// cltree 자체 플래그만 꺼내고, 나머지는 모두 Claude Code CLI로 전달
-fn parse_args() -> Args {
+fn parse_args() -> Result<Options> {
     let raw: Vec<String> = std::env::args().skip(1).collect();
 
     let mut path = PathBuf::from(".");
     let mut tree_width: u16 = 30;
     let mut show_hidden = false;
     let mut depth: usize = 10;
-    let mut claude_args = Vec::new();
+    let mut ambiguous_width = AmbiguousWidth::default();
+    let mut bell_mode = BellMode::default();
+    let mut notify_command: Option<String> = None;
+    let mut copy_format = CopyFormat::default();
+    let mut record: Option<PathBuf> = None;
+    let mut screenshot: Option<PathBuf> = None;
+    let mut screenshot_delay = Duration::from_secs(3);
+    let mut transcript: Option<PathBuf> = None;
+    let mut transcript_sections = false;
+    let mut images = ImageMode::default();
+    let mut split_command: Option<String> = None;
+    let mut suspend_key = Some(KeyBinding::default());
+    let mut suspend_children = false;
+    let mut prefix_key = Some(KeyBinding::ctrl('x'));
+    let mut profile_name = "claude".to_string();
+    let mut command_args = Vec::new();
 
-    // Known flags that take a value
-    let value_flags: &[&[&str]] = &[
-        &["-p", "--path"],
-        &["-w", "--tree-width"],
-        &["-d", "--depth"],
+    // Known flags that take a value: short name (if any) and long name
+    let value_flags: &[(Option<&str>, &str)] = &[
+        (Some("-p"), "--path"),
+        (Some("-w"), "--tree-width"),
+        (Some("-d"), "--depth"),
+        (None, "--ambiguous-width"),
+        (None, "--bell"),
+        (None, "--notify-command"),
+        (None, "--copy-format"),
+        (None, "--record"),
+        (None, "--screenshot"),
+        (None, "--screenshot-delay"),
+        (None, "--transcript"),
+        (None, "--images"),
+        (None, "--split-command"),
+        (None, "--suspend-key"),
+        (None, "--prefix-key"),
+        (None, "--profile"),
     ];
 
     let mut i = 0;
@@ -55,15 +100,39 @@ fn parse_args() -> Args {
         if arg == "-h" || arg == "--help" {
             eprintln!(
                 "A TUI file explorer for Claude Code CLI\n\n\
-                 Usage: cltree [OPTIONS] [CLAUDE_ARGS...]\n\n\
+                 Usage: cltree [OPTIONS] [ARGS...]\n\n\
                  Options:\n\
                  \x20 -p, --path <PATH>         Working directory [default: .]\n\
                  \x20 -w, --tree-width <WIDTH>   Tree panel width %% (10-50) [default: 30]\n\
                  \x20 -a, --show-hidden          Show hidden files\n\
                  \x20 -d, --depth <DEPTH>        Max tree depth [default: 10]\n\
+                 \x20     --ambiguous-width <W>  Width of East Asian ambiguous chars: narrow|wide [default: narrow]\n\
+                 \x20     --bell <MODE>          Bell style: both|visual|audible|none [default: both]\n\
+                 \x20     --notify-command <CMD> Shell command for desktop notifications ($1=title, $2=body)\n\
+                 \x20     --copy-format <FMT>    Clipboard format for copied text: plain|ansi|html [default: plain]\n\
+                 \x20     --record <FILE>        Record the Claude Code session as an asciicast v2 file\n\
+                 \x20     --screenshot <FILE>    Save the screen as SVG (or HTML for .html) after a delay, then quit\n\
+                 \x20     --screenshot-delay <S> Seconds to wait before --screenshot [default: 3]\n\
+                 \x20     --transcript <FILE>    Save the scrollback as Markdown (or text for .txt) on exit\n\
+                 \x20     --transcript-sections  Start a transcript section at every shell prompt (OSC 133)\n\
+                 \x20     --images <MODE>        Sixel/kitty images: halfblock|braille|sixel|kitty|none [default: halfblock]\n\
+                 \x20     --split-command <CMD>  Command for split panes (Ctrl+X % or \") [default: $SHELL]\n\
+                 \x20     --suspend-key <KEY>    Key that suspends cltree, e.g. ctrl+z, alt+s, f12, none [default: ctrl+z]\n\
+                 \x20     --suspend-children     Stop Claude too while cltree is suspended\n\
+                 \x20     --prefix-key <KEY>     Key before cltree commands (tabs, splits, search...), or none\n\
+                 \x20                            to send every key to Claude [default: ctrl+x]\n\
+                 \x20     --detach               Start in a background session instead of this terminal\n\
+                 \x20     --session <NAME>       Name for the --detach session [default: first free number]\n\
+                 \x20     --profile <NAME>       Launch profile: claude|aider|codex|shell or one from\n\
+                 \x20                            ~/.config/cltree/profiles.json [default: claude]\n\
                  \x20 -h, --help                 Print help\n\
                  \x20 -V, --version              Print version\n\n\
-                 All other arguments are passed through to Claude Code CLI.\n\
+                 Replay a recording:\n\
+                 \x20 cltree replay <FILE> [--speed <N>] [--ambiguous-width <W>]\n\n\
+                 Sessions (Ctrl+\\ detaches):\n\
+                 \x20 cltree attach [NAME]\n\
+                 \x20 cltree ls\n\n\
+                 All other arguments are passed through to Claude Code CLI (or the profile's command).\n\
                  Example: cltree --resume\n\
                  Example: cltree -p /my/project --continue"
             );
@@ -75,45 +144,56 @@ fn parse_args() -> Args {
         }
 
         // Check if it's one of our value flags (e.g. -p /path or --path=/path)
-        let mut matched_value_flag = false;
-        for names in value_flags {
-            // Handle --flag=value form
-            for name in *names {
-                if let Some(val) = arg.strip_prefix(&format!("{name}=")) {
-                    match *name {
-                        "-p" | "--path" => path = PathBuf::from(val),
-                        "-w" | "--tree-width" => tree_width = val.parse().unwrap_or(30),
-                        "-d" | "--depth" => depth = val.parse().unwrap_or(10),
-                        _ => {}
-                    }
-                    matched_value_flag = true;
-                    break;
+        let matched = value_flags.iter().find_map(|&(short, long)| {
+            [short, Some(long)].into_iter().flatten().find_map(|name| {
+                if arg == name {
+                    Some((long, None))
+                } else {
+                    let val = arg.strip_prefix(name)?.strip_prefix('=')?;
+                    Some((long, Some(val.to_string())))
                 }
-            }
-            if matched_value_flag {
-                break;
-            }
-
-            // Handle --flag value form
-            if names.contains(&arg.as_str()) {
-                let val = raw.get(i + 1).cloned().unwrap_or_default();
-                match names[1] {
-                    "--path" => path = PathBuf::from(&val),
-                    "--tree-width" => tree_width = val.parse().unwrap_or(30),
-                    "--depth" => depth = val.parse().unwrap_or(10),
-                    _ => {}
+            })
+        });
+        if let Some((flag, inline_val)) = matched {
+            let val = match inline_val {
+                Some(val) => {
+                    i += 1;
+                    val
                 }
-                i += 2;
-                matched_value_flag = true;
-                break;
-            }
-        }
-
-        if matched_value_flag {
-            if !arg.contains('=') {
-                continue; // already incremented i by 2
+                None => {
+                    i += 2;
+                    raw.get(i - 1).cloned().unwrap_or_default()
+                }
+            };
+            let invalid = |expected: &str| format!("invalid {flag} '{val}' (expected {expected})");
+            match flag {
+                "--path" => path = PathBuf::from(&val),
+                "--tree-width" => tree_width = val.parse().unwrap_or(30),
+                "--depth" => depth = val.parse().unwrap_or(10),
+                "--ambiguous-width" => ambiguous_width = parse_ambiguous_width(&val)?,
+                "--bell" => {
+                    bell_mode = BellMode::parse(&val)
+                        .with_context(|| invalid("both|visual|audible|none"))?
+                }
+                "--notify-command" => notify_command = Some(val),
+                "--copy-format" => {
+                    copy_format =
+                        CopyFormat::parse(&val).with_context(|| invalid("plain|ansi|html"))?
+                }
+                "--record" => record = Some(PathBuf::from(&val)),
+                "--screenshot" => screenshot = Some(PathBuf::from(&val)),
+                "--screenshot-delay" => screenshot_delay = parse_screenshot_delay(&val)?,
+                "--transcript" => transcript = Some(PathBuf::from(&val)),
+                "--images" => {
+                    images = ImageMode::parse(&val)
+                        .with_context(|| invalid("halfblock|braille|sixel|kitty|none"))?
+                }
+                "--split-command" => split_command = Some(val),
+                "--suspend-key" => suspend_key = parse_key_option(flag, &val)?,
+                "--prefix-key" => prefix_key = parse_key_option(flag, &val)?,
+                "--profile" => profile_name = val,
+                _ => {}
             }
-            i += 1;
             continue;
         }
 
@@ -123,22 +203,80 @@ fn parse_args() -> Args {
             i += 1;
             continue;
         }
+        if arg == "--transcript-sections" {
+            transcript_sections = true;
+            i += 1;
+            continue;
+        }
+        if arg == "--suspend-children" {
+            suspend_children = true;
+            i += 1;
+            continue;
+        }
 
         // Everything else goes to Claude Code
-        claude_args.push(arg.clone());
+        command_args.push(arg.clone());
         i += 1;
     }
 
-    Args {
+    let mut profiles = Profiles::load()?;
+    // CLTREE_COMMAND env var overrides the command Claude is started with
+    if let Ok(command) = std::env::var("CLTREE_COMMAND") {
+        profiles.override_claude_command(&command);
+    }
+    let profile = profiles.get(&profile_name)?;
+
+    Ok(Options {
         path,
         tree_width,
         show_hidden,
         depth,
-        claude_args,
+        terminal: TerminalSettings {
+            ambiguous_width,
+            record,
+            images,
+            cell_size: host_cell_size(),
+            ..Default::default()
+        },
+        bell_mode,
+        notify_command,
+        copy_format,
+        screenshot,
+        screenshot_delay,
+        transcript,
+        transcript_sections,
+        split_command,
+        suspend_key,
+        suspend_children,
+        prefix_key,
+        profile,
+        command_args,
+    })
+}
+
+/// A key option's value: `none` turns the key off
+fn parse_key_option(flag: &str, value: &str) -> Result<Option<KeyBinding>> {
+    if value.eq_ignore_ascii_case("none") {
+        return Ok(None);
     }
+    KeyBinding::parse(value).map(Some).with_context(|| {
+        format!("invalid {flag} '{value}' (expected e.g. ctrl+x, alt+s, f12 or none)")
+    })
 }
 
-/// Restore terminal state (raw mode, alternate screen, mouse capture).
+/// Pixel size of a host terminal cell, if the host reports its window size
+fn host_cell_size() -> Option<(usize, usize)> {
+    let size = crossterm::terminal::window_size().ok()?;
+    if size.width == 0 || size.height == 0 || size.columns == 0 || size.rows == 0 {
+        return None;
+    }
+    Some((
+        (size.width / size.columns) as usize,
+        (size.height / size.rows) as usize,
+    ))
+}
+
+/// Restore terminal state (raw mode, alternate screen, mouse capture, cursor style).
 /// Safe to call multiple times.
 fn restore_terminal() {
     let _ = disable_raw_mode();
@@ -147,16 +285,122 @@ fn restore_terminal() {
         LeaveAlternateScreen,
         DisableMouseCapture,
         DisableFocusChange,
-        DisableBracketedPaste
+        DisableBracketedPaste,
+        SetCursorStyle::DefaultUserShape
     );
 }
 
 /// Whether we have already entered raw/alternate-screen mode.
 static TERMINAL_INITIALIZED: AtomicBool = AtomicBool::new(false);
 
+/// With `--detach`, the session name from `--session` and the arguments
+/// for the cltree the server runs
+fn detach_args(args: &[String]) -> Result<Option<(Option<String>, Vec<String>)>> {
+    let mut detach = false;
+    let mut name = None;
+    let mut rest = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--detach" {
+            detach = true;
+        } else if arg == "--session" {
+            name = args.next().cloned();
+        } else if let Some(value) = arg.strip_prefix("--session=") {
+            name = Some(value.to_string());
+        } else {
+            rest.push(arg.clone());
+        }
+    }
+    match (detach, name) {
+        (true, name) => Ok(Some((name, rest))),
+        (false, Some(_)) => anyhow::bail!("--session only names a --detach session"),
+        (false, None) => Ok(None),
+    }
+}
+
+/// `cltree replay <FILE> [--speed <N>] [--ambiguous-width <W>]`
+fn replay_main(args: &[String]) -> Result<()> {
+    let mut path = None;
+    let mut speed = 1.0;
+    let mut ambiguous_width = AmbiguousWidth::default();
+    let mut i = 0;
+    while i < args.len() {
+        let arg = &args[i];
+        if let Some(val) = arg.strip_prefix("--speed=") {
+            speed = parse_speed(val)?;
+        } else if arg == "--speed" {
+            speed = parse_speed(args.get(i + 1).map_or("", String::as_str))?;
+            i += 1;
+        } else if let Some(val) = arg.strip_prefix("--ambiguous-width=") {
+            ambiguous_width = parse_ambiguous_width(val)?;
+        } else if arg == "--ambiguous-width" {
+            ambiguous_width = parse_ambiguous_width(args.get(i + 1).map_or("", String::as_str))?;
+            i += 1;
+        } else {
+            path = Some(PathBuf::from(arg));
+        }
+        i += 1;
+    }
+    let Some(path) = path else {
+        anyhow::bail!("usage: cltree replay <FILE> [--speed <N>] [--ambiguous-width <W>]");
+    };
+    // Load before touching the terminal so errors print normally
+    let cast = asciicast::Cast::load(&path)?;
+    let title = path.file_name().map_or_else(
+        || path.display().to_string(),
+        |n| n.to_string_lossy().into_owned(),
+    );
+
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    TERMINAL_INITIALIZED.store(true, Ordering::SeqCst);
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let result = replay::run(&mut terminal, cast, &title, speed, ambiguous_width);
+
+    restore_terminal();
+    terminal.show_cursor()?;
+    result
+}
+
+fn parse_ambiguous_width(val: &str) -> Result<AmbiguousWidth> {
+    AmbiguousWidth::parse(val)
+        .with_context(|| format!("invalid --ambiguous-width '{val}' (expected narrow|wide)"))
+}
+
+/// A finite number given for `flag`
+fn parse_number(flag: &str, val: &str) -> Result<f64> {
+    match val.parse::<f64>() {
+        Ok(n) if n.is_finite() => Ok(n),
+        _ => anyhow::bail!("invalid {flag} '{val}' (expected a number)"),
+    }
+}
+
+fn parse_speed(val: &str) -> Result<f64> {
+    let speed = parse_number("--speed", val)?;
+    if speed <= 0.0 {
+        anyhow::bail!("invalid --speed '{val}' (expected a number above 0)");
+    }
+    Ok(speed)
+}
+
+fn parse_screenshot_delay(val: &str) -> Result<Duration> {
+    let secs = parse_number("--screenshot-delay", val)?;
+    Duration::try_from_secs_f64(secs)
+        .ok()
+        .filter(|delay| Instant::now().checked_add(*delay).is_some())
+        .with_context(|| {
+            format!("invalid --screenshot-delay '{val}' (expected seconds, 0 or more)")
+        })
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args = parse_args();
+    let replay_args: Option<Vec<String>> = {
+        let mut args = std::env::args().skip(1);
+        (args.next().as_deref() == Some("replay")).then(|| args.collect())
+    };
 
     // Install panic hook to restore terminal state on panic.
     // Without this, a panic leaves the terminal in raw mode (unusable).
@@ -168,8 +412,31 @@ async fn main() -> Result<()> {
         original_hook(info);
     }));
 
+    if let Some(args) = replay_args {
+        return replay_main(&args);
+    }
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("attach") => return session::attach(args.get(1).cloned()).await,
+        Some("ls") => return session::list(),
+        Some("__server") if args.len() >= 2 => {
+            return session::serve(args[1].clone(), args[2..].to_vec()).await
+        }
+        _ => {}
+    }
+    if let Some((name, rest)) = detach_args(&args)? {
+        // Check the options here, where errors can still be seen
+        parse_args()?;
+        return session::start(name, rest);
+    }
+    let mut options = parse_args()?;
+
     // Setup terminal
     enable_raw_mode()?;
+    // Ask the host for its colours before the event stream starts reading stdin,
+    // so the child's OSC 4/10/11 queries get the real answer
+    let (palette, typed_ahead) = palette::query_host_palette(Duration::from_millis(200));
+    options.terminal.palette = palette;
     let mut stdout = io::stdout();
     execute!(
         stdout,
@@ -186,14 +453,9 @@ async fn main() -> Result<()> {
     let (pty_tx, pty_rx) = tokio::sync::mpsc::unbounded_channel();
 
     // Create app state
-    let mut app = App::new(
-        args.path,
-        args.tree_width,
-        args.show_hidden,
-        args.depth,
-        args.claude_args,
-        pty_tx,
-    )?;
+    let mut app = App::new(options, pty_tx)?;
+    // Keys typed while we waited for the host's colours were meant for Claude
+    app.terminal_mut().send_input(&typed_ahead);
 
     // Create event handler with file watching enabled for the tree root
     let watch_path = Some(app.tree.root_path().to_path_buf());
@@ -201,38 +463,180 @@ async fn main() -> Result<()> {
 
     // Run the app
     let result = run_app(&mut terminal, &mut app, event_handler).await;
+    let signal = result.as_ref().ok().copied().flatten();
+    if let Some(signal) = signal {
+        stop_children(&app, signal).await;
+    }
 
-    // Restore terminal
+    // Restore terminal (after SIGHUP it may be gone, so errors are ignored)
     restore_terminal();
-    terminal.show_cursor()?;
+    let _ = terminal.show_cursor();
+
+    match app.save_transcript_on_exit() {
+        Ok(Some(path)) => eprintln!("Transcript saved to {}", path.display()),
+        Ok(None) => {}
+        Err(err) => eprintln!("Error: {err:#}"),
+    }
 
     if let Err(err) = result {
         eprintln!("Error: {err:?}");
         std::process::exit(1);
     }
 
+    // Pass on how Claude ended, so scripts and CI wrapping cltree can rely on it
+    let session = app.primary_session();
+    let exit = session.exit_status();
+    if let Some(exit) = exit.filter(|exit| !exit.success()) {
+        eprintln!("{} {exit}", session.profile().title);
+    }
+    let code = match (exit, signal) {
+        (Some(exit), _) => exit.code(),
+        (None, Some(signal)) => 128 + signal,
+        (None, None) => 0,
+    };
+    if code != 0 {
+        std::process::exit(code);
+    }
+
     Ok(())
 }
 
+/// Put cltree in the background like any job stopped with Ctrl+Z: hand the
+/// terminal back, stop ourselves with SIGTSTP, and take the screen over
+/// again once continued. Claude keeps running in its PTY unless
+/// `--suspend-children` asked for it to be stopped as well.
+#[cfg(unix)]
+fn suspend(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App) -> Result<()> {
+    restore_terminal();
+    terminal.show_cursor()?;
+    if app.suspend_children {
+        // SIGSTOP, as TSTP to a group without job control would be discarded
+        app.signal_children(libc::SIGSTOP);
+    }
+    // SAFETY: raise(3) has no memory-safety preconditions. It returns once
+    // the shell sends SIGCONT (`fg` or `bg`).
+    unsafe {
+        libc::raise(libc::SIGTSTP);
+    }
+    if app.suspend_children {
+        app.signal_children(libc::SIGCONT);
+    }
+    take_screen(terminal, app)
+}
+
+/// Set up the host terminal again and repaint everything, including the
+/// cursor style and host-drawn images: after a suspend, or for a client
+/// that attached to our session
+fn take_screen(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App) -> Result<()> {
+    enable_raw_mode()?;
+    execute!(
+        io::stdout(),
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableFocusChange,
+        EnableBracketedPaste
+    )?;
+    // Unlike clear(), this doesn't ask the terminal where its cursor is; the
+    // answer can't get past the event reader
+    let size = terminal.size()?;
+    terminal.resize(Rect::new(0, 0, size.width, size.height))?;
+    app.applied_cursor_style = None;
+    app.shown_overlays.clear();
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn suspend(_terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, _app: &mut App) -> Result<()> {
+    Ok(())
+}
+
+/// How long children get to exit after a forwarded signal before SIGKILL
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(3);
+
+/// Forward `signal` to every child's process group and give them
+/// [`SHUTDOWN_GRACE`] to exit, then kill whatever is left
+async fn stop_children(app: &App, signal: i32) {
+    app.signal_children(signal);
+    if !wait_for_children(app, SHUTDOWN_GRACE).await {
+        #[cfg(unix)]
+        app.signal_children(libc::SIGKILL);
+        wait_for_children(app, Duration::from_secs(1)).await;
+    }
+}
+
+/// Wait up to `timeout` for every child to exit. Returns whether they did.
+async fn wait_for_children(app: &App, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    while !app.children_exited() {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    true
+}
+
+/// Runs until the user quits. Returns the signal that ended cltree, if one did.
 async fn run_app(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
     app: &mut App,
     mut event_handler: EventHandler,
-) -> Result<()> {
+) -> Result<Option<i32>> {
     loop {
         // Draw UI
-        terminal.draw(|frame| ui::draw(frame, app))?;
+        let frame = terminal.draw(|frame| ui::draw(frame, app))?;
+        if let Some(path) = app.screenshot_request.take() {
+            let format = ScreenshotFormat::from_path(&path);
+            let document = screenshot::render(
+                frame.buffer,
+                &app.settings.palette,
+                app.settings.ambiguous_width,
+                format,
+            );
+            let result = std::fs::write(&path, document)
+                .with_context(|| format!("cannot write screenshot {}", path.display()));
+            if app.exit_after_screenshot {
+                return result.map(|()| None);
+            }
+            app.set_status(match result {
+                Ok(()) => format!(" Saved {} ", path.display()),
+                Err(e) => format!(" {e:#} "),
+            });
+        }
+
+        // Passthrough images live outside ratatui's buffer: when they change,
+        // wipe the host screen, repaint, and draw the current set
+        let overlays: Vec<_> = app
+            .image_overlays
+            .iter()
+            .map(|(position, placement)| (*position, placement.serial))
+            .collect();
+        if overlays != app.shown_overlays {
+            if !app.shown_overlays.is_empty() {
+                ui::clear_images(app.settings.images)?;
+                terminal.clear()?;
+                terminal.draw(|frame| ui::draw(frame, app))?;
+            }
+            ui::write_images(&app.image_overlays, app.settings.images)?;
+            app.shown_overlays = overlays;
+        }
+        if app.applied_cursor_style != Some(app.cursor_style) {
+            ui::write_cursor_style(app.cursor_style)?;
+            app.applied_cursor_style = Some(app.cursor_style);
+        }
 
         // Handle events
         match event_handler.next().await? {
             event::Event::Tick => {
-                if app.tick() {
-                    return Ok(());
-                }
+                app.tick();
             }
             event::Event::Key(key_event) => {
                 if app.handle_key(key_event) {
-                    return Ok(());
+                    return Ok(None);
+                }
+                if app.suspend_requested {
+                    app.suspend_requested = false;
+                    suspend(terminal, app)?;
                 }
             }
             event::Event::Mouse(mouse_event) => {
@@ -241,12 +645,18 @@ async fn run_app(
             event::Event::Paste(text) => {
                 app.handle_paste(text);
             }
-            event::Event::Resize(_width, _height) => {}
+            event::Event::Resize(_width, _height) => {
+                // In a session, the server signals a resize when a client
+                // attaches; its terminal has none of our modes or contents
+                if session::is_inner() {
+                    take_screen(terminal, app)?;
+                }
+            }
             event::Event::FocusGained => {
-                app.terminal.send_focus_event(true);
+                app.handle_focus(true);
             }
             event::Event::FocusLost => {
-                app.terminal.send_focus_event(false);
+                app.handle_focus(false);
             }
             event::Event::FileChange(path) => {
                 app.handle_file_change(path);
@@ -254,9 +664,63 @@ async fn run_app(
             event::Event::PtyOutput => {
                 // vterm already updated by the reader thread; just redraw on next loop iteration
             }
-            event::Event::Signal => {
-                return Ok(());
+            event::Event::Signal(signal) => {
+                return Ok(Some(signal));
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_speed() {
+        assert_eq!(parse_speed("2").unwrap(), 2.0);
+        assert_eq!(parse_speed("0.5").unwrap(), 0.5);
+        for bad in ["", "fast", "NaN", "inf", "0", "-1"] {
+            let err = parse_speed(bad).unwrap_err().to_string();
+            assert!(err.contains("invalid --speed"), "{bad:?}: {err}");
+        }
+    }
+
+    #[test]
+    fn test_parse_screenshot_delay() {
+        assert_eq!(parse_screenshot_delay("0").unwrap(), Duration::ZERO);
+        assert_eq!(
+            parse_screenshot_delay("1.5").unwrap(),
+            Duration::from_millis(1500)
+        );
+        for bad in ["", "soon", "NaN", "inf", "-1", "1e300"] {
+            let err = parse_screenshot_delay(bad).unwrap_err().to_string();
+            assert!(err.contains("invalid --screenshot-delay"), "{bad:?}: {err}");
+        }
+    }
+
+    #[test]
+    fn test_detach_args() {
+        assert_eq!(
+            detach_args(&args(&["-p", "x", "--model", "opus"])).unwrap(),
+            None
+        );
+        assert_eq!(
+            detach_args(&args(&["--detach", "-p", "x", "--session", "work", "-c"])).unwrap(),
+            Some((Some("work".to_string()), args(&["-p", "x", "-c"])))
+        );
+        assert_eq!(
+            detach_args(&args(&["--session=work", "--detach"])).unwrap(),
+            Some((Some("work".to_string()), vec![]))
+        );
+        assert_eq!(
+            detach_args(&args(&["--detach"])).unwrap(),
+            Some((None, vec![]))
+        );
+        let err = detach_args(&args(&["--session", "work"])).unwrap_err();
+        assert!(err.to_string().contains("--detach"), "{err}");
+    }
+}
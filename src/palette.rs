@@ -0,0 +1,277 @@
+use ratatui::style::Color;
+
+pub type Rgb = (u8, u8, u8);
+
+/// Colours the vterm reports back to the child (OSC 4/10/11/12 queries).
+///
+/// The defaults describe cltree's own dark theme; `main` replaces them with
+/// the host terminal's real colours when it answers our startup query.
+#[derive(Clone, Debug)]
+pub struct Palette {
+    pub foreground: Rgb,
+    pub background: Rgb,
+    pub cursor: Rgb,
+    pub indexed: [Rgb; 256],
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        let mut indexed = [(0, 0, 0); 256];
+        for (i, entry) in indexed.iter_mut().enumerate() {
+            *entry = xterm_color(i as u8);
+        }
+        Self {
+            foreground: (229, 229, 229),
+            background: (0, 0, 0),
+            cursor: (229, 229, 229),
+            indexed,
+        }
+    }
+}
+
+impl Palette {
+    /// Concrete colour for a style colour; `None` and `Reset` are the
+    /// default foreground or background.
+    pub fn rgb(&self, color: Option<Color>, foreground: bool) -> Rgb {
+        let default = if foreground {
+            self.foreground
+        } else {
+            self.background
+        };
+        match color {
+            Some(Color::Rgb(r, g, b)) => (r, g, b),
+            Some(c) => ansi_index(c).map_or(default, |idx| self.indexed[idx as usize]),
+            None => default,
+        }
+    }
+}
+
+/// The standard xterm 256-colour palette.
+pub fn xterm_color(idx: u8) -> Rgb {
+    const ANSI: [Rgb; 16] = [
+        (0, 0, 0),
+        (205, 0, 0),
+        (0, 205, 0),
+        (205, 205, 0),
+        (0, 0, 238),
+        (205, 0, 205),
+        (0, 205, 205),
+        (229, 229, 229),
+        (127, 127, 127),
+        (255, 0, 0),
+        (0, 255, 0),
+        (255, 255, 0),
+        (92, 92, 255),
+        (255, 0, 255),
+        (0, 255, 255),
+        (255, 255, 255),
+    ];
+    match idx {
+        0..=15 => ANSI[idx as usize],
+        16..=231 => {
+            let i = idx - 16;
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            (level(i / 36), level((i / 6) % 6), level(i % 6))
+        }
+        _ => {
+            let v = 8 + (idx - 232) * 10;
+            (v, v, v)
+        }
+    }
+}
+
+/// Palette index of a named ANSI colour, as produced by SGR 30-37/90-97.
+pub fn ansi_index(color: Color) -> Option<u8> {
+    Some(match color {
+        Color::Black => 0,
+        Color::Red => 1,
+        Color::Green => 2,
+        Color::Yellow => 3,
+        Color::Blue => 4,
+        Color::Magenta => 5,
+        Color::Cyan => 6,
+        Color::Gray => 7,
+        Color::DarkGray => 8,
+        Color::LightRed => 9,
+        Color::LightGreen => 10,
+        Color::LightYellow => 11,
+        Color::LightBlue => 12,
+        Color::LightMagenta => 13,
+        Color::LightCyan => 14,
+        Color::White => 15,
+        Color::Indexed(i) => i,
+        _ => return None,
+    })
+}
+
+/// Parse an X11 colour spec: `rgb:R/G/B` (1-4 hex digits per channel) or
+/// `#RGB` / `#RRGGBB`.
+pub fn parse_color_spec(spec: &str) -> Option<Rgb> {
+    fn scale(hex: &str) -> Option<u8> {
+        if hex.is_empty() || hex.len() > 4 {
+            return None;
+        }
+        let value = u32::from_str_radix(hex, 16).ok()?;
+        let max = (1u32 << (4 * hex.len())) - 1;
+        Some((value * 255 / max) as u8)
+    }
+
+    if let Some(rest) = spec.strip_prefix("rgb:") {
+        let mut parts = rest.split('/');
+        let r = scale(parts.next()?)?;
+        let g = scale(parts.next()?)?;
+        let b = scale(parts.next()?)?;
+        return parts.next().is_none().then_some((r, g, b));
+    }
+    if let Some(hex) = spec.strip_prefix('#') {
+        let n = match hex.len() {
+            3 | 6 | 9 | 12 => hex.len() / 3,
+            _ => return None,
+        };
+        return Some((
+            scale(&hex[..n])?,
+            scale(&hex[n..2 * n])?,
+            scale(&hex[2 * n..])?,
+        ));
+    }
+    None
+}
+
+/// Format a colour the way xterm answers queries: `rgb:rrrr/gggg/bbbb`.
+pub fn format_color_spec((r, g, b): Rgb) -> String {
+    format!(
+        "rgb:{:04x}/{:04x}/{:04x}",
+        r as u16 * 257,
+        g as u16 * 257,
+        b as u16 * 257
+    )
+}
+
+/// Split what the host sent back after our palette queries into its
+/// answers, folded into `palette`, and everything else: keys the user typed
+/// meanwhile, returned in order so they can be passed on.
+///
+/// Understands OSC 10/11 (default colours) and OSC 4 (indexed colours)
+/// replies ended by BEL or ST, and the DA1 reply used as a sentinel.
+pub fn parse_host_replies(reply: &[u8], palette: &mut Palette) -> Vec<u8> {
+    let mut rest = Vec::new();
+    let mut i = 0;
+    while i < reply.len() {
+        if reply[i..].starts_with(b"\x1b]") {
+            let body = &reply[i + 2..];
+            let end = body.iter().enumerate().find_map(|(at, &b)| match b {
+                0x07 => Some((at, 1)),
+                0x1b if body.get(at + 1) == Some(&b'\\') => Some((at, 2)),
+                _ => None,
+            });
+            if let Some((at, terminator)) = end {
+                apply_color_reply(&String::from_utf8_lossy(&body[..at]), palette);
+                i += 2 + at + terminator;
+                continue;
+            }
+        } else if reply[i..].starts_with(b"\x1b[?") {
+            let params = reply[i + 3..]
+                .iter()
+                .take_while(|b| b.is_ascii_digit() || **b == b';')
+                .count();
+            if reply.get(i + 3 + params) == Some(&b'c') {
+                i += 3 + params + 1;
+                continue;
+            }
+        }
+        rest.push(reply[i]);
+        i += 1;
+    }
+    palette.cursor = palette.foreground;
+    rest
+}
+
+/// Apply one OSC reply body: `10;SPEC`, `11;SPEC` or `4;INDEX;SPEC`
+fn apply_color_reply(body: &str, palette: &mut Palette) {
+    let mut parts = body.splitn(3, ';');
+    let slot = match parts.next() {
+        Some("10") => &mut palette.foreground,
+        Some("11") => &mut palette.background,
+        Some("4") => match parts.next().and_then(|idx| idx.parse::<u8>().ok()) {
+            Some(idx) => &mut palette.indexed[idx as usize],
+            None => return,
+        },
+        _ => return,
+    };
+    if let Some(rgb) = parts.next().and_then(parse_color_spec) {
+        *slot = rgb;
+    }
+}
+
+/// Ask the host terminal for its default foreground/background (OSC 10/11)
+/// and its 16 ANSI colours (OSC 4), and fold the answers into cltree's
+/// default palette. Also returns the bytes read that were not answers, such
+/// as keys typed while cltree started.
+///
+/// Must be called in raw mode, before the event stream starts reading stdin.
+/// A trailing DA1 request acts as a sentinel so terminals that ignore the
+/// colour queries don't make us wait for the full timeout.
+#[cfg(unix)]
+pub fn query_host_palette(timeout: std::time::Duration) -> (Palette, Vec<u8>) {
+    use std::io::Write;
+    use std::os::unix::io::AsRawFd;
+    use std::time::Instant;
+
+    let mut palette = Palette::default();
+    let mut query = b"\x1b]10;?\x1b\\\x1b]11;?\x1b\\".to_vec();
+    for idx in 0..16 {
+        query.extend_from_slice(format!("\x1b]4;{idx};?\x1b\\").as_bytes());
+    }
+    query.extend_from_slice(b"\x1b[c");
+    let mut stdout = std::io::stdout();
+    if stdout
+        .write_all(&query)
+        .and_then(|_| stdout.flush())
+        .is_err()
+    {
+        return (palette, Vec::new());
+    }
+
+    let fd = std::io::stdin().as_raw_fd();
+    let deadline = Instant::now() + timeout;
+    let mut reply = Vec::new();
+    let mut buf = [0u8; 256];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        let mut pfd = libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: pfd is a valid pollfd for the duration of the call
+        let ready = unsafe { libc::poll(&mut pfd, 1, remaining.as_millis() as libc::c_int) };
+        if ready <= 0 {
+            break;
+        }
+        // Read the fd directly: std's buffered Stdin would keep any extra
+        // bytes away from crossterm's event reader
+        // SAFETY: buf is valid for writes of buf.len() bytes
+        let n = unsafe { libc::read(fd, buf.as_mut_ptr().cast(), buf.len()) };
+        if n <= 0 {
+            break;
+        }
+        reply.extend_from_slice(&buf[..n as usize]);
+        // DA1 reply (CSI ? ... c) is always last
+        if let Some(pos) = reply.windows(3).position(|w| w == b"\x1b[?") {
+            if reply[pos..].contains(&b'c') {
+                break;
+            }
+        }
+    }
+
+    let rest = parse_host_replies(&reply, &mut palette);
+    (palette, rest)
+}
+
+#[cfg(not(unix))]
+pub fn query_host_palette(_timeout: std::time::Duration) -> (Palette, Vec<u8>) {
+    (Palette::default(), Vec::new())
+}
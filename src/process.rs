@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant};
+
+/// A process in a child's tree, as read from `/proc`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub ppid: u32,
+    /// Levels below the tree's root
+    pub depth: usize,
+    /// The executable's name (`comm`)
+    pub name: String,
+    pub cmdline: Vec<String>,
+    pub cwd: Option<PathBuf>,
+    /// Share of one CPU used since the previous sample
+    pub cpu_percent: f32,
+    /// Resident memory in bytes
+    pub rss: u64,
+    /// Time since the process started
+    pub elapsed: Duration,
+}
+
+impl ProcessInfo {
+    /// The command line joined with spaces, or `[name]` when it is empty
+    /// (a zombie, or a process that overwrote its arguments)
+    pub fn command(&self) -> String {
+        if self.cmdline.is_empty() {
+            format!("[{}]", self.name)
+        } else {
+            self.cmdline.join(" ")
+        }
+    }
+}
+
+/// The fields cltree uses from `/proc/<pid>/stat`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Stat {
+    pub pid: u32,
+    pub name: String,
+    pub ppid: u32,
+    /// User plus system time, in clock ticks
+    pub cpu_ticks: u64,
+    /// When the process started, in clock ticks after boot
+    pub start_ticks: u64,
+    pub rss_pages: u64,
+}
+
+/// Parse the contents of `/proc/<pid>/stat`. The name is in parentheses and
+/// may itself contain spaces and parentheses, so the fields after it are
+/// found from the last `)`.
+pub fn parse_stat(stat: &str) -> Option<Stat> {
+    let open = stat.find('(')?;
+    let close = stat.rfind(')')?;
+    let pid = stat[..open].trim().parse().ok()?;
+    let name = stat.get(open + 1..close)?.to_string();
+    // Fields from 3 (state) on, so field n is at n - 3
+    let fields: Vec<&str> = stat[close + 1..].split_whitespace().collect();
+    let field = |n: usize| fields.get(n - 3)?.parse::<u64>().ok();
+    Some(Stat {
+        pid,
+        name,
+        ppid: field(4)? as u32,
+        cpu_ticks: field(14)? + field(15)?,
+        start_ticks: field(22)?,
+        rss_pages: field(24)?,
+    })
+}
+
+/// Reads a process tree repeatedly, remembering CPU time between samples
+/// to work out CPU usage
+#[derive(Default)]
+pub struct ProcessSampler {
+    last: HashMap<u32, u64>,
+    last_at: Option<Instant>,
+}
+
+impl ProcessSampler {
+    /// Each of `roots` that is still running and every process descended
+    /// from it, each parent followed by its children. Empty when the roots
+    /// are gone or the platform has no `/proc`.
+    pub fn sample(&mut self, roots: &[u32]) -> Vec<ProcessInfo> {
+        let stats = all_processes();
+        let now = Instant::now();
+        let interval = self.last_at.map(|at| now - at);
+        let system = System::read();
+
+        let mut children: HashMap<u32, Vec<&Stat>> = HashMap::new();
+        for stat in &stats {
+            children.entry(stat.ppid).or_default().push(stat);
+        }
+
+        let mut tree = Vec::new();
+        let mut last = HashMap::new();
+        // Popped in the order given
+        let mut stack: Vec<(&Stat, usize)> = roots
+            .iter()
+            .rev()
+            .filter_map(|&root| stats.iter().find(|stat| stat.pid == root))
+            .map(|stat| (stat, 0))
+            .collect();
+        while let Some((stat, depth)) = stack.pop() {
+            let cpu_percent = match (interval, self.last.get(&stat.pid)) {
+                (Some(interval), Some(&before)) if !interval.is_zero() => {
+                    let used = stat.cpu_ticks.saturating_sub(before) as f64 / system.ticks_per_sec;
+                    (used / interval.as_secs_f64() * 100.0) as f32
+                }
+                _ => 0.0,
+            };
+            last.insert(stat.pid, stat.cpu_ticks);
+            tree.push(ProcessInfo {
+                pid: stat.pid,
+                ppid: stat.ppid,
+                depth,
+                name: stat.name.clone(),
+                cmdline: read_cmdline(stat.pid),
+                cwd: crate::cwd::process_cwd(stat.pid),
+                cpu_percent,
+                rss: stat.rss_pages * system.page_size,
+                elapsed: Duration::from_secs_f64(
+                    (system.uptime - stat.start_ticks as f64 / system.ticks_per_sec).max(0.0),
+                ),
+            });
+            if let Some(kids) = children.get_mut(&stat.pid) {
+                // Popped in PID order
+                kids.sort_unstable_by_key(|kid| std::cmp::Reverse(kid.pid));
+                stack.extend(kids.iter().map(|kid| (*kid, depth + 1)));
+            }
+        }
+        self.last = last;
+        self.last_at = Some(now);
+        tree
+    }
+}
+
+/// Samples process trees on a background thread, so reading `/proc` never
+/// holds up drawing
+pub struct ProcessMonitor {
+    roots_tx: Sender<Vec<u32>>,
+    trees_rx: Receiver<(Vec<u32>, Vec<ProcessInfo>)>,
+    roots: Vec<u32>,
+}
+
+impl ProcessMonitor {
+    /// Start the thread. It samples every `interval`, and straight away when
+    /// the roots change; it ends when the monitor is dropped.
+    pub fn spawn(interval: Duration) -> Self {
+        let (roots_tx, roots_rx) = mpsc::channel::<Vec<u32>>();
+        let (trees_tx, trees_rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut sampler = ProcessSampler::default();
+            let mut roots = Vec::new();
+            loop {
+                match roots_rx.recv_timeout(interval) {
+                    Ok(mut next) => {
+                        // Only the latest request matters
+                        next = roots_rx.try_iter().last().unwrap_or(next);
+                        if next != roots {
+                            sampler = ProcessSampler::default();
+                            roots = next;
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+                let tree = sampler.sample(&roots);
+                if trees_tx.send((roots.clone(), tree)).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            roots_tx,
+            trees_rx,
+            roots: Vec::new(),
+        }
+    }
+
+    /// Follow the trees under `roots` from now on. Sampling starts again at
+    /// once when they differ from the current ones.
+    pub fn watch(&mut self, roots: Vec<u32>) {
+        if roots != self.roots {
+            self.refresh(roots);
+        }
+    }
+
+    /// Sample `roots` now rather than at the next interval
+    pub fn refresh(&mut self, roots: Vec<u32>) {
+        self.roots = roots.clone();
+        let _ = self.roots_tx.send(roots);
+    }
+
+    /// The newest tree of the watched roots, if one arrived since the last
+    /// call. Trees of roots no longer watched are dropped.
+    pub fn latest(&self) -> Option<Vec<ProcessInfo>> {
+        self.trees_rx
+            .try_iter()
+            .filter(|(roots, _)| *roots == self.roots)
+            .last()
+            .map(|(_, tree)| tree)
+    }
+}
+
+/// Directories the processes below the roots are working in, each once
+pub fn subprocess_dirs(tree: &[ProcessInfo]) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    for cwd in tree
+        .iter()
+        .filter(|p| p.depth > 0)
+        .filter_map(|p| p.cwd.as_ref())
+    {
+        if !dirs.contains(cwd) {
+            dirs.push(cwd.clone());
+        }
+    }
+    dirs
+}
+
+#[cfg(unix)]
+pub const SIGINT: i32 = libc::SIGINT;
+#[cfg(unix)]
+pub const SIGTERM: i32 = libc::SIGTERM;
+#[cfg(not(unix))]
+pub const SIGINT: i32 = 2;
+#[cfg(not(unix))]
+pub const SIGTERM: i32 = 15;
+
+/// Send `signal` to the single process `pid`
+#[cfg(unix)]
+pub fn send_signal(pid: u32, signal: i32) -> io::Result<()> {
+    // SAFETY: kill has no memory-safety preconditions
+    if unsafe { libc::kill(pid as libc::pid_t, signal) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(unix))]
+pub fn send_signal(_pid: u32, _signal: i32) -> io::Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+/// Clock and memory units, and the time since boot
+struct System {
+    ticks_per_sec: f64,
+    page_size: u64,
+    uptime: f64,
+}
+
+impl System {
+    #[cfg(target_os = "linux")]
+    fn read() -> Self {
+        // SAFETY: sysconf only reads configuration values
+        let (ticks, page) = unsafe {
+            (
+                libc::sysconf(libc::_SC_CLK_TCK),
+                libc::sysconf(libc::_SC_PAGESIZE),
+            )
+        };
+        let uptime = std::fs::read_to_string("/proc/uptime")
+            .ok()
+            .and_then(|text| text.split_whitespace().next()?.parse().ok())
+            .unwrap_or(0.0);
+        Self {
+            ticks_per_sec: if ticks > 0 { ticks as f64 } else { 100.0 },
+            page_size: if page > 0 { page as u64 } else { 4096 },
+            uptime,
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn read() -> Self {
+        Self {
+            ticks_per_sec: 100.0,
+            page_size: 4096,
+            uptime: 0.0,
+        }
+    }
+}
+
+/// The stat of every process
+#[cfg(target_os = "linux")]
+fn all_processes() -> Vec<Stat> {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().parse::<u32>().is_ok())
+        .filter_map(|entry| std::fs::read_to_string(entry.path().join("stat")).ok())
+        .filter_map(|stat| parse_stat(&stat))
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn all_processes() -> Vec<Stat> {
+    Vec::new()
+}
+
+/// Arguments from `/proc/<pid>/cmdline`, which separates them with NULs
+#[cfg(target_os = "linux")]
+fn read_cmdline(pid: u32) -> Vec<String> {
+    std::fs::read(format!("/proc/{pid}/cmdline"))
+        .map(|bytes| {
+            bytes
+                .split(|&b| b == 0)
+                .filter(|arg| !arg.is_empty())
+                .map(|arg| String::from_utf8_lossy(arg).into_owned())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_cmdline(_pid: u32) -> Vec<String> {
+    Vec::new()
+}
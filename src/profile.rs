@@ -0,0 +1,315 @@
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use crate::cwd::{self, CwdTracker};
+
+/// Where to look for a session's working directory
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CwdHints {
+    /// Detectors to ask, highest priority first (see [`cwd::DETECTORS`])
+    pub detectors: Vec<String>,
+    /// Rows at the top of the screen the `screen` and `regex` detectors read
+    pub screen_rows: usize,
+    /// For the `regex` detector: its first capture group is the path
+    pub pattern: Option<String>,
+    /// Ticks a guessed, shallower directory must be seen before it's followed
+    pub settle_ticks: u32,
+}
+
+impl Default for CwdHints {
+    fn default() -> Self {
+        Self {
+            detectors: vec!["osc7".to_string(), "process".to_string()],
+            screen_rows: 8,
+            pattern: None,
+            settle_ticks: cwd::SETTLE_TICKS,
+        }
+    }
+}
+
+/// How to launch the program in a terminal pane
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Profile {
+    pub name: String,
+    /// Shown on the pane's border and in exit messages
+    pub title: String,
+    pub command: String,
+    /// Arguments placed before the ones given on the command line
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    pub env_remove: Vec<String>,
+    pub term: String,
+    pub cwd: CwdHints,
+    /// Printed when the command cannot be started
+    pub install_hint: Option<String>,
+    /// Added when restarting to continue the last session; empty if the
+    /// program has no such option
+    pub continue_args: Vec<String>,
+    /// Added when restarting to pick a session to resume
+    pub resume_args: Vec<String>,
+    /// Flags that pick a session, dropped before `continue_args` or
+    /// `resume_args` are added. `"-r [ID]"` also drops a value after `-r`.
+    pub session_flags: Vec<String>,
+}
+
+impl Profile {
+    /// A bare profile running `command`, named after it
+    pub fn command(command: &str) -> Self {
+        let name = Path::new(command)
+            .file_name()
+            .map_or(command.to_string(), |n| n.to_string_lossy().into_owned());
+        Self {
+            title: name.clone(),
+            name,
+            command: command.to_string(),
+            args: Vec::new(),
+            env: Vec::new(),
+            env_remove: Vec::new(),
+            term: "xterm-256color".to_string(),
+            cwd: CwdHints::default(),
+            install_hint: None,
+            continue_args: Vec::new(),
+            resume_args: Vec::new(),
+            session_flags: Vec::new(),
+        }
+    }
+
+    /// The profiles cltree ships with: claude (the default), aider, codex
+    /// and shell
+    pub fn builtin() -> Vec<Profile> {
+        let claude = Profile {
+            name: "claude".to_string(),
+            title: "Claude Code".to_string(),
+            // Allow nested Claude sessions
+            env_remove: vec!["CLAUDECODE".to_string()],
+            cwd: CwdHints {
+                detectors: ["osc7", "process", "screen"].map(String::from).to_vec(),
+                ..CwdHints::default()
+            },
+            install_hint: Some(
+                "Make sure 'claude' CLI is installed and in your PATH.\r\n\
+                 Install: npm install -g @anthropic-ai/claude-code"
+                    .to_string(),
+            ),
+            continue_args: vec!["--continue".to_string()],
+            resume_args: vec!["--resume".to_string()],
+            session_flags: ["-c", "--continue", "-r [ID]", "--resume [ID]"]
+                .map(String::from)
+                .to_vec(),
+            ..Profile::command("claude")
+        };
+        let aider = Profile {
+            title: "Aider".to_string(),
+            install_hint: Some(
+                "Install: python -m pip install aider-install && aider-install".into(),
+            ),
+            continue_args: vec!["--restore-chat-history".to_string()],
+            session_flags: vec!["--restore-chat-history".to_string()],
+            ..Profile::command("aider")
+        };
+        let codex = Profile {
+            title: "Codex".to_string(),
+            install_hint: Some("Install: npm install -g @openai/codex".to_string()),
+            continue_args: ["resume", "--last"].map(String::from).to_vec(),
+            resume_args: vec!["resume".to_string()],
+            session_flags: ["resume [ID]", "--last"].map(String::from).to_vec(),
+            ..Profile::command("codex")
+        };
+        let shell = Profile {
+            name: "shell".to_string(),
+            title: "Shell".to_string(),
+            ..Profile::command(&std::env::var("SHELL").unwrap_or_else(|_| "sh".to_string()))
+        };
+        vec![claude, aider, codex, shell]
+    }
+
+    /// Apply the fields set in a JSON profile object; the rest are kept
+    fn merge(&mut self, json: &Value) -> Result<()> {
+        let Some(object) = json.as_object() else {
+            bail!("profile '{}' must be a JSON object", self.name);
+        };
+        let string = |value: &Value, key: &str| -> Result<String> {
+            value
+                .as_str()
+                .map(str::to_string)
+                .with_context(|| format!("profile '{}': {key} must be a string", self.name))
+        };
+        let strings = |value: &Value, key: &str| -> Result<Vec<String>> {
+            value
+                .as_array()
+                .and_then(|items| items.iter().map(|v| v.as_str().map(String::from)).collect())
+                .with_context(|| {
+                    format!("profile '{}': {key} must be a list of strings", self.name)
+                })
+        };
+
+        for (key, value) in object {
+            match key.as_str() {
+                "title" => self.title = string(value, key)?,
+                "command" => self.command = string(value, key)?,
+                "args" => self.args = strings(value, key)?,
+                "env" => {
+                    let vars = value.as_object().with_context(|| {
+                        format!("profile '{}': env must be an object", self.name)
+                    })?;
+                    for (var, value) in vars {
+                        self.env.retain(|(name, _)| name != var);
+                        self.env.push((var.clone(), string(value, key)?));
+                    }
+                }
+                "env_remove" => self.env_remove = strings(value, key)?,
+                "term" => self.term = string(value, key)?,
+                "install_hint" => self.install_hint = Some(string(value, key)?),
+                "continue_args" => self.continue_args = strings(value, key)?,
+                "resume_args" => self.resume_args = strings(value, key)?,
+                "session_flags" => self.session_flags = strings(value, key)?,
+                "cwd" => {
+                    let hints = value.as_object().with_context(|| {
+                        format!("profile '{}': cwd must be an object", self.name)
+                    })?;
+                    for (hint, value) in hints {
+                        match hint.as_str() {
+                            "detectors" => self.cwd.detectors = strings(value, hint)?,
+                            "screen_rows" => {
+                                self.cwd.screen_rows =
+                                    value.as_u64().map_or(self.cwd.screen_rows, |n| n as usize)
+                            }
+                            "pattern" => self.cwd.pattern = Some(string(value, hint)?),
+                            // Older files switch the two original detectors
+                            // on and off; keep reading them
+                            "osc7" | "process" => {
+                                let detectors = &mut self.cwd.detectors;
+                                let listed = detectors.iter().any(|d| d == hint);
+                                match value.as_bool() {
+                                    Some(true) if !listed => detectors.push(hint.clone()),
+                                    Some(false) => detectors.retain(|d| d != hint),
+                                    _ => {}
+                                }
+                            }
+                            "settle_ticks" => {
+                                self.cwd.settle_ticks =
+                                    value.as_u64().map_or(self.cwd.settle_ticks, |n| n as u32)
+                            }
+                            _ => bail!("profile '{}': unknown cwd hint '{hint}'", self.name),
+                        }
+                    }
+                    CwdTracker::from_hints(&self.cwd)
+                        .with_context(|| format!("profile '{}'", self.name))?;
+                }
+                _ => bail!("profile '{}': unknown field '{key}'", self.name),
+            }
+        }
+        Ok(())
+    }
+
+    /// `args` without this profile's session flags, so continue or resume
+    /// arguments can be added without conflicting
+    pub fn without_session_flags(&self, args: &[String]) -> Vec<String> {
+        // Each flag, and whether a value may follow it
+        let flags: Vec<(&str, bool)> = self
+            .session_flags
+            .iter()
+            .map(|flag| match flag.split_once(' ') {
+                Some((flag, _)) => (flag, true),
+                None => (flag.as_str(), false),
+            })
+            .collect();
+        let mut kept = Vec::new();
+        let mut args = args.iter().peekable();
+        while let Some(arg) = args.next() {
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, _)) => (name, true),
+                None => (arg.as_str(), false),
+            };
+            match flags.iter().find(|(flag, _)| *flag == name) {
+                Some((_, true)) if !inline_value => {
+                    args.next_if(|next| !next.starts_with('-'));
+                }
+                Some((_, takes_value)) if *takes_value || !inline_value => {}
+                _ => kept.push(arg.clone()),
+            }
+        }
+        kept
+    }
+}
+
+/// The built-in profiles, plus those from the user's profiles file
+pub struct Profiles {
+    profiles: Vec<Profile>,
+}
+
+impl Profiles {
+    /// `~/.config/cltree/profiles.json` (or the platform's equivalent)
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("cltree").join("profiles.json"))
+    }
+
+    pub fn builtin() -> Self {
+        Self {
+            profiles: Profile::builtin(),
+        }
+    }
+
+    /// Built-in profiles, with the user's profiles file applied if there is one
+    pub fn load() -> Result<Self> {
+        let mut profiles = Self::builtin();
+        if let Some(path) = Self::default_path().filter(|path| path.exists()) {
+            let text = std::fs::read_to_string(&path)
+                .with_context(|| format!("cannot read {}", path.display()))?;
+            profiles
+                .merge_json(&text)
+                .with_context(|| format!("invalid profiles file {}", path.display()))?;
+        }
+        Ok(profiles)
+    }
+
+    /// Apply a JSON object of `name: profile` pairs. A profile named after an
+    /// existing one changes only the fields it sets; a new one starts from a
+    /// bare profile running its `command` (or its name).
+    pub fn merge_json(&mut self, text: &str) -> Result<()> {
+        let json: Value = serde_json::from_str(text)?;
+        let Some(object) = json.as_object() else {
+            bail!("expected an object of profiles");
+        };
+        for (name, value) in object {
+            match self.profiles.iter_mut().find(|p| &p.name == name) {
+                Some(profile) => profile.merge(value)?,
+                None => {
+                    let mut profile = Profile {
+                        name: name.clone(),
+                        ..Profile::command(name)
+                    };
+                    profile.merge(value)?;
+                    if value.get("title").is_none() {
+                        profile.title = name.clone();
+                    }
+                    self.profiles.push(profile);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Run `command` for the claude profile instead (`CLTREE_COMMAND`).
+    /// Other profiles keep their own command.
+    pub fn override_claude_command(&mut self, command: &str) {
+        if let Some(claude) = self.profiles.iter_mut().find(|p| p.name == "claude") {
+            claude.command = command.to_string();
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<Profile> {
+        match self.profiles.iter().find(|p| p.name == name) {
+            Some(profile) => Ok(profile.clone()),
+            None => bail!(
+                "unknown profile '{name}' (available: {})",
+                self.profiles
+                    .iter()
+                    .map(|p| p.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
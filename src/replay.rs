@@ -0,0 +1,169 @@
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Paragraph},
+};
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::asciicast::{Cast, EventKind};
+use crate::ui::TerminalWidget;
+use crate::unicode::AmbiguousWidth;
+use crate::vterm::VirtualTerminal;
+
+const MIN_SPEED: f64 = 0.25;
+const MAX_SPEED: f64 = 16.0;
+/// Seconds skipped by the left/right arrow keys
+const SEEK_STEP: f64 = 5.0;
+const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Feeds a recording through a fresh vterm in recorded time
+struct Player {
+    cast: Cast,
+    vterm: VirtualTerminal,
+    ambiguous_width: AmbiguousWidth,
+    /// Index of the first event not yet applied
+    next: usize,
+    /// Playback position in recording seconds
+    position: f64,
+    speed: f64,
+    paused: bool,
+}
+
+impl Player {
+    fn new(cast: Cast, speed: f64, ambiguous_width: AmbiguousWidth) -> Self {
+        let vterm = Self::new_vterm(&cast, ambiguous_width);
+        Self {
+            cast,
+            vterm,
+            ambiguous_width,
+            next: 0,
+            position: 0.0,
+            speed: speed.clamp(MIN_SPEED, MAX_SPEED),
+            paused: false,
+        }
+    }
+
+    fn new_vterm(cast: &Cast, ambiguous_width: AmbiguousWidth) -> VirtualTerminal {
+        let mut vterm = VirtualTerminal::new(cast.width as usize, cast.height as usize);
+        vterm.set_ambiguous_width(ambiguous_width);
+        vterm
+    }
+
+    /// Apply every event up to `time`
+    fn advance_to(&mut self, time: f64) {
+        while let Some(event) = self.cast.events.get(self.next) {
+            if event.time > time {
+                break;
+            }
+            match event.kind {
+                EventKind::Output(ref data) => {
+                    self.vterm.feed(data.as_bytes());
+                    // Nothing answers queries during playback
+                    self.vterm.take_responses();
+                }
+                EventKind::Resize(cols, rows) => self.vterm.resize(cols as usize, rows as usize),
+            }
+            self.next += 1;
+        }
+        self.position = time.clamp(0.0, self.cast.duration());
+    }
+
+    /// Jump to `time`; going backwards replays from the start
+    fn seek(&mut self, time: f64) {
+        if time < self.position {
+            self.vterm = Self::new_vterm(&self.cast, self.ambiguous_width);
+            self.next = 0;
+        }
+        self.advance_to(time.max(0.0));
+    }
+
+    fn finished(&self) -> bool {
+        self.next >= self.cast.events.len()
+    }
+}
+
+/// Play `cast` until the user quits. Space pauses, +/- change speed,
+/// arrows seek, q quits.
+pub fn run(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    cast: Cast,
+    title: &str,
+    speed: f64,
+    ambiguous_width: AmbiguousWidth,
+) -> Result<()> {
+    let mut player = Player::new(cast, speed, ambiguous_width);
+    let mut last_frame = Instant::now();
+    loop {
+        terminal.draw(|frame| draw(frame, &player, title))?;
+
+        if event::poll(FRAME_INTERVAL)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    match key.code {
+                        KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                        KeyCode::Char(' ') => player.paused = !player.paused,
+                        KeyCode::Char('+') | KeyCode::Char('=') | KeyCode::Up => {
+                            player.speed = (player.speed * 2.0).min(MAX_SPEED)
+                        }
+                        KeyCode::Char('-') | KeyCode::Down => {
+                            player.speed = (player.speed / 2.0).max(MIN_SPEED)
+                        }
+                        KeyCode::Right => player.seek(player.position + SEEK_STEP),
+                        KeyCode::Left => player.seek(player.position - SEEK_STEP),
+                        KeyCode::Home | KeyCode::Char('0') => player.seek(0.0),
+                        KeyCode::End => player.seek(player.cast.duration()),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        let now = Instant::now();
+        let elapsed = now.duration_since(last_frame).as_secs_f64();
+        last_frame = now;
+        if !player.paused && !player.finished() {
+            player.advance_to(player.position + elapsed * player.speed);
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, player: &Player, title: &str) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .split(frame.area());
+
+    let block = Block::default()
+        .title(format!(" Replay: {title} "))
+        .title_style(Style::default().fg(Color::Cyan).bold())
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let inner = block.inner(chunks[0]);
+    frame.render_widget(block, chunks[0]);
+    frame.render_widget(TerminalWidget::new(&player.vterm, None, None, None), inner);
+
+    let cursor = player.vterm.cursor();
+    if cursor.visible && (cursor.x as u16) < inner.width && (cursor.y as u16) < inner.height {
+        frame.set_cursor_position((inner.x + cursor.x as u16, inner.y + cursor.y as u16));
+    }
+
+    let state = if player.finished() {
+        "■"
+    } else if player.paused {
+        "⏸"
+    } else {
+        "▶"
+    };
+    let status = format!(
+        " {state} {:.1}s / {:.1}s  {}x   space pause  ←/→ seek  +/- speed  q quit",
+        player.position,
+        player.cast.duration(),
+        player.speed
+    );
+    frame.render_widget(
+        Paragraph::new(status).style(Style::default().fg(Color::DarkGray)),
+        chunks[1],
+    );
+}
@@ -0,0 +1,262 @@
+use ratatui::buffer::Buffer;
+use ratatui::style::{Modifier, Style};
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::export::{css_color, css_style, escape_html, text_decoration};
+use crate::palette::{Palette, Rgb};
+use crate::unicode::{grapheme_width, AmbiguousWidth};
+
+/// Cell size in SVG user units for the 14px font
+const CELL_WIDTH: f64 = 8.4;
+const CELL_HEIGHT: f64 = 18.0;
+const FONT_SIZE: f64 = 14.0;
+/// Distance from the top of a cell to the text baseline
+const BASELINE: f64 = 14.0;
+const FONT_FAMILY: &str = "ui-monospace,SFMono-Regular,Menlo,Consolas,'DejaVu Sans Mono',monospace";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScreenshotFormat {
+    #[default]
+    Svg,
+    Html,
+}
+
+impl ScreenshotFormat {
+    /// Format implied by a file name: `.html`/`.htm` or SVG otherwise
+    pub fn from_path(path: &Path) -> Self {
+        match path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase)
+            .as_deref()
+        {
+            Some("html" | "htm") => Self::Html,
+            _ => Self::Svg,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Svg => "svg",
+            Self::Html => "html",
+        }
+    }
+}
+
+/// A visible glyph of the frame: the cells it covers and its style
+struct Glyph<'a> {
+    col: usize,
+    width: usize,
+    text: &'a str,
+    style: Style,
+}
+
+/// Render a whole frame buffer as a self-contained document. Named and
+/// indexed colours are resolved through `palette` so the file looks the
+/// same everywhere. `ambiguous_width` must match the panes' setting so wide
+/// ambiguous characters cover the cells they were drawn over.
+pub fn render(
+    buf: &Buffer,
+    palette: &Palette,
+    ambiguous_width: AmbiguousWidth,
+    format: ScreenshotFormat,
+) -> String {
+    let rows = glyph_rows(buf, ambiguous_width);
+    match format {
+        ScreenshotFormat::Svg => to_svg(&rows, buf.area.width as usize, palette),
+        ScreenshotFormat::Html => to_html(&rows, palette),
+    }
+}
+
+fn glyph_rows(buf: &Buffer, ambiguous_width: AmbiguousWidth) -> Vec<Vec<Glyph<'_>>> {
+    let area = buf.area;
+    (area.top()..area.bottom())
+        .map(|y| {
+            let mut glyphs = Vec::new();
+            let mut x = area.left();
+            while x < area.right() {
+                let Some(cell) = buf.cell((x, y)) else {
+                    break;
+                };
+                let text = cell.symbol();
+                let width = grapheme_width(text, ambiguous_width).clamp(1, 2);
+                glyphs.push(Glyph {
+                    col: (x - area.left()) as usize,
+                    width,
+                    text,
+                    style: cell.style(),
+                });
+                // The cell after a wide glyph is covered by it
+                x += width as u16;
+            }
+            glyphs
+        })
+        .collect()
+}
+
+/// Concrete foreground and background, with reverse video applied
+fn colors(style: Style, palette: &Palette) -> (Rgb, Rgb) {
+    let fg = palette.rgb(style.fg, true);
+    let bg = palette.rgb(style.bg, false);
+    if style.add_modifier.contains(Modifier::REVERSED) {
+        (bg, fg)
+    } else {
+        (fg, bg)
+    }
+}
+
+fn is_blank(glyph: &Glyph) -> bool {
+    glyph.text.trim().is_empty() && text_decoration(glyph.style).is_none()
+}
+
+/// Text attributes of an SVG `<text>` element for a style
+fn text_attributes(style: Style, palette: &Palette) -> String {
+    let (fg, _) = colors(style, palette);
+    let mut attrs = format!(" fill=\"{}\"", css_color(fg));
+    if style.add_modifier.contains(Modifier::BOLD) {
+        attrs.push_str(" font-weight=\"bold\"");
+    }
+    if style.add_modifier.contains(Modifier::ITALIC) {
+        attrs.push_str(" font-style=\"italic\"");
+    }
+    if style.add_modifier.contains(Modifier::DIM) {
+        attrs.push_str(" opacity=\"0.6\"");
+    }
+    if let Some(decoration) = text_decoration(style) {
+        let _ = write!(attrs, " text-decoration=\"{decoration}\"");
+    }
+    attrs
+}
+
+fn to_svg(rows: &[Vec<Glyph>], cols: usize, palette: &Palette) -> String {
+    let width = format!("{:.1}", cols as f64 * CELL_WIDTH);
+    let height = format!("{:.1}", rows.len() as f64 * CELL_HEIGHT);
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+         viewBox=\"0 0 {width} {height}\" font-family=\"{FONT_FAMILY}\" font-size=\"{FONT_SIZE}\">\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"{}\"/>\n",
+        css_color(palette.background)
+    );
+
+    // Backgrounds: one rect per run of cells sharing a non-default colour
+    out.push_str("<g shape-rendering=\"crispEdges\">\n");
+    for (row, glyphs) in rows.iter().enumerate() {
+        let mut run: Option<(usize, usize, Rgb)> = None;
+        for glyph in glyphs {
+            let (_, bg) = colors(glyph.style, palette);
+            let bg = (bg != palette.background).then_some(bg);
+            run = match (run, bg) {
+                (Some((start, end, color)), Some(bg)) if color == bg && end == glyph.col => {
+                    Some((start, glyph.col + glyph.width, color))
+                }
+                (previous, bg) => {
+                    if let Some(previous) = previous {
+                        push_rect(&mut out, row, previous);
+                    }
+                    bg.map(|bg| (glyph.col, glyph.col + glyph.width, bg))
+                }
+            };
+        }
+        if let Some(run) = run {
+            push_rect(&mut out, row, run);
+        }
+    }
+    out.push_str("</g>\n");
+
+    // Text: one element per run of glyphs sharing text attributes, each
+    // glyph placed on its own cell so the grid survives font differences
+    out.push_str("<g xml:space=\"preserve\">\n");
+    for (row, glyphs) in rows.iter().enumerate() {
+        let y = format!("{:.1}", row as f64 * CELL_HEIGHT + BASELINE);
+        let mut run: Vec<&Glyph> = Vec::new();
+        let mut run_attrs = String::new();
+        for glyph in glyphs {
+            if is_blank(glyph) || glyph.style.add_modifier.contains(Modifier::HIDDEN) {
+                continue;
+            }
+            let attrs = text_attributes(glyph.style, palette);
+            // Per-character x positions only work for single-char glyphs
+            let single = glyph.text.chars().count() == 1;
+            if attrs != run_attrs || !single {
+                push_text(&mut out, &y, &run, &run_attrs);
+                run.clear();
+                run_attrs = attrs;
+            }
+            if single {
+                run.push(glyph);
+            } else {
+                push_text(&mut out, &y, &[glyph], &run_attrs);
+            }
+        }
+        push_text(&mut out, &y, &run, &run_attrs);
+    }
+    out.push_str("</g>\n</svg>\n");
+    out
+}
+
+fn push_rect(out: &mut String, row: usize, (start, end, color): (usize, usize, Rgb)) {
+    let _ = writeln!(
+        out,
+        "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{CELL_HEIGHT}\" fill=\"{}\"/>",
+        start as f64 * CELL_WIDTH,
+        row as f64 * CELL_HEIGHT,
+        (end - start) as f64 * CELL_WIDTH,
+        css_color(color)
+    );
+}
+
+fn push_text(out: &mut String, y: &str, glyphs: &[&Glyph], attrs: &str) {
+    if glyphs.is_empty() {
+        return;
+    }
+    let xs: Vec<String> = glyphs
+        .iter()
+        .map(|g| format!("{:.1}", g.col as f64 * CELL_WIDTH))
+        .collect();
+    let text: String = glyphs.iter().map(|g| g.text).collect();
+    let _ = writeln!(
+        out,
+        "<text x=\"{}\" y=\"{y}\"{attrs}>{}</text>",
+        xs.join(" "),
+        escape_html(&text)
+    );
+}
+
+fn to_html(rows: &[Vec<Glyph>], palette: &Palette) -> String {
+    let background = css_color(palette.background);
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>cltree</title>\n</head>\n\
+         <body style=\"margin:0;background:{background}\">\n\
+         <pre style=\"margin:0;padding:8px;background:{background};color:{};\
+         font-family:{FONT_FAMILY};font-size:{FONT_SIZE}px;line-height:{CELL_HEIGHT}px\">",
+        css_color(palette.foreground)
+    );
+    for (i, glyphs) in rows.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        let mut runs: Vec<(Style, String)> = Vec::new();
+        for glyph in glyphs {
+            let text = if glyph.text.is_empty() {
+                " "
+            } else {
+                glyph.text
+            };
+            match runs.last_mut() {
+                Some((style, run)) if *style == glyph.style => run.push_str(text),
+                _ => runs.push((glyph.style, text.to_string())),
+            }
+        }
+        for (style, text) in runs {
+            let css = css_style(style, |color, fg| palette.rgb(color, fg));
+            if css.is_empty() {
+                out.push_str(&escape_html(&text));
+            } else {
+                let _ = write!(out, "<span style=\"{css}\">{}</span>", escape_html(&text));
+            }
+        }
+    }
+    out.push_str("</pre>\n</body>\n</html>\n");
+    out
+}
@@ -0,0 +1,141 @@
+use regex::{Regex, RegexBuilder};
+
+use crate::vterm::VirtualTerminal;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SearchMode {
+    #[default]
+    Plain,
+    Regex,
+}
+
+/// A match as `(line, col)` cell positions. Lines are [`VirtualTerminal::line`]
+/// indexes plus [`VirtualTerminal::evicted_lines`], so a match stays on its
+/// text as old lines leave the scrollback. `end` is the last matched cell,
+/// inclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchMatch {
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+}
+
+impl SearchMatch {
+    pub fn contains(&self, line: usize, col: usize) -> bool {
+        self.start <= (line, col) && (line, col) <= self.end
+    }
+}
+
+/// Compile a query. Matching is case-insensitive unless the query contains
+/// an uppercase letter (smart case).
+pub fn build_regex(query: &str, mode: SearchMode) -> Result<Regex, regex::Error> {
+    let pattern = match mode {
+        SearchMode::Plain => regex::escape(query),
+        SearchMode::Regex => query.to_string(),
+    };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!query.chars().any(char::is_uppercase))
+        .build()
+}
+
+/// Find every match in the scrollback and screen. Soft-wrapped rows are
+/// joined first, so a match may start on one row and end on the next.
+pub fn find_matches(vt: &VirtualTerminal, regex: &Regex) -> Vec<SearchMatch> {
+    let total = vt.total_lines();
+    let evicted = vt.evicted_lines();
+    let mut matches = Vec::new();
+    let mut text = String::new();
+    // Cell position of every byte in `text`
+    let mut positions: Vec<(usize, usize)> = Vec::new();
+    let mut line = 0;
+    while line < total {
+        text.clear();
+        positions.clear();
+        while let Some(row) = vt.line(line) {
+            for (col, cell) in row.text_cells().iter().enumerate() {
+                // Skip wide-char continuation cells
+                if cell.ch.is_empty() {
+                    continue;
+                }
+                text.push_str(&cell.ch);
+                positions.extend(std::iter::repeat_n((evicted + line, col), cell.ch.len()));
+            }
+            line += 1;
+            if !row.wrapped {
+                break;
+            }
+        }
+        for m in regex.find_iter(&text) {
+            if m.is_empty() {
+                continue;
+            }
+            matches.push(SearchMatch {
+                start: positions[m.start()],
+                end: positions[m.end() - 1],
+            });
+        }
+    }
+    matches
+}
+
+/// State of the search bar opened over the terminal pane
+#[derive(Debug, Default)]
+pub struct Search {
+    pub query: String,
+    pub mode: SearchMode,
+    /// Keys edit the query; once confirmed with Enter they step through matches
+    pub editing: bool,
+    pub matches: Vec<SearchMatch>,
+    pub current: Option<usize>,
+    /// The query is not a valid regex
+    pub invalid: bool,
+}
+
+impl Search {
+    pub fn new() -> Self {
+        Self {
+            editing: true,
+            ..Default::default()
+        }
+    }
+
+    /// Re-run the query and select the last match that starts above the
+    /// bottom of the current view.
+    pub fn update(&mut self, vt: &VirtualTerminal) {
+        self.matches.clear();
+        self.current = None;
+        self.invalid = false;
+        if self.query.is_empty() {
+            return;
+        }
+        match build_regex(&self.query, self.mode) {
+            Ok(regex) => self.matches = find_matches(vt, &regex),
+            Err(_) => {
+                self.invalid = true;
+                return;
+            }
+        }
+        let view_bottom = vt.evicted_lines() + vt.view_top() + vt.rows();
+        self.current = self
+            .matches
+            .iter()
+            .rposition(|m| m.start.0 < view_bottom)
+            .or(self.matches.len().checked_sub(1));
+    }
+
+    /// Move to the previous (older) or next (newer) match, wrapping around
+    pub fn step(&mut self, older: bool) {
+        let len = self.matches.len();
+        if len == 0 {
+            return;
+        }
+        self.current = Some(match self.current {
+            Some(i) if older => (i + len - 1) % len,
+            Some(i) => (i + 1) % len,
+            None => len - 1,
+        });
+    }
+
+    pub fn current_match(&self) -> Option<&SearchMatch> {
+        self.current.and_then(|i| self.matches.get(i))
+    }
+}
@@ -0,0 +1,763 @@
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+
+/// Set in the environment of the cltree a session server runs
+const SESSION_ENV: &str = "CLTREE_SESSION";
+
+/// Whether this cltree runs inside a session server, so a resize may mean a
+/// new client attached and needs the terminal modes and a full repaint
+pub fn is_inner() -> bool {
+    std::env::var_os(SESSION_ENV).is_some()
+}
+
+/// Where session sockets live: `$XDG_RUNTIME_DIR/cltree`, or a directory
+/// only the user can read under the temp dir
+#[cfg(unix)]
+fn socket_dir() -> Result<PathBuf> {
+    use std::os::unix::fs::DirBuilderExt;
+
+    let dir = match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(runtime) => PathBuf::from(runtime).join("cltree"),
+        // SAFETY: getuid has no preconditions and cannot fail
+        None => std::env::temp_dir().join(format!("cltree-{}", unsafe { libc::getuid() })),
+    };
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(&dir)
+        .with_context(|| format!("cannot create {}", dir.display()))?;
+    check_private_dir(&dir)?;
+    Ok(dir)
+}
+
+/// Bail unless `dir` is a real directory, not a symlink, that belongs to
+/// this user and that no one else may use. The temp dir fallback may have
+/// been made by someone else before us, to plant or read sockets.
+#[cfg(unix)]
+fn check_private_dir(dir: &Path) -> Result<()> {
+    use std::os::unix::fs::MetadataExt;
+
+    let meta =
+        std::fs::symlink_metadata(dir).with_context(|| format!("cannot read {}", dir.display()))?;
+    // SAFETY: getuid has no preconditions and cannot fail
+    let uid = unsafe { libc::getuid() };
+    if !meta.is_dir() || meta.uid() != uid || meta.mode() & 0o777 != 0o700 {
+        bail!(
+            "{} must be a directory of yours with mode 700",
+            dir.display()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn socket_path(name: &str) -> Result<PathBuf> {
+    socket_in(&socket_dir()?, name)
+}
+
+/// The socket for session `name` in `dir`. Names are file names: no `/`
+/// and no NUL.
+#[cfg(unix)]
+fn socket_in(dir: &Path, name: &str) -> Result<PathBuf> {
+    if name.is_empty() || name.contains(['/', '\0']) {
+        bail!("invalid session name '{name}'");
+    }
+    Ok(dir.join(format!("{name}.sock")))
+}
+
+#[cfg(unix)]
+pub use unix::{attach, list, serve, start};
+
+#[cfg(not(unix))]
+pub fn start(_name: Option<String>, _args: Vec<String>) -> Result<()> {
+    bail!("detached sessions need a Unix system")
+}
+
+#[cfg(not(unix))]
+pub async fn serve(_name: String, _args: Vec<String>) -> Result<()> {
+    bail!("detached sessions need a Unix system")
+}
+
+#[cfg(not(unix))]
+pub async fn attach(_name: Option<String>) -> Result<()> {
+    bail!("detached sessions need a Unix system")
+}
+
+#[cfg(not(unix))]
+pub fn list() -> Result<()> {
+    bail!("detached sessions need a Unix system")
+}
+
+/// The server runs cltree itself on a PTY and relays it to one client at a
+/// time over a Unix socket, as dtach does; the vterm and tree live in that
+/// inner cltree, and a client that attaches gets a full repaint from it.
+/// Clients send framed messages (a type byte, a big-endian u32 length, the
+/// payload); the server sends the PTY's output as it comes.
+#[cfg(unix)]
+mod unix {
+    use anyhow::{bail, Context, Result};
+    use crossterm::{
+        cursor::Show,
+        event::{DisableBracketedPaste, DisableFocusChange, DisableMouseCapture},
+        execute,
+        terminal::{disable_raw_mode, enable_raw_mode, LeaveAlternateScreen},
+    };
+    use portable_pty::{native_pty_system, CommandBuilder, MasterPty, PtySize};
+    use std::collections::HashMap;
+    use std::fs::{File, TryLockError};
+    use std::io::{self, BufRead, BufReader, Read, Write};
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::os::unix::net::UnixStream as StdUnixStream;
+    use std::os::unix::process::CommandExt;
+    use std::path::Path;
+    use std::process::{Command, Stdio};
+    use std::time::{Duration, Instant};
+    use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+    use tokio::net::unix::OwnedWriteHalf;
+    use tokio::net::{UnixListener, UnixStream};
+    use tokio::signal::unix::{signal, SignalKind};
+    use tokio::sync::mpsc;
+    use tokio::task::JoinHandle;
+
+    use super::{socket_dir, socket_path, SESSION_ENV};
+
+    /// Keys from the client, for the PTY
+    const INPUT: u8 = 0;
+    /// The client's terminal size: cols then rows, as big-endian u16s.
+    /// The first one attaches the client.
+    const RESIZE: u8 = 1;
+    /// Asks for one status line (`attached|detached <cwd>`) instead of
+    /// attaching
+    const INFO: u8 = 2;
+    /// Longest frame accepted from a client
+    const MAX_FRAME: usize = 1 << 20;
+    /// Ctrl+\ detaches the client, as in dtach
+    const DETACH_KEY: u8 = 0x1c;
+    /// Bracketed paste markers: a Ctrl+\ in pasted text does not detach
+    const PASTE_START: &[u8] = b"\x1b[200~";
+    const PASTE_END: &[u8] = b"\x1b[201~";
+    /// PTY reads queued for the attached client. One that falls this far
+    /// behind is dropped rather than holding up the session.
+    const CLIENT_QUEUE: usize = 256;
+
+    enum ClientEvent {
+        Input(u64, Vec<u8>),
+        Resize(u64, u16, u16),
+        Info(u64),
+        Gone(u64),
+    }
+
+    async fn write_frame(
+        writer: &mut (impl AsyncWrite + Unpin),
+        kind: u8,
+        payload: &[u8],
+    ) -> io::Result<()> {
+        let mut frame = Vec::with_capacity(payload.len() + 5);
+        frame.push(kind);
+        frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        frame.extend_from_slice(payload);
+        writer.write_all(&frame).await
+    }
+
+    async fn read_frame(reader: &mut (impl AsyncRead + Unpin)) -> io::Result<(u8, Vec<u8>)> {
+        let kind = reader.read_u8().await?;
+        let len = reader.read_u32().await? as usize;
+        if len > MAX_FRAME {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "frame too long"));
+        }
+        let mut payload = vec![0; len];
+        reader.read_exact(&mut payload).await?;
+        Ok((kind, payload))
+    }
+
+    /// The attached client: its output queue and the task writing it out
+    struct Client {
+        id: u64,
+        output: mpsc::Sender<Vec<u8>>,
+        writer: JoinHandle<()>,
+    }
+
+    impl Client {
+        fn spawn(id: u64, mut writer: OwnedWriteHalf) -> Self {
+            let (output, mut queue) = mpsc::channel::<Vec<u8>>(CLIENT_QUEUE);
+            let writer = tokio::spawn(async move {
+                while let Some(bytes) = queue.recv().await {
+                    if writer.write_all(&bytes).await.is_err() {
+                        break;
+                    }
+                }
+            });
+            Self { id, output, writer }
+        }
+
+        /// Queue output for the client. False when it has gone or its queue
+        /// is full.
+        fn send(&self, bytes: Vec<u8>) -> bool {
+            self.output.try_send(bytes).is_ok()
+        }
+
+        /// Write out what is still queued, giving up after a moment
+        async fn finish(mut self) {
+            let (closed, _) = mpsc::channel(1);
+            drop(std::mem::replace(&mut self.output, closed));
+            let _ = tokio::time::timeout(Duration::from_secs(1), &mut self.writer).await;
+        }
+    }
+
+    impl Drop for Client {
+        /// Stop writing, which hangs the client up
+        fn drop(&mut self) {
+            self.writer.abort();
+        }
+    }
+
+    fn size_payload(cols: u16, rows: u16) -> [u8; 4] {
+        let [c0, c1] = cols.to_be_bytes();
+        let [r0, r1] = rows.to_be_bytes();
+        [c0, c1, r0, r1]
+    }
+
+    /// Whether a server answers on `path`
+    fn is_live(path: &Path) -> bool {
+        StdUnixStream::connect(path).is_ok()
+    }
+
+    /// Lock the session whose socket is `socket`, through a `.lock` file
+    /// next to it; None while someone else holds it. A server keeps the
+    /// lock for as long as it runs, so only the lock holder may bind or
+    /// remove the socket.
+    fn try_lock_session(socket: &Path) -> io::Result<Option<File>> {
+        let file = File::options()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .mode(0o600)
+            .open(socket.with_extension("lock"))?;
+        match file.try_lock() {
+            Ok(()) => Ok(Some(file)),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(e)) => Err(e),
+        }
+    }
+
+    /// Whether the peer on `stream` runs as this user
+    fn is_same_user(stream: &UnixStream) -> bool {
+        // SAFETY: getuid has no preconditions and cannot fail
+        let uid = unsafe { libc::getuid() };
+        stream.peer_cred().is_ok_and(|cred| cred.uid() == uid)
+    }
+
+    /// Names of the sessions with a live server, removing sockets left
+    /// behind by servers that died
+    fn live_sessions() -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        for entry in std::fs::read_dir(socket_dir()?)?.flatten() {
+            let path = entry.path();
+            let Some(name) = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.strip_suffix(".sock"))
+            else {
+                continue;
+            };
+            if is_live(&path) {
+                names.push(name.to_string());
+            } else if let Ok(Some(_lock)) = try_lock_session(&path) {
+                // No server holds the lock, so none is about to bind it
+                let _ = std::fs::remove_file(&path);
+            }
+        }
+        names.sort_by_key(|name| (name.parse::<u64>().unwrap_or(u64::MAX), name.clone()));
+        Ok(names)
+    }
+
+    /// `name`, unless a session in `taken` has it, or else the lowest number
+    /// not taken
+    fn session_name(name: Option<String>, taken: &[String]) -> Result<String> {
+        Ok(match name {
+            Some(name) if taken.contains(&name) => bail!("session '{name}' already exists"),
+            Some(name) => name,
+            None => (0..)
+                .map(|n: u64| n.to_string())
+                .find(|n| !taken.contains(n))
+                .unwrap_or_default(),
+        })
+    }
+
+    /// `cltree --detach`: start a server for `args` in the background under
+    /// `name` (or the lowest free number) and return once it listens
+    pub fn start(name: Option<String>, args: Vec<String>) -> Result<()> {
+        let name = session_name(name, &live_sessions()?)?;
+        let path = socket_path(&name)?;
+
+        let mut server = Command::new(std::env::current_exe()?);
+        server
+            .arg("__server")
+            .arg(&name)
+            .args(&args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        // SAFETY: setsid is async-signal-safe. A session of its own keeps
+        // the server alive when the terminal that started it goes away.
+        unsafe {
+            server.pre_exec(|| {
+                libc::setsid();
+                Ok(())
+            });
+        }
+        let mut server = server.spawn().context("cannot start the session server")?;
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !is_live(&path) {
+            if Instant::now() >= deadline || server.try_wait()?.is_some() {
+                bail!("the session server did not start");
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        println!("Started session {name}; attach with: cltree attach {name}");
+        Ok(())
+    }
+
+    /// `cltree ls`
+    pub fn list() -> Result<()> {
+        let names = live_sessions()?;
+        if names.is_empty() {
+            println!("No sessions");
+        }
+        for name in names {
+            let status = session_info(&socket_path(&name)?).unwrap_or_else(|_| "?".to_string());
+            println!("{name}: {status}");
+        }
+        Ok(())
+    }
+
+    fn session_info(path: &Path) -> io::Result<String> {
+        let mut stream = StdUnixStream::connect(path)?;
+        stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+        stream.write_all(&[INFO, 0, 0, 0, 0])?;
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line)?;
+        Ok(line.trim_end().to_string())
+    }
+
+    /// The hidden `__server` command: run cltree with `args` on a PTY and
+    /// relay it to attached clients until it exits
+    pub async fn serve(name: String, args: Vec<String>) -> Result<()> {
+        let path = socket_path(&name)?;
+        // Held until the server is gone, so no one else binds or removes the
+        // socket meanwhile. Only then is one already there left over.
+        let Some(_lock) = try_lock_session(&path)? else {
+            bail!("session '{name}' already exists");
+        };
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path)
+            .with_context(|| format!("cannot listen on {}", path.display()))?;
+        let result = relay(&name, args, listener, &path).await;
+        let _ = std::fs::remove_file(&path);
+        result
+    }
+
+    async fn relay(
+        name: &str,
+        args: Vec<String>,
+        listener: UnixListener,
+        path: &Path,
+    ) -> Result<()> {
+        let cwd = std::env::current_dir()?;
+        let pty = native_pty_system().openpty(PtySize {
+            rows: 24,
+            cols: 80,
+            pixel_width: 0,
+            pixel_height: 0,
+        })?;
+        let mut cmd = CommandBuilder::new(std::env::current_exe()?);
+        cmd.args(&args);
+        cmd.cwd(&cwd);
+        cmd.env(SESSION_ENV, name);
+        let mut child = pty.slave.spawn_command(cmd)?;
+        // Only the child may hold the slave open, or EOF never arrives
+        drop(pty.slave);
+        let child_pid = child.process_id();
+        let master: Box<dyn MasterPty + Send> = pty.master;
+        let mut pty_writer = master.take_writer()?;
+        let mut pty_reader = master.try_clone_reader()?;
+
+        // Write keys on a thread of their own: the PTY blocks when cltree
+        // is slow to read, and the relay must keep serving meanwhile
+        let (input_tx, input_rx) = std::sync::mpsc::channel::<Vec<u8>>();
+        std::thread::spawn(move || {
+            for bytes in input_rx {
+                if pty_writer.write_all(&bytes).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let (output_tx, mut output_rx) = mpsc::unbounded_channel::<Vec<u8>>();
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 8192];
+            loop {
+                match pty_reader.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => {
+                        if output_tx.send(buf[..n].to_vec()).is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+
+        let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+        // Connections that have not attached yet, and the attached one
+        let mut pending: HashMap<u64, OwnedWriteHalf> = HashMap::new();
+        let mut client: Option<Client> = None;
+        let mut next_id = 0;
+        loop {
+            tokio::select! {
+                output = output_rx.recv() => {
+                    let Some(bytes) = output else {
+                        break;
+                    };
+                    if client.as_ref().is_some_and(|client| !client.send(bytes)) {
+                        client = None;
+                    }
+                }
+                accepted = listener.accept() => {
+                    let Ok((stream, _)) = accepted else {
+                        continue;
+                    };
+                    // The directory is private already; refuse anyone else
+                    // who gets a connection through regardless
+                    if !is_same_user(&stream) {
+                        continue;
+                    }
+                    next_id += 1;
+                    let (reader, writer) = stream.into_split();
+                    pending.insert(next_id, writer);
+                    tokio::spawn(read_client(next_id, reader, event_tx.clone()));
+                }
+                Some(event) = event_rx.recv() => match event {
+                    ClientEvent::Resize(id, cols, rows) => {
+                        let active = client.as_ref().is_some_and(|client| client.id == id);
+                        if !active && !pending.contains_key(&id) {
+                            continue;
+                        }
+                        let _ = master.resize(PtySize {
+                            rows,
+                            cols,
+                            pixel_width: 0,
+                            pixel_height: 0,
+                        });
+                        if let Some(writer) = pending.remove(&id) {
+                            // Dropping the previous client hangs it up
+                            client = Some(Client::spawn(id, writer));
+                            // Make cltree repaint for the new client even if
+                            // the size did not change
+                            if let Some(pid) = child_pid {
+                                // SAFETY: kill has no memory-safety preconditions
+                                unsafe {
+                                    libc::kill(pid as libc::pid_t, libc::SIGWINCH);
+                                }
+                            }
+                        }
+                    }
+                    ClientEvent::Input(id, bytes) => {
+                        if client.as_ref().is_some_and(|client| client.id == id) {
+                            let _ = input_tx.send(bytes);
+                        }
+                    }
+                    ClientEvent::Info(id) => {
+                        if let Some(writer) = pending.remove(&id) {
+                            let status = if client.is_some() { "attached" } else { "detached" };
+                            let line = format!("{status} {}\n", cwd.display());
+                            // One short line fits the socket buffer; never
+                            // wait on a client here
+                            let _ = writer.try_write(line.as_bytes());
+                        }
+                    }
+                    ClientEvent::Gone(id) => {
+                        pending.remove(&id);
+                        if client.as_ref().is_some_and(|client| client.id == id) {
+                            client = None;
+                        }
+                    }
+                },
+            }
+        }
+
+        // Gone before the client is hung up, so it can tell the session ended
+        let _ = std::fs::remove_file(path);
+        if let Some(client) = client {
+            client.finish().await;
+        }
+        let _ = child.wait();
+        Ok(())
+    }
+
+    /// Turn a client's frames into events until it goes away
+    async fn read_client(
+        id: u64,
+        mut reader: tokio::net::unix::OwnedReadHalf,
+        events: mpsc::UnboundedSender<ClientEvent>,
+    ) {
+        while let Ok((kind, payload)) = read_frame(&mut reader).await {
+            let event = match (kind, payload.as_slice()) {
+                (INPUT, _) => ClientEvent::Input(id, payload),
+                (RESIZE, &[c0, c1, r0, r1]) => ClientEvent::Resize(
+                    id,
+                    u16::from_be_bytes([c0, c1]),
+                    u16::from_be_bytes([r0, r1]),
+                ),
+                (INFO, _) => ClientEvent::Info(id),
+                _ => break,
+            };
+            if events.send(event).is_err() {
+                return;
+            }
+        }
+        let _ = events.send(ClientEvent::Gone(id));
+    }
+
+    /// `cltree attach [name]`: relay this terminal to a session until
+    /// Ctrl+\ detaches, the session ends or another client takes it over
+    pub async fn attach(name: Option<String>) -> Result<()> {
+        let name = match name {
+            Some(name) => name,
+            None => {
+                let names = live_sessions()?;
+                match names.as_slice() {
+                    [] => bail!("no sessions; start one with: cltree --detach"),
+                    [name] => name.clone(),
+                    _ => bail!("several sessions, name one of: {}", names.join(", ")),
+                }
+            }
+        };
+        let path = socket_path(&name)?;
+        let stream = UnixStream::connect(&path)
+            .await
+            .with_context(|| format!("no session '{name}'"))?;
+        let (mut reader, mut writer) = stream.into_split();
+
+        // Read stdin on a plain thread: tokio's stdin would keep the
+        // runtime from shutting down while a read is pending
+        let (input_tx, mut input_rx) = mpsc::unbounded_channel::<Vec<u8>>();
+        std::thread::spawn(move || {
+            let mut stdin = io::stdin();
+            let mut buf = [0u8; 4096];
+            while let Ok(n) = stdin.read(&mut buf) {
+                if n == 0 || input_tx.send(buf[..n].to_vec()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        enable_raw_mode()?;
+        let result = relay_terminal(&mut reader, &mut writer, &mut input_rx).await;
+        let _ = disable_raw_mode();
+        let _ = execute!(
+            io::stdout(),
+            LeaveAlternateScreen,
+            DisableMouseCapture,
+            DisableFocusChange,
+            DisableBracketedPaste,
+            Show
+        );
+        match result? {
+            true => println!("[detached from session {name}]"),
+            false if is_live(&path) => println!("[session {name} attached elsewhere]"),
+            false => println!("[session {name} ended]"),
+        }
+        Ok(())
+    }
+
+    /// Returns true when the user detached, false when the server hung up
+    async fn relay_terminal(
+        reader: &mut tokio::net::unix::OwnedReadHalf,
+        writer: &mut OwnedWriteHalf,
+        input: &mut mpsc::UnboundedReceiver<Vec<u8>>,
+    ) -> Result<bool> {
+        let (cols, rows) = crossterm::terminal::size()?;
+        write_frame(writer, RESIZE, &size_payload(cols, rows)).await?;
+        let mut window_change = signal(SignalKind::window_change())?;
+        let mut stdout = tokio::io::stdout();
+        let mut buf = vec![0u8; 16384];
+        let mut in_paste = false;
+        loop {
+            tokio::select! {
+                read = reader.read(&mut buf) => match read {
+                    Ok(0) | Err(_) => return Ok(false),
+                    Ok(n) => {
+                        stdout.write_all(&buf[..n]).await?;
+                        stdout.flush().await?;
+                    }
+                },
+                bytes = input.recv() => {
+                    let Some(bytes) = bytes else {
+                        return Ok(true);
+                    };
+                    if is_detach_key(&bytes, in_paste) {
+                        return Ok(true);
+                    }
+                    in_paste = in_paste_after(&bytes, in_paste);
+                    write_frame(writer, INPUT, &bytes).await?;
+                }
+                _ = window_change.recv() => {
+                    let (cols, rows) = crossterm::terminal::size()?;
+                    write_frame(writer, RESIZE, &size_payload(cols, rows)).await?;
+                }
+            }
+        }
+    }
+
+    /// Whether `bytes`, one read from the terminal, is Ctrl+\ pressed on its
+    /// own. A Ctrl+\ in a paste or among other input goes to the session.
+    fn is_detach_key(bytes: &[u8], in_paste: bool) -> bool {
+        !in_paste && bytes == [DETACH_KEY]
+    }
+
+    /// Whether a bracketed paste is still open after `bytes`
+    fn in_paste_after(bytes: &[u8], in_paste: bool) -> bool {
+        let last = |marker: &[u8]| bytes.windows(marker.len()).rposition(|w| w == marker);
+        match (last(PASTE_START), last(PASTE_END)) {
+            (Some(start), Some(end)) => start > end,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => in_paste,
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::super::{check_private_dir, socket_in};
+        use super::*;
+        use std::os::unix::fs::PermissionsExt;
+
+        #[tokio::test]
+        async fn test_frames_round_trip() {
+            let mut stream = Vec::new();
+            write_frame(&mut stream, RESIZE, &size_payload(120, 40))
+                .await
+                .unwrap();
+            write_frame(&mut stream, INPUT, b"").await.unwrap();
+            assert_eq!(&stream[..5], [RESIZE, 0, 0, 0, 4]);
+
+            let mut reader = stream.as_slice();
+            assert_eq!(
+                read_frame(&mut reader).await.unwrap(),
+                (RESIZE, vec![0, 120, 0, 40])
+            );
+            assert_eq!(read_frame(&mut reader).await.unwrap(), (INPUT, vec![]));
+            let eof = read_frame(&mut reader).await.unwrap_err();
+            assert_eq!(eof.kind(), io::ErrorKind::UnexpectedEof);
+        }
+
+        #[tokio::test]
+        async fn test_frames_over_the_limit_are_rejected() {
+            let payload = vec![7; MAX_FRAME];
+            let mut stream = Vec::new();
+            write_frame(&mut stream, INPUT, &payload).await.unwrap();
+            let (kind, read) = read_frame(&mut stream.as_slice()).await.unwrap();
+            assert_eq!((kind, read.len()), (INPUT, MAX_FRAME));
+
+            // The length is checked before anything is allocated
+            let mut header = vec![INPUT];
+            header.extend_from_slice(&(MAX_FRAME as u32 + 1).to_be_bytes());
+            let err = read_frame(&mut header.as_slice()).await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+            // A frame cut short is an error, not a short payload
+            let truncated = [INPUT, 0, 0, 0, 9, b'a'];
+            assert!(read_frame(&mut truncated.as_slice()).await.is_err());
+        }
+
+        #[test]
+        fn test_socket_names() {
+            let dir = Path::new("/run/cltree");
+            assert_eq!(socket_in(dir, "work").unwrap(), dir.join("work.sock"));
+            for name in ["", "a/b", "../x", "a\0b"] {
+                assert!(socket_in(dir, name).is_err(), "{name:?}");
+            }
+        }
+
+        #[test]
+        fn test_socket_dir_must_be_private() {
+            let root = std::env::temp_dir().join(format!("cltree-test-{}", std::process::id()));
+            let dir = root.join("sockets");
+            std::fs::create_dir_all(&dir).unwrap();
+            let mode = |mode| {
+                std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(mode)).unwrap()
+            };
+
+            mode(0o755);
+            assert!(check_private_dir(&dir).is_err());
+            mode(0o700);
+            check_private_dir(&dir).unwrap();
+            // Not even a link to a private directory will do
+            let link = root.join("link");
+            std::os::unix::fs::symlink(&dir, &link).unwrap();
+            assert!(check_private_dir(&link).is_err());
+            assert!(check_private_dir(&root.join("missing")).is_err());
+            std::fs::remove_dir_all(&root).unwrap();
+        }
+
+        #[test]
+        fn test_one_server_holds_a_session_lock() {
+            let root = std::env::temp_dir().join(format!("cltree-lock-{}", std::process::id()));
+            std::fs::create_dir_all(&root).unwrap();
+            let socket = root.join("work.sock");
+            let lock = try_lock_session(&socket).unwrap().unwrap();
+            assert!(root.join("work.lock").exists());
+            assert!(try_lock_session(&socket).unwrap().is_none());
+            drop(lock);
+            assert!(try_lock_session(&socket).unwrap().is_some());
+            std::fs::remove_dir_all(&root).unwrap();
+        }
+
+        #[tokio::test]
+        async fn test_peers_of_the_same_user_are_accepted() {
+            let (stream, _peer) = UnixStream::pair().unwrap();
+            assert!(is_same_user(&stream));
+        }
+
+        #[test]
+        fn test_session_names() {
+            let taken: Vec<String> = ["0", "1", "3", "work"].map(String::from).to_vec();
+            assert_eq!(session_name(None, &taken).unwrap(), "2");
+            assert_eq!(session_name(None, &[]).unwrap(), "0");
+            assert_eq!(session_name(Some("play".into()), &taken).unwrap(), "play");
+            let err = session_name(Some("work".into()), &taken).unwrap_err();
+            assert!(err.to_string().contains("already exists"), "{err}");
+        }
+
+        #[test]
+        fn test_only_a_lone_ctrl_backslash_detaches() {
+            assert!(is_detach_key(&[DETACH_KEY], false));
+            assert!(!is_detach_key(&[DETACH_KEY], true));
+            assert!(!is_detach_key(b"ab\x1c", false));
+
+            // A paste split over several reads stays a paste until it ends
+            let mut in_paste = in_paste_after(b"\x1b[200~first half", false);
+            assert!(in_paste);
+            assert!(!is_detach_key(&[DETACH_KEY], in_paste));
+            in_paste = in_paste_after(b"second half\x1b[201~", in_paste);
+            assert!(!in_paste);
+            assert!(!in_paste_after(b"\x1b[200~whole\x1b[201~", false));
+            assert!(in_paste_after(b"x\x1b[201~\x1b[200~y", false));
+        }
+
+        #[tokio::test]
+        async fn test_a_stalled_client_is_dropped_when_its_queue_fills() {
+            // The other end never reads
+            let (stream, _peer) = UnixStream::pair().unwrap();
+            let (_, writer) = stream.into_split();
+            let client = Client::spawn(1, writer);
+            // The writer task does not run in between, so nothing drains
+            let queued = (0..CLIENT_QUEUE * 2)
+                .take_while(|_| client.send(vec![0; 1024]))
+                .count();
+            assert_eq!(queued, CLIENT_QUEUE);
+        }
+    }
+}
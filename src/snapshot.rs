@@ -0,0 +1,239 @@
+use ratatui::style::{Color, Style};
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::export::{self, CopyFormat};
+use crate::graphics::ImageSource;
+use crate::vterm::{trim_trailing_blanks, Cell, CursorState, VirtualTerminal};
+
+/// Which lines a [`Snapshot`] covers
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Scope {
+    /// The live screen only (ignores the scroll offset)
+    #[default]
+    Screen,
+    /// Scrollback followed by the screen
+    All,
+}
+
+/// A view of a terminal's contents, renderable as plain text, ANSI
+/// text or a structured cell dump. Meant for asserting whole screens in
+/// tests and for tooling that drives the vterm headlessly.
+pub struct Snapshot<'a> {
+    vt: &'a VirtualTerminal,
+    scope: Scope,
+}
+
+impl<'a> Snapshot<'a> {
+    pub fn new(vt: &'a VirtualTerminal, scope: Scope) -> Self {
+        Self { vt, scope }
+    }
+
+    /// `(first line index, line count)` in [`VirtualTerminal::line`] terms
+    fn span(&self) -> (usize, usize) {
+        let scrollback = self.vt.scrollback().len();
+        match self.scope {
+            Scope::Screen => (scrollback, self.vt.grid().len()),
+            Scope::All => (0, scrollback + self.vt.grid().len()),
+        }
+    }
+
+    /// One entry per row with trailing blanks trimmed. With `keep_styled`,
+    /// blanks with a visible style (a coloured background, reverse video)
+    /// are kept.
+    fn rows(&self, keep_styled: bool) -> Vec<Vec<Cell>> {
+        let (first, count) = self.span();
+        (first..first + count)
+            .map(|idx| {
+                let mut cells = self
+                    .vt
+                    .line(idx)
+                    .map(|row| row.to_vec())
+                    .unwrap_or_default();
+                if keep_styled {
+                    while cells.last().is_some_and(|c| {
+                        (c.ch == " " || c.ch.is_empty())
+                            && self.vt.resolve_style(c.style) == Style::default()
+                    }) {
+                        cells.pop();
+                    }
+                } else {
+                    trim_trailing_blanks(&mut cells);
+                }
+                cells
+            })
+            .collect()
+    }
+
+    /// Row text, one line per row with trailing blanks trimmed. Blank rows
+    /// are kept so the screen height is visible in the output.
+    pub fn text(&self) -> String {
+        export::render(self.vt, &self.rows(false), CopyFormat::Plain)
+    }
+
+    /// Like [`Self::text`] but with SGR sequences for colours and attributes
+    pub fn ansi(&self) -> String {
+        export::render(self.vt, &self.rows(false), CopyFormat::Ansi)
+    }
+
+    /// Structured dump: size, cursor, images, then every row with its text
+    /// and the column ranges of each non-default style. Stable enough for golden
+    /// files. Unlike [`Self::text`], trailing blanks that are visible on screen
+    /// (reverse video, underline, a background colour) are kept.
+    pub fn dump(&self) -> String {
+        let vt = self.vt;
+        let CursorState { x, y, visible } = vt.cursor().clone();
+        let mut out = format!("size {}x{}\n", vt.cols(), vt.rows());
+        let _ = writeln!(
+            out,
+            "cursor {x},{y}{}",
+            if visible { "" } else { " hidden" }
+        );
+
+        let (first, count) = self.span();
+        let scrollback = vt.scrollback().len();
+        let label = |idx: usize| {
+            if idx < scrollback {
+                format!("s{idx}")
+            } else {
+                format!("{}", idx - scrollback)
+            }
+        };
+        for placement in vt.images() {
+            let Some(idx) = placement.line.checked_sub(vt.evicted_lines()) else {
+                continue;
+            };
+            if idx + placement.rows <= first || idx >= first + count {
+                continue;
+            }
+            let protocol = match placement.source {
+                ImageSource::Sixel(_) => "sixel",
+                ImageSource::Kitty(_) => "kitty",
+            };
+            let _ = writeln!(
+                out,
+                "image {},{} {}x{} cells {}x{}px {protocol}",
+                label(idx),
+                placement.col,
+                placement.cols,
+                placement.rows,
+                placement.image.width,
+                placement.image.height
+            );
+        }
+
+        for (i, cells) in self.rows(true).iter().enumerate() {
+            let idx = first + i;
+            let label = label(idx);
+            let wrapped = vt.line(idx).is_some_and(|row| row.wrapped);
+            let _ = writeln!(
+                out,
+                "{label:>3} |{}|{}",
+                crate::vterm::cells_text(cells),
+                if wrapped { " wrapped" } else { "" }
+            );
+            for (start, end, style) in style_spans(vt, cells) {
+                let _ = writeln!(out, "      {start}..{end} {}", describe_style(style));
+            }
+        }
+        out
+    }
+}
+
+/// Column ranges (end exclusive) of cells sharing one non-default style
+fn style_spans(vt: &VirtualTerminal, cells: &[Cell]) -> Vec<(usize, usize, Style)> {
+    let mut spans: Vec<(usize, usize, Style)> = Vec::new();
+    for (col, cell) in cells.iter().enumerate() {
+        let style = vt.resolve_style(cell.style);
+        match spans.last_mut() {
+            Some((_, end, last)) if *end == col && *last == style => *end = col + 1,
+            _ => spans.push((col, col + 1, style)),
+        }
+    }
+    spans.retain(|(_, _, style)| *style != Style::default());
+    spans
+}
+
+fn describe_style(style: Style) -> String {
+    let mut parts = Vec::new();
+    for (name, color) in [("fg", style.fg), ("bg", style.bg)] {
+        match color {
+            None | Some(Color::Reset) => {}
+            Some(Color::Rgb(r, g, b)) => parts.push(format!("{name}=#{r:02x}{g:02x}{b:02x}")),
+            Some(Color::Indexed(idx)) => parts.push(format!("{name}={idx}")),
+            Some(color) => parts.push(format!("{name}={color:?}")),
+        }
+    }
+    for (name, _) in style.add_modifier.iter_names() {
+        parts.push(name.to_ascii_lowercase());
+    }
+    parts.join(" ")
+}
+
+/// Compare `actual` against the golden file at `path`. With
+/// `UPDATE_GOLDEN=1` in the environment the file is (re)written instead.
+///
+/// # Panics
+///
+/// When the file is missing or differs, naming the first differing line.
+pub fn assert_golden(path: impl AsRef<Path>, actual: &str) {
+    let path = path.as_ref();
+    if std::env::var_os("UPDATE_GOLDEN").is_some_and(|v| v != "0") {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).expect("cannot create golden directory");
+        }
+        std::fs::write(path, actual).expect("cannot write golden file");
+        return;
+    }
+    let expected = std::fs::read_to_string(path).unwrap_or_else(|e| {
+        panic!(
+            "cannot read golden file {}: {e}\nrun with UPDATE_GOLDEN=1 to create it",
+            path.display()
+        )
+    });
+    if let Some(message) = first_difference(&expected, actual) {
+        panic!(
+            "snapshot differs from {}\n{message}\nrun with UPDATE_GOLDEN=1 to accept the new output",
+            path.display()
+        );
+    }
+}
+
+/// Compare a snapshot's text against an inline expectation
+///
+/// # Panics
+///
+/// When they differ, naming the first differing line.
+pub fn assert_screen(actual: &str, expected: &str) {
+    if let Some(message) = first_difference(expected, actual) {
+        panic!("screen differs from expectation\n{message}\n--- actual ---\n{actual}");
+    }
+}
+
+fn first_difference(expected: &str, actual: &str) -> Option<String> {
+    if expected == actual {
+        return None;
+    }
+    let (mut exp, mut act) = (expected.lines(), actual.lines());
+    let mut line = 1;
+    loop {
+        match (exp.next(), act.next()) {
+            (Some(e), Some(a)) if e == a => line += 1,
+            (None, None) => return Some("trailing newline differs".to_string()),
+            (e, a) => {
+                return Some(format!(
+                    "line {line}:\n  expected: {:?}\n  actual:   {:?}",
+                    e.unwrap_or("<end>"),
+                    a.unwrap_or("<end>")
+                ))
+            }
+        }
+    }
+}
+
+impl VirtualTerminal {
+    /// Snapshot of the screen, or of scrollback and screen
+    pub fn snapshot(&self, scope: Scope) -> Snapshot<'_> {
+        Snapshot::new(self, scope)
+    }
+}
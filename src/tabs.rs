@@ -0,0 +1,350 @@
+use crate::terminal::TerminalPane;
+
+/// Where the auxiliary pane sits relative to Claude
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SplitDirection {
+    Right,
+    Below,
+}
+
+/// A second terminal (a shell or `--split-command`) next to Claude
+pub struct Split {
+    pub pane: TerminalPane,
+    /// Shown on the pane's border: the shell's name or the command
+    pub title: String,
+    pub direction: SplitDirection,
+    /// Share of the terminal area given to this pane, in percent
+    pub percent: u16,
+    /// Keys and mouse selection go to this pane rather than Claude
+    pub focused: bool,
+}
+
+impl Split {
+    pub fn new(pane: TerminalPane, direction: SplitDirection, title: String) -> Self {
+        Self {
+            pane,
+            title,
+            direction,
+            percent: 40,
+            focused: true,
+        }
+    }
+
+    /// Grow (or with a negative `delta`, shrink) the pane, keeping both
+    /// sides usable
+    pub fn resize(&mut self, delta: i16) {
+        self.percent = self.percent.saturating_add_signed(delta).clamp(20, 80);
+    }
+}
+
+/// One Claude session: its terminal pane, optional split and tab bar entry
+pub struct Tab {
+    pub pane: TerminalPane,
+    pub split: Option<Split>,
+    /// Name given with rename; unnamed tabs are titled after their CWD
+    name: Option<String>,
+    /// Output arrived while the tab was in the background
+    pub activity: bool,
+}
+
+impl Tab {
+    pub fn new(pane: TerminalPane) -> Self {
+        Self {
+            pane,
+            split: None,
+            name: None,
+            activity: false,
+        }
+    }
+
+    pub fn title(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => self
+                .pane
+                .cwd()
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| self.pane.cwd().to_string_lossy().to_string()),
+        }
+    }
+
+    /// The pane keys go to: the split when it has focus, otherwise Claude
+    pub fn focused(&self) -> &TerminalPane {
+        match &self.split {
+            Some(split) if split.focused => &split.pane,
+            _ => &self.pane,
+        }
+    }
+
+    pub fn focused_mut(&mut self) -> &mut TerminalPane {
+        match &mut self.split {
+            Some(split) if split.focused => &mut split.pane,
+            _ => &mut self.pane,
+        }
+    }
+
+    /// The pane that does not have focus, when the tab is split
+    pub fn unfocused_mut(&mut self) -> Option<&mut TerminalPane> {
+        match &mut self.split {
+            Some(split) if split.focused => Some(&mut self.pane),
+            Some(split) => Some(&mut split.pane),
+            None => None,
+        }
+    }
+
+    /// Claude's pane followed by the split's, if any
+    pub fn panes(&self) -> impl Iterator<Item = &TerminalPane> {
+        std::iter::once(&self.pane).chain(self.split.as_ref().map(|split| &split.pane))
+    }
+
+    pub fn panes_mut(&mut self) -> impl Iterator<Item = &mut TerminalPane> {
+        std::iter::once(&mut self.pane).chain(self.split.as_mut().map(|split| &mut split.pane))
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Set the tab's name; a blank name goes back to following the CWD
+    pub fn rename(&mut self, name: &str) {
+        let name = name.trim();
+        self.name = (!name.is_empty()).then(|| name.to_string());
+    }
+}
+
+/// The open tabs, in tab bar order. There is always at least one.
+pub struct Tabs {
+    tabs: Vec<Tab>,
+    active: usize,
+}
+
+impl Tabs {
+    pub fn new(first: TerminalPane) -> Self {
+        Self {
+            tabs: vec![Tab::new(first)],
+            active: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tabs.len()
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    pub fn active(&self) -> &Tab {
+        &self.tabs[self.active]
+    }
+
+    pub fn active_mut(&mut self) -> &mut Tab {
+        &mut self.tabs[self.active]
+    }
+
+    /// The leftmost tab: the one cltree started with, unless it was closed
+    pub fn first(&self) -> &Tab {
+        &self.tabs[0]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Tab> {
+        self.tabs.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Tab> {
+        self.tabs.iter_mut()
+    }
+
+    /// Add a tab right after the active one and switch to it
+    pub fn open(&mut self, pane: TerminalPane) {
+        self.active = (self.active + 1).min(self.tabs.len());
+        self.tabs.insert(self.active, Tab::new(pane));
+    }
+
+    /// Switch to tab `index`. Returns false if there is no such tab.
+    pub fn select(&mut self, index: usize) -> bool {
+        if index >= self.tabs.len() {
+            return false;
+        }
+        self.active = index;
+        self.tabs[index].activity = false;
+        true
+    }
+
+    /// Switch to the next (or previous) tab, wrapping around
+    pub fn cycle(&mut self, forward: bool) {
+        let len = self.tabs.len();
+        let index = if forward {
+            (self.active + 1) % len
+        } else {
+            (self.active + len - 1) % len
+        };
+        self.select(index);
+    }
+
+    /// Close tab `index`, ending its session, unless it is the last tab.
+    /// Returns whether it was closed.
+    pub fn close(&mut self, index: usize) -> bool {
+        if self.tabs.len() <= 1 || index >= self.tabs.len() {
+            return false;
+        }
+        self.tabs.remove(index);
+        if self.active > index || self.active == self.tabs.len() {
+            self.active -= 1;
+        }
+        self.tabs[self.active].activity = false;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::Profile;
+    use crate::terminal::TerminalSettings;
+    use std::path::Path;
+
+    /// A pane whose command cannot start, so no process is spawned. `name`
+    /// becomes its CWD and so the tab's title.
+    fn pane(name: &str) -> TerminalPane {
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let profile = Profile::command("/nonexistent/cltree-test");
+        let cwd = Path::new("/tmp").join(name);
+        TerminalPane::new(&cwd, &profile, &[], &TerminalSettings::default(), tx).unwrap()
+    }
+
+    /// Tabs titled `a`, `b`, ... with the first one active
+    fn tabs(names: &[&str]) -> Tabs {
+        let mut tabs = Tabs::new(pane(names[0]));
+        for name in &names[1..] {
+            tabs.open(pane(name));
+        }
+        tabs.select(0);
+        tabs
+    }
+
+    fn titles(tabs: &Tabs) -> Vec<String> {
+        tabs.iter().map(Tab::title).collect()
+    }
+
+    #[test]
+    fn test_open_inserts_after_the_active_tab() {
+        let mut tabs = tabs(&["a", "b"]);
+        tabs.open(pane("c"));
+        assert_eq!(titles(&tabs), ["a", "c", "b"]);
+        assert_eq!(tabs.active_index(), 1);
+    }
+
+    #[test]
+    fn test_close_before_at_and_after_the_active_tab() {
+        let mut t = tabs(&["a", "b", "c", "d"]);
+        t.select(2);
+        // Before: the active tab keeps its place in the list
+        assert!(t.close(0));
+        assert_eq!(t.active().title(), "c");
+        // After: nothing moves
+        assert!(t.close(2));
+        assert_eq!(t.active().title(), "c");
+        // At: the tab that slides into its place becomes active
+        t.open(pane("e"));
+        t.select(0);
+        assert!(t.close(0));
+        assert_eq!(titles(&t), ["c", "e"]);
+        assert_eq!(t.active().title(), "c");
+        // At the end: the new last tab becomes active
+        t.select(1);
+        assert!(t.close(1));
+        assert_eq!(t.active().title(), "c");
+    }
+
+    #[test]
+    fn test_close_keeps_the_last_tab() {
+        let mut t = tabs(&["a"]);
+        assert!(!t.close(0));
+        assert!(!t.close(1));
+        assert_eq!(t.len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_close_ends_the_tabs_command() {
+        use std::time::{Duration, Instant};
+
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let sleep = TerminalPane::new(
+            Path::new("/tmp"),
+            &Profile::command("sleep"),
+            &["30".to_string()],
+            &TerminalSettings::default(),
+            tx,
+        )
+        .unwrap();
+        let pid = sleep.child_pid().unwrap() as libc::pid_t;
+        let mut t = tabs(&["a"]);
+        t.open(sleep);
+        assert!(t.close(1));
+
+        // Hung up, the command exits and the reader thread reaps it
+        let deadline = Instant::now() + Duration::from_secs(5);
+        // SAFETY: signal 0 only checks that the process exists
+        while unsafe { libc::kill(pid, 0) } == 0 {
+            assert!(Instant::now() < deadline, "sleep still running");
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    #[test]
+    fn test_cycle_wraps_around_and_clears_activity() {
+        let mut t = tabs(&["a", "b", "c"]);
+        t.cycle(false);
+        assert_eq!(t.active_index(), 2);
+        t.iter_mut().next().unwrap().activity = true;
+        t.cycle(true);
+        assert_eq!(t.active_index(), 0);
+        assert!(!t.active().activity);
+    }
+
+    #[test]
+    fn test_split_resize_clamps_both_sides() {
+        let mut split = Split::new(pane("shell"), SplitDirection::Right, "sh".into());
+        split.resize(25);
+        assert_eq!(split.percent, 65);
+        split.resize(i16::MAX);
+        assert_eq!(split.percent, 80);
+        split.resize(i16::MIN);
+        assert_eq!(split.percent, 20);
+    }
+
+    #[test]
+    fn test_focus_follows_the_split() {
+        let cwd = |pane: &TerminalPane| pane.cwd().to_path_buf();
+        let mut tab = Tab::new(pane("claude"));
+        assert_eq!(cwd(tab.focused()), Path::new("/tmp/claude"));
+        assert!(tab.unfocused_mut().is_none());
+
+        // A new split takes focus
+        tab.split = Some(Split::new(
+            pane("shell"),
+            SplitDirection::Below,
+            "sh".into(),
+        ));
+        assert_eq!(cwd(tab.focused()), Path::new("/tmp/shell"));
+        assert_eq!(cwd(tab.focused_mut()), Path::new("/tmp/shell"));
+        assert_eq!(cwd(tab.unfocused_mut().unwrap()), Path::new("/tmp/claude"));
+
+        tab.split.as_mut().unwrap().focused = false;
+        assert_eq!(cwd(tab.focused()), Path::new("/tmp/claude"));
+        assert_eq!(cwd(tab.focused_mut()), Path::new("/tmp/claude"));
+        assert_eq!(cwd(tab.unfocused_mut().unwrap()), Path::new("/tmp/shell"));
+    }
+
+    #[test]
+    fn test_select_rejects_missing_tabs() {
+        let mut t = tabs(&["a", "b"]);
+        assert!(!t.select(2));
+        assert_eq!(t.active_index(), 0);
+        assert!(t.select(1));
+        assert_eq!(t.active_index(), 1);
+    }
+}
@@ -1,28 +1,116 @@
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
-use portable_pty::{native_pty_system, CommandBuilder, PtyPair, PtySize};
-use std::io::{Read, Write};
+use portable_pty::{native_pty_system, CommandBuilder, MasterPty, PtySize};
+use std::fmt;
+use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, MutexGuard};
 use std::thread;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
+use crate::app::Selection;
+use crate::asciicast::Recorder;
+use crate::copy_mode::{self, Pos};
+use crate::cwd::{CwdTracker, PaneState};
+use crate::export::{self, CopyFormat};
+use crate::graphics::ImageMode;
+use crate::palette::Palette;
+use crate::profile::Profile;
+use crate::unicode::AmbiguousWidth;
 use crate::vterm::VirtualTerminal;
 
+/// How a child process ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChildExit {
+    Code(i32),
+    /// Killed by this signal
+    Signal(i32),
+}
+
+impl ChildExit {
+    pub fn success(self) -> bool {
+        self == ChildExit::Code(0)
+    }
+
+    /// Exit code for cltree to pass on, shell style: 128 + n for signal n
+    pub fn code(self) -> i32 {
+        match self {
+            ChildExit::Code(code) => code,
+            ChildExit::Signal(signal) => 128 + signal,
+        }
+    }
+}
+
+impl fmt::Display for ChildExit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChildExit::Code(code) => write!(f, "exited with code {code}"),
+            ChildExit::Signal(signal) => write!(f, "was killed by signal {signal}"),
+        }
+    }
+}
+
+/// Flags the reader thread raises for the UI thread
+#[derive(Default)]
+struct ChildStatus {
+    exited: AtomicBool,
+    /// Wait status, once the child has been reaped
+    exit_status: Mutex<Option<ChildExit>>,
+    /// The child wrote something since the UI last looked
+    new_output: AtomicBool,
+}
+
 /// RAII guard that ensures the child process is waited on when dropped,
 /// preventing zombie processes even if the reader thread panics.
 struct ChildGuard {
     child: Box<dyn portable_pty::Child + Send>,
-    exited: Arc<AtomicBool>,
+    status: Arc<ChildStatus>,
 }
 
 impl Drop for ChildGuard {
     fn drop(&mut self) {
-        self.exited.store(true, Ordering::SeqCst);
-        let _ = self.child.wait();
+        let status = wait_child(&mut self.child);
+        *lock_or_recover(&self.status.exit_status) = status;
+        self.status.exited.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Wait for `child` to exit. portable-pty only keeps a description of the
+/// signal that killed a child, so on Unix we reap it ourselves to get the
+/// number.
+#[cfg(unix)]
+fn wait_child(child: &mut Box<dyn portable_pty::Child + Send>) -> Option<ChildExit> {
+    use std::os::unix::process::ExitStatusExt;
+
+    let pid = child.process_id()? as libc::pid_t;
+    let mut raw = 0;
+    loop {
+        // SAFETY: `raw` is a valid out-pointer for the duration of the call
+        if unsafe { libc::waitpid(pid, &mut raw, 0) } == pid {
+            break;
+        }
+        if io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+            return None;
+        }
     }
+    let status = std::process::ExitStatus::from_raw(raw);
+    Some(match status.signal() {
+        Some(signal) => ChildExit::Signal(signal),
+        None => ChildExit::Code(status.code().unwrap_or(1)),
+    })
+}
+
+#[cfg(not(unix))]
+fn wait_child(child: &mut Box<dyn portable_pty::Child + Send>) -> Option<ChildExit> {
+    let status = child.wait().ok()?;
+    Some(ChildExit::Code(status.exit_code() as i32))
 }
 
+/// How long a closed pane's command has to exit after SIGHUP before it
+/// is killed
+const HANGUP_GRACE: Duration = Duration::from_secs(1);
+
 /// Lock a mutex, recovering from poison (prior thread panic).
 fn lock_or_recover<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
     mutex
@@ -30,76 +118,158 @@ fn lock_or_recover<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
         .unwrap_or_else(|poisoned| poisoned.into_inner())
 }
 
+/// Emulation settings applied to every terminal pane's vterm.
+#[derive(Clone, Debug, Default)]
+pub struct TerminalSettings {
+    pub ambiguous_width: AmbiguousWidth,
+    pub palette: Palette,
+    /// Write the session to this asciicast v2 file
+    pub record: Option<PathBuf>,
+    /// How sixel and kitty images are drawn
+    pub images: ImageMode,
+    /// Host cell size in pixels, when the host terminal reports it
+    pub cell_size: Option<(usize, usize)>,
+}
+
+impl TerminalSettings {
+    fn new_vterm(&self, cols: usize, rows: usize) -> VirtualTerminal {
+        let mut vt = VirtualTerminal::new(cols, rows);
+        vt.set_ambiguous_width(self.ambiguous_width);
+        vt.set_palette(self.palette.clone());
+        if let Some((width, height)) = self.cell_size {
+            vt.set_cell_size(width, height);
+        }
+        vt
+    }
+}
+
 pub struct TerminalPane {
-    pty_pair: Option<PtyPair>,
+    pty_master: Option<Box<dyn MasterPty + Send>>,
     pty_writer: Arc<Mutex<Option<Box<dyn Write + Send>>>>,
     vterm: Arc<Mutex<VirtualTerminal>>,
     cwd: PathBuf,
     child_pid: Option<u32>,
-    process_exited: Arc<AtomicBool>,
+    status: Arc<ChildStatus>,
     last_cols: u16,
     last_rows: u16,
-    // Debounce: pending CWD change must be detected consistently before applying
-    pending_cwd: Option<PathBuf>,
-    pending_cwd_count: u32,
+    cwd_tracker: CwdTracker,
+    recorder: Option<Arc<Recorder>>,
+    /// What the pane runs, for restarts; `args` follow the profile's own
+    profile: Profile,
+    args: Vec<String>,
+    pty_tx: mpsc::UnboundedSender<()>,
+    /// The exit message has been written to the vterm
+    exit_reported: bool,
 }
 
 impl TerminalPane {
+    /// A pane running `profile` with `args` added to the profile's own.
+    /// If it cannot be started, the pane shows why.
     pub fn new(
         cwd: &Path,
-        claude_args: &[String],
+        profile: &Profile,
+        args: &[String],
+        settings: &TerminalSettings,
         pty_tx: mpsc::UnboundedSender<()>,
     ) -> anyhow::Result<Self> {
-        let vterm = Arc::new(Mutex::new(VirtualTerminal::new(80, 24)));
-        let process_exited = Arc::new(AtomicBool::new(false));
-
-        let pty_writer: Arc<Mutex<Option<Box<dyn Write + Send>>>> = Arc::new(Mutex::new(None));
-
-        // Try to create PTY and spawn claude process
-        let (pty_pair, child_pid) = match Self::try_spawn_claude(
-            cwd,
-            &vterm,
-            claude_args,
-            &process_exited,
-            pty_tx,
-            &pty_writer,
-        ) {
-            Ok((pair, pid)) => (Some(pair), pid),
-            Err(e) => {
-                // Store error message in vterm so user can see it
-                let msg = format!(
-                    "Failed to start Claude Code: {}\r\n\r\n\
-                     Make sure 'claude' CLI is installed and in your PATH.\r\n\
-                     Install: npm install -g @anthropic-ai/claude-code\r\n",
-                    e
-                );
-                lock_or_recover(&vterm).feed(msg.as_bytes());
-                (None, None)
-            }
+        let recorder = match settings.record {
+            Some(ref path) => Some(Arc::new(Recorder::create(path, &profile.term)?)),
+            None => None,
         };
-
-        Ok(Self {
-            pty_pair,
-            pty_writer,
-            vterm,
+        let mut pane = Self {
+            pty_master: None,
+            pty_writer: Arc::new(Mutex::new(None)),
+            vterm: Arc::new(Mutex::new(settings.new_vterm(80, 24))),
             cwd: cwd.to_path_buf(),
-            child_pid,
-            process_exited,
+            child_pid: None,
+            status: Arc::new(ChildStatus::default()),
             last_cols: 80,
             last_rows: 24,
-            pending_cwd: None,
-            pending_cwd_count: 0,
-        })
+            cwd_tracker: CwdTracker::from_hints(&profile.cwd)?,
+            recorder,
+            profile: profile.clone(),
+            args: args.to_vec(),
+            pty_tx,
+            exit_reported: false,
+        };
+
+        if let Err(e) = pane.start() {
+            // Store error message in vterm so user can see it
+            let mut msg = format!("Failed to start {}: {e}\r\n", profile.title);
+            if let Some(ref hint) = profile.install_hint {
+                msg.push_str(&format!("\r\n{hint}\r\n"));
+            }
+            lock_or_recover(&pane.vterm).feed(msg.as_bytes());
+        }
+        Ok(pane)
     }
 
-    fn try_spawn_claude(
-        cwd: &Path,
+    /// Spawn the pane's command in its CWD on a fresh PTY of the pane's size
+    fn start(&mut self) -> anyhow::Result<()> {
+        let profile = &self.profile;
+        let mut cmd = CommandBuilder::new(&profile.command);
+        cmd.args(&profile.args);
+        cmd.args(&self.args);
+        cmd.cwd(&self.cwd);
+        cmd.env("TERM", &profile.term);
+        for (name, value) in &profile.env {
+            cmd.env(name, value);
+        }
+        for name in &profile.env_remove {
+            cmd.env_remove(name);
+        }
+        let status = Arc::new(ChildStatus::default());
+        let (pty_master, child_pid) = Self::try_spawn(
+            cmd,
+            &self.vterm,
+            &status,
+            self.pty_tx.clone(),
+            &self.pty_writer,
+            self.recorder.clone(),
+        )?;
+        let _ = pty_master.resize(PtySize {
+            rows: self.last_rows,
+            cols: self.last_cols,
+            pixel_width: 0,
+            pixel_height: 0,
+        });
+        self.pty_master = Some(pty_master);
+        self.child_pid = child_pid;
+        self.status = status;
+        self.exit_reported = false;
+        Ok(())
+    }
+
+    /// Run the command again with `args` after it exited, in the same
+    /// vterm so the scrollback is kept
+    pub fn restart(&mut self, args: Vec<String>) -> anyhow::Result<()> {
+        self.pty_master.take();
+        self.args = args;
+        self.start()
+    }
+
+    /// Arguments the command was (last) started with, after the profile's
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    /// How the child ended, once it has been reaped
+    pub fn exit_status(&self) -> Option<ChildExit> {
+        *lock_or_recover(&self.status.exit_status)
+    }
+
+    fn try_spawn(
+        mut cmd: CommandBuilder,
         vterm: &Arc<Mutex<VirtualTerminal>>,
-        claude_args: &[String],
-        process_exited: &Arc<AtomicBool>,
+        status: &Arc<ChildStatus>,
         pty_tx: mpsc::UnboundedSender<()>,
         pty_writer: &Arc<Mutex<Option<Box<dyn Write + Send>>>>,
-    ) -> anyhow::Result<(PtyPair, Option<u32>)> {
+        recorder: Option<Arc<Recorder>>,
+    ) -> anyhow::Result<(Box<dyn MasterPty + Send>, Option<u32>)> {
         // Create PTY
         let pty_system = native_pty_system();
         let pty_pair = pty_system.openpty(PtySize {
@@ -109,28 +279,19 @@ impl TerminalPane {
             pixel_height: 0,
         })?;
 
-        // Spawn claude process (CLTREE_COMMAND env var overrides the default)
-        let command = std::env::var("CLTREE_COMMAND").unwrap_or_else(|_| "claude".to_string());
-        let mut cmd = CommandBuilder::new(&command);
-        cmd.cwd(cwd);
-        for arg in claude_args {
-            cmd.arg(arg);
-        }
-        cmd.env("TERM", "xterm-256color");
         // Inherit essential environment variables for Claude CLI to work in VHS/PTY environments
-        if let Ok(path) = std::env::var("PATH") {
-            cmd.env("PATH", path);
-        }
-        if let Ok(home) = std::env::var("HOME") {
-            cmd.env("HOME", home);
-        }
-        if let Ok(lang) = std::env::var("LANG") {
-            cmd.env("LANG", lang);
+        for name in ["PATH", "HOME", "LANG"] {
+            if cmd.get_env(name).is_none() {
+                if let Ok(value) = std::env::var(name) {
+                    cmd.env(name, value);
+                }
+            }
         }
-        // Remove CLAUDECODE env var to allow nested Claude sessions
-        cmd.env_remove("CLAUDECODE");
 
         let child = pty_pair.slave.spawn_command(cmd)?;
+        // Only the child may hold the slave open, or the reader never sees
+        // EOF when it exits
+        drop(pty_pair.slave);
 
         // Get child PID before moving child into the thread
         let child_pid = child.process_id();
@@ -144,20 +305,23 @@ impl TerminalPane {
         // Read output in background thread
         let mut reader = pty_pair.master.try_clone_reader()?;
         let vterm_clone = Arc::clone(vterm);
-        let exited_clone = Arc::clone(process_exited);
+        let status_clone = Arc::clone(status);
         let writer_clone = Arc::clone(pty_writer);
 
         thread::spawn(move || {
             // ChildGuard ensures wait() is called even on panic
             let _guard = ChildGuard {
                 child,
-                exited: exited_clone,
+                status: Arc::clone(&status_clone),
             };
             let mut buf = [0u8; 4096];
             loop {
                 match reader.read(&mut buf) {
                     Ok(0) => break,
                     Ok(n) => {
+                        if let Some(ref recorder) = recorder {
+                            recorder.output(&buf[..n]);
+                        }
                         let mut vt = lock_or_recover(&vterm_clone);
                         vt.feed(&buf[..n]);
                         // Flush any DSR/CPR responses back to the PTY
@@ -173,6 +337,7 @@ impl TerminalPane {
                                 }
                             }
                         }
+                        status_clone.new_output.store(true, Ordering::SeqCst);
                         let _ = pty_tx.send(());
                     }
                     Err(e) => {
@@ -184,7 +349,7 @@ impl TerminalPane {
             // ChildGuard::drop will set exited flag and wait for child
         });
 
-        Ok((pty_pair, child_pid))
+        Ok((pty_pair.master, child_pid))
     }
 
     pub fn cwd(&self) -> &Path {
@@ -192,128 +357,104 @@ impl TerminalPane {
     }
 
     pub fn tick(&mut self) {
-        // 1. Try OSC 7 first (shell-reported CWD) — only apply if deeper or same
-        //    (prevents flickering from stale reports)
-        if let Ok(vt) = self.vterm.lock() {
-            if let Some(reported) = vt.reported_cwd() {
-                if reported != self.cwd {
-                    let new_depth = reported.components().count();
-                    let cur_depth = self.cwd.components().count();
-                    if new_depth >= cur_depth {
-                        self.cwd = reported.to_path_buf();
-                        self.pending_cwd = None;
-                        self.pending_cwd_count = 0;
-                        return;
-                    }
-                }
-            }
+        if self.is_process_exited() {
+            self.report_exit();
+            return;
         }
 
-        // 2. Try OS process CWD — only apply if deeper or same
-        if let Some(pid) = self.child_pid {
-            if let Some(proc_cwd) = get_process_cwd(pid) {
-                if proc_cwd != self.cwd {
-                    let new_depth = proc_cwd.components().count();
-                    let cur_depth = self.cwd.components().count();
-                    if new_depth >= cur_depth {
-                        self.cwd = proc_cwd;
-                        self.pending_cwd = None;
-                        self.pending_cwd_count = 0;
-                        return;
-                    }
-                }
+        let vterm = lock_or_recover(&self.vterm);
+        let pane = PaneState {
+            vterm: &vterm,
+            pid: self.child_pid,
+        };
+        if let Some(cwd) = self.cwd_tracker.tick(&self.cwd, &pane) {
+            drop(vterm);
+            self.cwd = cwd;
+        }
+    }
+
+    /// The child's PID while it runs
+    pub fn child_pid(&self) -> Option<u32> {
+        self.child_pid.filter(|_| !self.is_process_exited())
+    }
+
+    /// How the pane's directory was worked out, for the debug overlay
+    pub fn cwd_tracker(&self) -> &CwdTracker {
+        &self.cwd_tracker
+    }
+
+    /// Put the pane back into a sane state and say how the child ended, once
+    fn report_exit(&mut self) {
+        if self.exit_reported {
+            return;
+        }
+        self.exit_reported = true;
+        let how = match self.exit_status() {
+            Some(status) => status.to_string(),
+            None => "exited".to_string(),
+        };
+        // Leave the alternate screen, reset the scroll region, attributes,
+        // focus reporting and cursor, then print the notice below the output
+        let mut vt = lock_or_recover(&self.vterm);
+        vt.feed(b"\x1b[?1049l\x1b7\x1b[r\x1b8\x1b[0m\x1b[?1004l\x1b[?25h\x1b[0 q");
+        if vt.cursor().x > 0 {
+            vt.feed(b"\r\n");
+        }
+        let msg = format!("\x1b[2m[{} {how}]\x1b[0m\r\n", self.profile.title);
+        vt.feed(msg.as_bytes());
+    }
+
+    /// Send `signal` to the child's process group, which portable-pty
+    /// makes a session of its own, so helpers it started get it too
+    #[cfg(unix)]
+    pub fn signal(&self, signal: i32) {
+        if let (Some(pid), false) = (self.child_pid, self.is_process_exited()) {
+            // SAFETY: kill(2) has no memory-safety preconditions
+            unsafe {
+                libc::kill(-(pid as libc::pid_t), signal);
             }
         }
+    }
 
-        // 3. Fall back to vterm buffer scanning (for Claude Code UI which doesn't cd)
-        if let Ok(vt) = self.vterm.lock() {
-            // Scan vterm buffer for CWD path displayed by Claude Code.
-            // Collect ALL valid paths and pick the deepest (most specific) one.
-            let home = dirs::home_dir().unwrap_or_default();
-            let mut best_path: Option<PathBuf> = None;
-            let mut best_depth: usize = 0;
-
-            for row in 0..8.min(vt.rows()) {
-                let text = vt.row_text(row);
-                let trimmed = text.trim();
-
-                let resolved = if let Some(pos) = trimmed.find("~/") {
-                    let rest = &trimmed[pos + 2..];
-                    let path_part: String = rest
-                        .chars()
-                        .take_while(|c| {
-                            c.is_alphanumeric() || matches!(*c, '/' | '.' | '-' | '_' | '+' | '@')
-                        })
-                        .collect();
-                    if path_part.is_empty() {
-                        None
-                    } else {
-                        Some(home.join(path_part.trim_end_matches('/')))
-                    }
-                } else if let Some(pos) = trimmed.find('/') {
-                    let path_part: String = trimmed[pos..]
-                        .chars()
-                        .take_while(|c| {
-                            c.is_alphanumeric() || matches!(*c, '/' | '.' | '-' | '_' | '+' | '@')
-                        })
-                        .collect();
-                    if path_part.len() > 1 {
-                        Some(PathBuf::from(path_part.trim_end_matches('/')))
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                };
-
-                if let Some(path) = resolved {
-                    if path.is_dir() {
-                        let depth = path.components().count();
-                        if depth > best_depth {
-                            best_depth = depth;
-                            best_path = Some(path);
-                        }
+    #[cfg(not(unix))]
+    pub fn signal(&self, _signal: i32) {}
+
+    /// End the pane's session as a closed terminal window would: close our
+    /// ends of the PTY and send SIGHUP to the child's process group, then
+    /// SIGKILL if it is still running after [`HANGUP_GRACE`]. Once the
+    /// child is gone the reader thread sees EOF, reaps it and exits.
+    fn hang_up(&mut self) {
+        *lock_or_recover(&self.pty_writer) = None;
+        self.pty_master.take();
+        #[cfg(unix)]
+        if let (Some(pid), false) = (self.child_pid, self.is_process_exited()) {
+            self.signal(libc::SIGHUP);
+            let status = Arc::clone(&self.status);
+            thread::spawn(move || {
+                let deadline = Instant::now() + HANGUP_GRACE;
+                while Instant::now() < deadline {
+                    if status.exited.load(Ordering::SeqCst) {
+                        return;
                     }
+                    thread::sleep(Duration::from_millis(20));
                 }
-            }
-
-            if let Some(path) = best_path {
-                if path == self.cwd {
-                    // Same as current — stable
-                    self.pending_cwd = None;
-                    self.pending_cwd_count = 0;
-                } else {
-                    let new_depth = path.components().count();
-                    let cur_depth = self.cwd.components().count();
-
-                    if new_depth > cur_depth {
-                        // Deeper path — apply immediately (user navigated deeper)
-                        self.cwd = path;
-                        self.pending_cwd = None;
-                        self.pending_cwd_count = 0;
-                    } else if new_depth < cur_depth {
-                        // Shallower path — require consistent detection to prevent flickering
-                        if self.pending_cwd.as_ref() == Some(&path) {
-                            self.pending_cwd_count += 1;
-                            if self.pending_cwd_count >= 8 {
-                                // ~2 seconds of consistent shallow detection
-                                self.cwd = path;
-                                self.pending_cwd = None;
-                                self.pending_cwd_count = 0;
-                            }
-                        } else {
-                            self.pending_cwd = Some(path);
-                            self.pending_cwd_count = 1;
-                        }
+                if !status.exited.load(Ordering::SeqCst) {
+                    // SAFETY: kill(2) has no memory-safety preconditions
+                    unsafe {
+                        libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
                     }
-                    // Same depth — ignore (stay at current)
                 }
-            }
+            });
         }
     }
 
     pub fn is_process_exited(&self) -> bool {
-        self.process_exited.load(Ordering::SeqCst)
+        self.status.exited.load(Ordering::SeqCst)
+    }
+
+    /// Whether the child wrote anything since the last call
+    pub fn take_new_output(&self) -> bool {
+        self.status.new_output.swap(false, Ordering::SeqCst)
     }
 
     pub fn handle_key(&mut self, key: KeyEvent) {
@@ -594,6 +735,19 @@ impl TerminalPane {
         }
     }
 
+    /// Write raw bytes to the child, as if typed
+    pub fn send_input(&mut self, bytes: &[u8]) {
+        if bytes.is_empty() {
+            return;
+        }
+        if let Ok(mut guard) = self.pty_writer.lock() {
+            if let Some(ref mut writer) = *guard {
+                let _ = writer.write_all(bytes);
+                let _ = writer.flush();
+            }
+        }
+    }
+
     pub fn send_interrupt(&mut self) {
         if let Ok(mut guard) = self.pty_writer.lock() {
             if let Some(ref mut writer) = *guard {
@@ -640,71 +794,97 @@ impl TerminalPane {
         vt.set_scroll_offset(current.saturating_sub(3));
     }
 
-    /// Extract text from a selection range (terminal-local coordinates).
-    /// Coordinates are (col, row) relative to the visible terminal area.
-    pub fn extract_text(&self, start: (u16, u16), end: (u16, u16)) -> String {
-        let vt = lock_or_recover(&self.vterm);
-        let grid = vt.grid();
-        let scrollback = vt.scrollback();
-        let scroll_offset = vt.scroll_offset();
-        let cols = vt.cols();
-        let total_lines = scrollback.len() + grid.len();
-
-        // Normalize start/end so start is before end
-        let (start, end) = if (start.1, start.0) <= (end.1, end.0) {
-            (start, end)
+    /// Scroll so the previous (or next) OSC 133 prompt is the first visible
+    /// line. Jumping forward past the last prompt returns to the live view.
+    pub fn jump_to_prompt(&mut self, forward: bool) {
+        let mut vt = lock_or_recover(&self.vterm);
+        let top = vt.view_top();
+        let prompts = vt.prompt_lines();
+        let target = if forward {
+            prompts.into_iter().find(|&line| line > top)
         } else {
-            (end, start)
+            prompts.into_iter().rev().find(|&line| line < top)
         };
+        match target {
+            Some(line) => vt.scroll_to_line(line),
+            None if forward => vt.set_scroll_offset(0),
+            None => {}
+        }
+    }
 
-        let visible_height = grid.len();
-        // Map screen row to absolute line index in scrollback+grid
-        let abs_line = |screen_row: u16| -> usize {
-            if scroll_offset == 0 {
-                scrollback.len() + screen_row as usize
-            } else {
-                let bottom = total_lines.saturating_sub(scroll_offset);
-                let top = bottom.saturating_sub(visible_height);
-                top + screen_row as usize
-            }
-        };
+    /// Centre `line` in the view, unless it is already visible
+    pub fn reveal_line(&mut self, line: usize) {
+        let mut vt = lock_or_recover(&self.vterm);
+        let top = vt.view_top();
+        let rows = vt.rows();
+        if line < top || line >= top + rows {
+            vt.scroll_to_line(line.saturating_sub(rows / 2));
+        }
+    }
 
-        let start_line = abs_line(start.1);
-        let end_line = abs_line(end.1);
+    /// Scroll as little as possible to bring `line` into view
+    pub fn keep_line_visible(&mut self, line: usize) {
+        let mut vt = lock_or_recover(&self.vterm);
+        let top = vt.view_top();
+        let rows = vt.rows();
+        if line < top {
+            vt.scroll_to_line(line);
+        } else if line >= top + rows {
+            vt.scroll_to_line(line + 1 - rows);
+        }
+    }
 
-        let get_row = |line_idx: usize| -> Option<&Vec<crate::vterm::Cell>> {
-            if line_idx < scrollback.len() {
-                scrollback.get(line_idx)
-            } else {
-                grid.get(line_idx - scrollback.len())
-            }
+    /// Scroll to the last command output block that starts in or above the
+    /// view. Returns a selection over it, together with the block's text.
+    pub fn select_output_block(&mut self) -> Option<(Selection, String)> {
+        let mut vt = lock_or_recover(&self.vterm);
+        let view_bottom = vt.view_top() + vt.rows();
+        let block = vt
+            .output_blocks()
+            .into_iter()
+            .rev()
+            .find(|block| block.start < view_bottom)?;
+        vt.scroll_to_line(block.start);
+        let evicted = vt.evicted_lines();
+        let selection = Selection {
+            start: (evicted + block.start, 0),
+            end: (evicted + block.end - 1, vt.cols().saturating_sub(1)),
         };
+        Some((selection, vt.lines_text(block)))
+    }
 
-        let mut lines = Vec::new();
-        for line_idx in start_line..=end_line {
-            if let Some(row) = get_row(line_idx) {
-                let col_start = if line_idx == start_line {
-                    start.0 as usize
-                } else {
-                    0
-                };
-                let col_end = if line_idx == end_line {
-                    (end.0 as usize + 1).min(cols)
-                } else {
-                    cols
-                };
-                let text: String = row[col_start..col_end.min(row.len())]
-                    .iter()
-                    .map(|c| if c.ch.is_empty() { " " } else { c.ch.as_str() })
-                    .collect();
-                lines.push(text.trim_end().to_string());
-            }
-        }
+    /// Text of the most recent command output block, if any.
+    pub fn last_output_text(&self) -> Option<String> {
+        let vt = lock_or_recover(&self.vterm);
+        let block = vt.output_blocks().pop()?;
+        Some(vt.lines_text(block))
+    }
 
-        lines.join("\n")
+    /// Buffer position (see [`crate::copy_mode::Pos`]) of a cell in the
+    /// visible terminal area.
+    pub fn buffer_pos(&self, col: u16, row: u16) -> Pos {
+        let vt = lock_or_recover(&self.vterm);
+        (
+            vt.evicted_lines() + vt.view_top() + row as usize,
+            (col as usize).min(vt.cols().saturating_sub(1)),
+        )
+    }
+
+    /// Extract the text covered by a selection in `format`, joining
+    /// soft-wrapped rows. Lines evicted from the scrollback since the
+    /// selection was made are skipped.
+    pub fn extract_text(&self, selection: &Selection, format: CopyFormat) -> String {
+        let vt = lock_or_recover(&self.vterm);
+        let (start, end) = selection.ordered();
+        let lines = copy_mode::range_lines(&vt, start, end);
+        export::render(&vt, &lines, format)
     }
 
     pub fn resize(&mut self, cols: u16, rows: u16) {
+        // Before the size check: the first call writes the recording header
+        if let Some(ref recorder) = self.recorder {
+            recorder.resize(cols, rows);
+        }
         if cols == self.last_cols && rows == self.last_rows {
             return;
         }
@@ -712,8 +892,8 @@ impl TerminalPane {
         self.last_rows = rows;
 
         // Resize the PTY
-        if let Some(ref pty_pair) = self.pty_pair {
-            let _ = pty_pair.master.resize(PtySize {
+        if let Some(ref pty_master) = self.pty_master {
+            let _ = pty_master.resize(PtySize {
                 rows,
                 cols,
                 pixel_width: 0,
@@ -728,81 +908,86 @@ impl TerminalPane {
 
 impl Drop for TerminalPane {
     fn drop(&mut self) {
-        // PTY will be cleaned up automatically
-        self.pty_pair.take();
+        self.hang_up();
     }
 }
 
-/// Get the current working directory of a process by PID.
-/// Uses macOS `proc_pidinfo` API or Linux `/proc/PID/cwd`.
-#[cfg(target_os = "macos")]
-fn get_process_cwd(pid: u32) -> Option<PathBuf> {
-    use std::ffi::{c_int, c_void};
-    use std::mem;
-
-    const PROC_PIDVNODEPATHINFO: c_int = 9;
-    const MAXPATHLEN: usize = 1024;
-
-    #[repr(C)]
-    struct VnodeInfoPath {
-        // struct vnode_info (see Darwin sys/proc_info.h: vnode_info is 152 bytes)
-        _vip_vi: [u8; 152],
-        vip_path: [u8; MAXPATHLEN],
-    }
-
-    #[repr(C)]
-    struct ProcVnodePathInfo {
-        pvi_cdir: VnodeInfoPath,
-        _pvi_rdir: VnodeInfoPath,
-    }
-
-    extern "C" {
-        fn proc_pidinfo(
-            pid: c_int,
-            flavor: c_int,
-            arg: u64,
-            buffer: *mut c_void,
-            buffersize: c_int,
-        ) -> c_int;
-    }
-
-    unsafe {
-        let mut info: ProcVnodePathInfo = mem::zeroed();
-        let size = mem::size_of::<ProcVnodePathInfo>() as c_int;
-
-        let ret = proc_pidinfo(
-            pid as c_int,
-            PROC_PIDVNODEPATHINFO,
-            0,
-            &mut info as *mut _ as *mut c_void,
-            size,
-        );
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    /// A pane in /tmp running `sh -c script`
+    fn sh(script: &str) -> TerminalPane {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let profile = Profile::command("sh");
+        let args = ["-c".to_string(), script.to_string()];
+        TerminalPane::new(
+            Path::new("/tmp"),
+            &profile,
+            &args,
+            &TerminalSettings::default(),
+            tx,
+        )
+        .unwrap()
+    }
 
-        if ret != size {
-            return None;
+    /// Wait up to five seconds for `done`
+    fn wait_for(what: &str, mut done: impl FnMut() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !done() {
+            assert!(Instant::now() < deadline, "timed out waiting for {what}");
+            thread::sleep(Duration::from_millis(20));
         }
+    }
 
-        let path_bytes = &info.pvi_cdir.vip_path;
-        let len = path_bytes
-            .iter()
-            .position(|&b| b == 0)
-            .unwrap_or(MAXPATHLEN);
-        let path_str = std::str::from_utf8(&path_bytes[..len]).ok()?;
-
-        if path_str.is_empty() {
-            None
-        } else {
-            Some(PathBuf::from(path_str))
-        }
+    #[test]
+    fn test_child_exit_code_and_message() {
+        assert_eq!(ChildExit::Code(0).code(), 0);
+        assert_eq!(ChildExit::Code(3).code(), 3);
+        assert_eq!(ChildExit::Signal(9).code(), 137);
+        assert!(ChildExit::Code(0).success());
+        assert!(!ChildExit::Code(1).success());
+        assert!(!ChildExit::Signal(15).success());
+        assert_eq!(ChildExit::Code(2).to_string(), "exited with code 2");
+        assert_eq!(ChildExit::Signal(15).to_string(), "was killed by signal 15");
     }
-}
 
-#[cfg(target_os = "linux")]
-fn get_process_cwd(pid: u32) -> Option<PathBuf> {
-    std::fs::read_link(format!("/proc/{}/cwd", pid)).ok()
-}
+    #[cfg(unix)]
+    #[test]
+    fn test_wait_child_reports_codes_and_signals() {
+        let pty = native_pty_system().openpty(PtySize::default()).unwrap();
+        let wait = |script: &str| {
+            let mut cmd = CommandBuilder::new("sh");
+            cmd.args(["-c", script]);
+            let mut child: Box<dyn portable_pty::Child + Send> =
+                pty.slave.spawn_command(cmd).unwrap();
+            wait_child(&mut child)
+        };
+        assert_eq!(wait("exit 0"), Some(ChildExit::Code(0)));
+        assert_eq!(wait("exit 3"), Some(ChildExit::Code(3)));
+        assert_eq!(
+            wait("kill -TERM $$"),
+            Some(ChildExit::Signal(libc::SIGTERM))
+        );
+    }
 
-#[cfg(not(any(target_os = "macos", target_os = "linux")))]
-fn get_process_cwd(_pid: u32) -> Option<PathBuf> {
-    None
+    #[cfg(unix)]
+    #[test]
+    fn test_signal_reaches_the_whole_process_group() {
+        let pane = sh("sleep 30 & echo $!; wait");
+        let text = || pane.vterm_lock().row_text(0);
+        wait_for("the background pid", || {
+            text().trim().parse::<u32>().is_ok()
+        });
+        let sleep: libc::pid_t = text().trim().parse().unwrap();
+
+        pane.signal(libc::SIGTERM);
+        wait_for("sh to exit", || pane.is_process_exited());
+        assert_eq!(pane.exit_status(), Some(ChildExit::Signal(libc::SIGTERM)));
+        // The background sleep is in the group too. It is not our child,
+        // so it goes away once init reaps it.
+        // SAFETY: signal 0 only checks that the process exists
+        wait_for("sleep to exit", || unsafe { libc::kill(sleep, 0) } != 0);
+    }
 }
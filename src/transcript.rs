@@ -0,0 +1,159 @@
+use std::ops::Range;
+use std::path::Path;
+
+use crate::vterm::{cells_text, VirtualTerminal};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TranscriptFormat {
+    #[default]
+    Markdown,
+    Text,
+}
+
+impl TranscriptFormat {
+    /// Format implied by a file name: `.txt`/`.log` or Markdown otherwise
+    pub fn from_path(path: &Path) -> Self {
+        match path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase)
+            .as_deref()
+        {
+            Some("txt" | "text" | "log") => Self::Text,
+            _ => Self::Markdown,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Text => "txt",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TranscriptOptions {
+    pub format: TranscriptFormat,
+    /// Markdown only: start a section at every OSC 133 prompt
+    pub sections: bool,
+}
+
+/// Scrollback and screen as text without styling, soft wraps joined. In
+/// Markdown, indented runs and box-drawn UI are fenced so their layout
+/// survives rendering.
+pub fn render(vt: &VirtualTerminal, options: TranscriptOptions) -> String {
+    let all = 0..vt.total_lines();
+    match options.format {
+        TranscriptFormat::Text => {
+            let mut lines = text_lines(vt, all);
+            trim_blank_edges(&mut lines);
+            lines.iter().map(|line| format!("{line}\n")).collect()
+        }
+        TranscriptFormat::Markdown => {
+            let prompts = vt.prompt_lines();
+            let mut out = String::from("# Transcript\n");
+            if !options.sections || prompts.is_empty() {
+                push_blocks(&mut out, text_lines(vt, all));
+                return out;
+            }
+
+            // Output before the first prompt, then one section per prompt
+            push_blocks(&mut out, text_lines(vt, 0..prompts[0]));
+            for (i, &start) in prompts.iter().enumerate() {
+                let end = prompts.get(i + 1).copied().unwrap_or(all.end);
+                let mut lines = text_lines(vt, start..end).into_iter();
+                let command = lines.next().unwrap_or_default();
+                out.push_str(&format!("\n## {}\n", command.trim()));
+                push_blocks(&mut out, lines.collect());
+            }
+            out
+        }
+    }
+}
+
+/// Logical lines (soft wraps joined) of `lines`, as plain text
+fn text_lines(vt: &VirtualTerminal, lines: Range<usize>) -> Vec<String> {
+    if lines.is_empty() {
+        return Vec::new();
+    }
+    vt.logical_lines(
+        (lines.start, 0),
+        (lines.end - 1, vt.cols().saturating_sub(1)),
+    )
+    .iter()
+    .map(|cells| cells_text(cells))
+    .collect()
+}
+
+fn trim_blank_edges(lines: &mut Vec<String>) {
+    while lines.last().is_some_and(|l| l.trim().is_empty()) {
+        lines.pop();
+    }
+    let leading = lines.iter().take_while(|l| l.trim().is_empty()).count();
+    lines.drain(..leading);
+}
+
+/// Lines whose layout matters: indented text (code, aligned output) and
+/// anything drawn with box characters. Literal fences are fenced too so they
+/// cannot open a block of their own.
+fn is_code_like(line: &str) -> bool {
+    line.starts_with("    ")
+        || line.starts_with('\t')
+        || line.trim_start().starts_with("```")
+        || line.chars().any(|c| ('\u{2500}'..='\u{257f}').contains(&c))
+}
+
+/// Append `lines` as Markdown paragraphs and fenced blocks, separated by
+/// single blank lines
+fn push_blocks(out: &mut String, mut lines: Vec<String>) {
+    trim_blank_edges(&mut lines);
+    let mut blocks: Vec<(bool, Vec<String>)> = Vec::new();
+    let mut blanks = 0;
+    for line in lines {
+        if line.trim().is_empty() {
+            blanks += 1;
+            continue;
+        }
+        let code = is_code_like(&line);
+        match blocks.last_mut() {
+            // Blank lines inside a code run are part of it
+            Some((true, block)) if code => {
+                block.extend(std::iter::repeat_n(String::new(), blanks));
+                block.push(line);
+            }
+            Some((false, block)) if !code && blanks == 0 => block.push(line),
+            _ => blocks.push((code, vec![line])),
+        }
+        blanks = 0;
+    }
+
+    for (code, block) in blocks {
+        out.push('\n');
+        if code {
+            let fence = fence_for(&block);
+            out.push_str(&format!("{fence}\n"));
+            for line in &block {
+                out.push_str(line);
+                out.push('\n');
+            }
+            out.push_str(&format!("{fence}\n"));
+        } else {
+            for line in &block {
+                out.push_str(line.trim_end());
+                out.push('\n');
+            }
+        }
+    }
+}
+
+/// A backtick fence longer than any backtick run inside the block
+fn fence_for(block: &[String]) -> String {
+    let longest = block
+        .iter()
+        .flat_map(|line| line.split(|c| c != '`'))
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
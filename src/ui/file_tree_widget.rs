@@ -1,18 +1,32 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use ratatui::{prelude::*, widgets::StatefulWidget};
 
 use super::FileTreeWidgetState;
 use crate::tree::FileTree;
+use crate::unicode::{self, AmbiguousWidth};
 
 pub struct FileTreeWidget<'a> {
     tree: &'a FileTree,
     cwd: Option<&'a Path>,
+    /// Directories subprocesses are working in
+    busy_dirs: &'a [PathBuf],
+    ambiguous_width: AmbiguousWidth,
 }
 
 impl<'a> FileTreeWidget<'a> {
-    pub fn new(tree: &'a FileTree, cwd: Option<&'a Path>) -> Self {
-        Self { tree, cwd }
+    pub fn new(tree: &'a FileTree, cwd: Option<&'a Path>, ambiguous_width: AmbiguousWidth) -> Self {
+        Self {
+            tree,
+            cwd,
+            busy_dirs: &[],
+            ambiguous_width,
+        }
+    }
+
+    pub fn busy_dirs(mut self, dirs: &'a [PathBuf]) -> Self {
+        self.busy_dirs = dirs;
+        self
     }
 }
 
@@ -21,6 +35,7 @@ impl<'a> StatefulWidget for FileTreeWidget<'a> {
 
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
         let nodes = self.tree.nodes();
+        let policy = self.ambiguous_width;
         let visible_height = area.height as usize;
 
         // Calculate visible range
@@ -41,6 +56,7 @@ impl<'a> StatefulWidget for FileTreeWidget<'a> {
 
             // Check if this node is the CWD
             let is_cwd = self.cwd.is_some_and(|cwd| node.is_dir && node.path == cwd);
+            let is_busy = !is_cwd && node.is_dir && self.busy_dirs.contains(&node.path);
 
             // Clear background for CWD item
             if is_cwd {
@@ -57,6 +73,8 @@ impl<'a> StatefulWidget for FileTreeWidget<'a> {
                     .bg(Color::Rgb(80, 70, 30))
                     .fg(Color::Rgb(255, 220, 100))
                     .bold()
+            } else if is_busy {
+                Style::default().fg(Color::Rgb(130, 190, 255)).bold()
             } else {
                 let color = node.display_color();
                 let mut s = Style::default().fg(color);
@@ -73,17 +91,19 @@ impl<'a> StatefulWidget for FileTreeWidget<'a> {
                 let icon = node.expanded_icon(true);
                 let display = if is_cwd {
                     format!("{}● {}", icon, node.name)
+                } else if is_busy {
+                    format!("{}◆ {}", icon, node.name)
                 } else {
                     format!("{} {}", icon, node.name)
                 };
-                buf.set_string(x_offset, y, &display, node_style);
-                x_offset += unicode_width::UnicodeWidthStr::width(display.as_str()) as u16;
+                unicode::set_string(buf, x_offset, y, &display, node_style, policy);
+                x_offset += unicode::str_width(&display, policy) as u16;
             } else {
                 // Draw ancestor connectors
                 for &ancestor_is_last in &node.connector {
                     let connector_str = if ancestor_is_last { "    " } else { "│   " };
-                    buf.set_string(x_offset, y, connector_str, tree_style);
-                    x_offset += 4;
+                    unicode::set_string(buf, x_offset, y, connector_str, tree_style, policy);
+                    x_offset += unicode::str_width(connector_str, policy) as u16;
                 }
 
                 // Draw this node's branch connector
@@ -92,18 +112,20 @@ impl<'a> StatefulWidget for FileTreeWidget<'a> {
                 } else {
                     "├── "
                 };
-                buf.set_string(x_offset, y, branch, tree_style);
-                x_offset += 4;
+                unicode::set_string(buf, x_offset, y, branch, tree_style, policy);
+                x_offset += unicode::str_width(branch, policy) as u16;
 
                 // Draw icon + name
                 let icon = node.expanded_icon(true);
                 let display = if is_cwd {
                     format!("{}● {}", icon, node.name)
+                } else if is_busy {
+                    format!("{}◆ {}", icon, node.name)
                 } else {
                     format!("{} {}", icon, node.name)
                 };
-                buf.set_string(x_offset, y, &display, node_style);
-                x_offset += unicode_width::UnicodeWidthStr::width(display.as_str()) as u16;
+                unicode::set_string(buf, x_offset, y, &display, node_style, policy);
+                x_offset += unicode::str_width(&display, policy) as u16;
             }
 
             // Truncate if too long
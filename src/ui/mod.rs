@@ -1,14 +1,28 @@
 mod file_tree_widget;
 mod terminal_widget;
 
+use crossterm::{
+    cursor::{MoveTo, SetCursorStyle},
+    execute, queue,
+};
 use ratatui::{
     prelude::*,
-    widgets::{Block, Borders, Paragraph},
+    widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table, TableState},
 };
+use std::io::{self, Write};
+use std::time::Duration;
 
 use crate::app::App;
+use crate::copy_mode::{CopyMode, SelectionKind};
+use crate::cwd::Verdict;
+use crate::graphics::{self, ImageMode, Placement};
+use crate::process::{self, ProcessInfo};
+use crate::search::{Search, SearchMode};
+use crate::tabs::{SplitDirection, Tab};
+use crate::terminal::TerminalPane;
+use crate::vterm::{CursorShape, CursorStyle};
 use file_tree_widget::FileTreeWidget;
-use terminal_widget::TerminalWidget;
+pub use terminal_widget::TerminalWidget;
 
 pub fn draw(frame: &mut Frame, app: &mut App) {
     let size = frame.area();
@@ -22,32 +36,139 @@ pub fn draw(frame: &mut Frame, app: &mut App) {
         ])
         .split(size);
 
-    // Terminal pane (left/main area)
-    let terminal_area = chunks[0];
-    let terminal_block = Block::default()
-        .title(" Claude Code ")
-        .title_style(Style::default().fg(Color::Cyan).bold())
+    // Terminal panes (left/main area): Claude, and a split beside or below it
+    let (claude_area, split_area) = pane_areas(app.tabs.active(), chunks[0]);
+    let split_focused = app
+        .tabs
+        .active()
+        .split
+        .as_ref()
+        .is_some_and(|split| split.focused);
+    // Visual bell: briefly highlight the border when the child rings BEL
+    let border_color = if app.bell.is_flashing() {
+        Color::LightYellow
+    } else if split_focused {
+        Color::DarkGray
+    } else {
+        Color::Cyan
+    };
+    let mut terminal_block = Block::default()
+        .title(terminal_title(app))
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Cyan));
+        .border_style(Style::default().fg(border_color));
+    // Search and copy mode belong to the focused pane
+    let mut mode_bar = match (app.search.as_ref(), app.copy_mode.as_ref()) {
+        (Some(search), _) => Some(search_bar(search)),
+        (None, Some(copy)) => Some(copy_mode_bar(copy)),
+        (None, None) => None,
+    };
+    if let Some(name) = app.rename.as_ref() {
+        terminal_block = terminal_block.title_bottom(Line::from(vec![
+            Span::styled(
+                format!(" Rename tab: {name}"),
+                Style::default().fg(Color::White).bold(),
+            ),
+            Span::styled("_ ", Style::default().fg(Color::Cyan)),
+        ]));
+    } else if let Some(bar) = mode_bar.take_if(|_| !split_focused) {
+        terminal_block = terminal_block.title_bottom(bar);
+    } else if let Some(status) = app.status() {
+        terminal_block = terminal_block.title_bottom(Line::from(Span::styled(
+            status.to_string(),
+            Style::default().fg(Color::Green),
+        )));
+    } else if app.tabs.active().pane.is_process_exited() {
+        terminal_block = terminal_block.title_bottom(exit_bar(&app.tabs.active().pane));
+    }
 
-    let terminal_inner = terminal_block.inner(terminal_area);
-    frame.render_widget(terminal_block, terminal_area);
+    let claude_inner = terminal_block.inner(claude_area);
+    frame.render_widget(terminal_block, claude_area);
 
-    // Resize PTY to match terminal area
-    app.terminal
-        .resize(terminal_inner.width, terminal_inner.height);
+    let mut split_inner = None;
+    if let (Some(area), Some(split)) = (split_area, app.tabs.active().split.as_ref()) {
+        let color = if split.focused {
+            Color::Cyan
+        } else {
+            Color::DarkGray
+        };
+        let mut split_block = Block::default()
+            .title(format!(" {} ", split.title))
+            .title_style(Style::default().fg(color).bold())
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(color));
+        if let Some(bar) = mode_bar {
+            split_block = split_block.title_bottom(bar);
+        }
+        split_inner = Some(split_block.inner(area));
+        frame.render_widget(split_block, area);
+    }
 
-    // Store terminal area for mouse drag routing
-    app.terminal_area = Some(terminal_inner);
+    // Resize PTYs to match their panes; background tabs too, so they are
+    // ready to show when switched to
+    for tab in app.tabs.iter_mut() {
+        let (claude_area, split_area) = pane_areas(tab, chunks[0]);
+        let inner = pane_inner(claude_area);
+        tab.pane.resize(inner.width, inner.height);
+        if let (Some(split), Some(area)) = (tab.split.as_mut(), split_area) {
+            let inner = pane_inner(area);
+            split.pane.resize(inner.width, inner.height);
+        }
+    }
 
-    let terminal_widget = TerminalWidget::new(&app.terminal, app.selection.as_ref());
-    frame.render_widget(terminal_widget, terminal_inner);
+    // Store pane areas for mouse routing
+    app.panes_area = Some(chunks[0]);
+    let (terminal_inner, other_inner) = focus_areas(app.tabs.active(), chunks[0]);
+    app.terminal_area = Some(terminal_inner);
+    app.other_pane_area = other_inner;
 
-    // Set hardware blinking cursor position (terminal always focused)
     {
-        let vterm = app.terminal.vterm_lock();
+        let tab = app.tabs.active();
+        let panes = std::iter::once((&tab.pane, claude_inner, !split_focused)).chain(
+            tab.split
+                .as_ref()
+                .zip(split_inner)
+                .map(|(split, inner)| (&split.pane, inner, split_focused)),
+        );
+        app.image_overlays.clear();
+        for (pane, inner, focused) in panes {
+            let vterm = pane.vterm_lock();
+            let terminal_widget = TerminalWidget::new(
+                &vterm,
+                app.selection.as_ref().filter(|_| focused),
+                app.search.as_ref().filter(|_| focused),
+                app.copy_mode.as_ref().filter(|_| focused),
+            )
+            .images(app.settings.images);
+            frame.render_widget(terminal_widget, inner);
+            app.image_overlays
+                .extend(terminal_widget::passthrough_images(
+                    &vterm,
+                    inner,
+                    app.settings.images,
+                ));
+        }
+    }
+
+    if app.cwd_debug {
+        cwd_debug_overlay(frame, &app.tabs.active().pane, claude_inner);
+    }
+    if let Some(inspector) = app.inspector {
+        process_inspector(frame, &app.processes, inspector.row, claude_inner);
+    }
+
+    // Set hardware blinking cursor position in the focused pane
+    let requested_style = {
+        let vterm = app.terminal().vterm_lock();
         let cursor = vterm.cursor();
-        if cursor.visible {
+        if let Some(copy) = app.copy_mode.as_ref() {
+            // Copy mode: show its cursor wherever it is in the view
+            let row = copy.cursor_index(&vterm).checked_sub(vterm.view_top());
+            if let Some(row) = row.filter(|&r| r < terminal_inner.height as usize) {
+                let cx = terminal_inner.x
+                    + (copy.cursor.1 as u16).min(terminal_inner.width.saturating_sub(1));
+                frame.set_cursor_position((cx, terminal_inner.y + row as u16));
+            }
+        } else if cursor.visible {
             let cx =
                 terminal_inner.x + (cursor.x as u16).min(terminal_inner.width.saturating_sub(1));
             let cy =
@@ -58,7 +179,16 @@ pub fn draw(frame: &mut Frame, app: &mut App) {
                 frame.set_cursor_position((cx, cy));
             }
         }
-    }
+        vterm.cursor_style()
+    };
+
+    // Mirror the child's cursor shape (DECSCUSR) on the host terminal while we
+    // have focus; hand the user's own style back when focus leaves
+    app.cursor_style = if app.host_focused {
+        requested_style
+    } else {
+        CursorStyle::default()
+    };
 
     // File tree pane (right side)
     let tree_area = chunks[1];
@@ -91,7 +221,7 @@ pub fn draw(frame: &mut Frame, app: &mut App) {
     } else {
         // Auto-scroll to keep CWD visible — only when CWD actually changes
         let visible_height = tree_inner.height as usize;
-        let cwd = app.terminal.cwd();
+        let cwd = app.tabs.active().pane.cwd();
         let cwd_changed = app
             .last_auto_scroll_cwd
             .as_ref()
@@ -116,8 +246,14 @@ pub fn draw(frame: &mut Frame, app: &mut App) {
             app.last_auto_scroll_cwd = Some(cwd.to_path_buf());
         }
 
-        // Render file tree
-        let file_tree_widget = FileTreeWidget::new(&app.tree, Some(app.terminal.cwd()));
+        // Render file tree, marking where the session and its subprocesses work
+        let busy_dirs = process::subprocess_dirs(&app.processes);
+        let file_tree_widget = FileTreeWidget::new(
+            &app.tree,
+            Some(app.tabs.active().pane.cwd()),
+            app.settings.ambiguous_width,
+        )
+        .busy_dirs(&busy_dirs);
         frame.render_stateful_widget(
             file_tree_widget,
             tree_inner,
@@ -128,6 +264,332 @@ pub fn draw(frame: &mut Frame, app: &mut App) {
     }
 }
 
+/// Outer areas of `tab`'s Claude pane and split pane within `area`
+fn pane_areas(tab: &Tab, area: Rect) -> (Rect, Option<Rect>) {
+    let Some(split) = tab.split.as_ref() else {
+        return (area, None);
+    };
+    let direction = match split.direction {
+        SplitDirection::Right => Direction::Horizontal,
+        SplitDirection::Below => Direction::Vertical,
+    };
+    let chunks = Layout::default()
+        .direction(direction)
+        .constraints([
+            Constraint::Percentage(100 - split.percent),
+            Constraint::Percentage(split.percent),
+        ])
+        .split(area);
+    (chunks[0], Some(chunks[1]))
+}
+
+/// Inner areas of `tab`'s focused pane and, when split, the other pane
+pub fn focus_areas(tab: &Tab, area: Rect) -> (Rect, Option<Rect>) {
+    let (claude_area, split_area) = pane_areas(tab, area);
+    let claude_inner = pane_inner(claude_area);
+    match (split_area.map(pane_inner), tab.split.as_ref()) {
+        (Some(split_inner), Some(split)) if split.focused => (split_inner, Some(claude_inner)),
+        (split_inner, _) => (claude_inner, split_inner),
+    }
+}
+
+/// Area inside a terminal pane's border
+fn pane_inner(area: Rect) -> Rect {
+    Block::default().borders(Borders::ALL).inner(area)
+}
+
+/// Tab bar on the terminal pane's top border, then a marker when the next
+/// key goes to cltree rather than the child. A single tab that was never
+/// renamed keeps the plain title.
+fn terminal_title(app: &App) -> Line<'static> {
+    let marker = match app.prefix_key {
+        Some(key) if app.prefix_pending => format!("[{key}] "),
+        _ if app.copy_mode.is_some() => "[COPY] ".to_string(),
+        _ => String::new(),
+    };
+    let title_style = Style::default().fg(Color::Cyan).bold();
+    if app.tabs.len() == 1 && app.tabs.active().name().is_none() {
+        let title = &app.tabs.active().pane.profile().title;
+        return Line::from(Span::styled(format!(" {title} {marker}"), title_style));
+    }
+
+    let mut spans = vec![Span::raw(" ")];
+    for (index, tab) in app.tabs.iter().enumerate() {
+        let label = format!(" {}:{}", index + 1, tab.title());
+        if index == app.tabs.active_index() {
+            spans.push(Span::styled(
+                format!("{label} "),
+                Style::default().fg(Color::Black).bg(Color::Cyan).bold(),
+            ));
+        } else {
+            spans.push(Span::styled(label, Style::default().fg(Color::Gray)));
+            // Activity indicator: output arrived since the tab was last shown
+            if tab.activity {
+                spans.push(Span::styled("●", Style::default().fg(Color::Yellow)));
+            }
+            spans.push(Span::raw(" "));
+        }
+    }
+    spans.push(Span::styled(format!(" {marker}"), title_style));
+    Line::from(spans)
+}
+
+/// How the session ended and the keys to bring it back, on the terminal
+/// pane's bottom border
+fn exit_bar(pane: &TerminalPane) -> Line<'static> {
+    let how = pane
+        .exit_status()
+        .map_or_else(|| "exited".to_string(), |status| status.to_string());
+    let profile = pane.profile();
+    let mut keys = " r restart ".to_string();
+    if !profile.continue_args.is_empty() {
+        keys.push_str(&format!(" c {} ", profile.continue_args.join(" ")));
+    }
+    if !profile.resume_args.is_empty() {
+        keys.push_str(&format!(" R {} ", profile.resume_args.join(" ")));
+    }
+    keys.push_str(" q quit ");
+    Line::from(vec![
+        Span::styled(
+            format!(" {} {how} ", profile.title),
+            Style::default().fg(Color::LightRed).bold(),
+        ),
+        Span::styled(keys, Style::default().fg(Color::Gray)),
+    ])
+}
+
+/// What each CWD detector found on the last tick, in the top-right corner
+/// of the Claude pane; the detector the tree is following is marked
+fn cwd_debug_overlay(frame: &mut Frame, pane: &TerminalPane, area: Rect) {
+    let tracker = pane.cwd_tracker();
+    let mut lines = vec![Line::from(vec![
+        Span::styled(" cwd ", Style::default().fg(Color::Gray)),
+        Span::styled(
+            pane.cwd().display().to_string(),
+            Style::default().fg(Color::White).bold(),
+        ),
+        Span::raw(" "),
+    ])];
+    for reading in tracker.readings() {
+        let winner = tracker.winner() == Some(reading.detector);
+        let verdict = match reading.verdict {
+            Verdict::Nothing => "-".to_string(),
+            Verdict::Same => "same".to_string(),
+            Verdict::Followed => "followed".to_string(),
+            Verdict::Settling(seen) => format!("settling {seen}/{}", tracker.settle_ticks()),
+            Verdict::Ignored => "ignored".to_string(),
+            Verdict::Skipped => "skipped".to_string(),
+        };
+        let path = reading
+            .path
+            .as_ref()
+            .map_or("-".to_string(), |path| path.display().to_string());
+        let name_style = if winner {
+            Style::default().fg(Color::Green).bold()
+        } else {
+            Style::default().fg(Color::Cyan)
+        };
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("{}{:<8}", if winner { "▶" } else { " " }, reading.detector),
+                name_style,
+            ),
+            Span::raw(format!("{path} ")),
+            Span::styled(format!("{verdict} "), Style::default().fg(Color::DarkGray)),
+        ]));
+    }
+
+    let width = lines
+        .iter()
+        .map(Line::width)
+        .max()
+        .unwrap_or(0)
+        .saturating_add(2)
+        .min(area.width as usize) as u16;
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let overlay = Rect::new(area.right().saturating_sub(width), area.y, width, height);
+    frame.render_widget(Clear, overlay);
+    frame.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .title(" CWD detectors ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::DarkGray)),
+        ),
+        overlay,
+    );
+}
+
+/// The session's processes over the bottom of the Claude pane, one row per
+/// process indented under its parent, with the selected one highlighted
+fn process_inspector(frame: &mut Frame, processes: &[ProcessInfo], selected: usize, area: Rect) {
+    let header = Row::new(["PID", "CPU%", "RSS", "TIME", "CWD", "COMMAND"])
+        .style(Style::default().fg(Color::Gray).bold());
+    let rows = processes.iter().map(|process| {
+        let cwd = process
+            .cwd
+            .as_ref()
+            .map_or("?".to_string(), |cwd| cwd.display().to_string());
+        Row::new([
+            Cell::from(process.pid.to_string()),
+            Cell::from(format!("{:.1}", process.cpu_percent)),
+            Cell::from(format_bytes(process.rss)),
+            Cell::from(format_elapsed(process.elapsed)),
+            Cell::from(cwd).style(Style::default().fg(Color::Cyan)),
+            Cell::from(format!(
+                "{}{}",
+                "  ".repeat(process.depth),
+                process.command()
+            )),
+        ])
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(7),
+            Constraint::Length(6),
+            Constraint::Length(7),
+            Constraint::Length(9),
+            Constraint::Percentage(30),
+            Constraint::Fill(1),
+        ],
+    )
+    .header(header)
+    .row_highlight_style(Style::default().bg(Color::Rgb(50, 60, 90)).bold())
+    .block(
+        Block::default()
+            .title(" Processes ")
+            .title_bottom(Line::from(Span::styled(
+                " ↑↓ select  i SIGINT  t SIGTERM  q close ",
+                Style::default().fg(Color::Gray),
+            )))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan)),
+    );
+
+    // Header and borders take three rows
+    let height = (processes.len() as u16 + 3)
+        .min((area.height / 2).max(4))
+        .min(area.height);
+    let panel = Rect::new(area.x, area.bottom() - height, area.width, height);
+    let mut state = TableState::default().with_selected(Some(selected));
+    frame.render_widget(Clear, panel);
+    frame.render_stateful_widget(table, panel, &mut state);
+}
+
+/// `1.5G`, `320M`, `12K`
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "K", "M", "G"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if value < 10.0 && unit > 0 {
+        format!("{value:.1}{}", UNITS[unit])
+    } else {
+        format!("{value:.0}{}", UNITS[unit])
+    }
+}
+
+/// `mm:ss`, or `h:mm:ss` past an hour
+fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes:02}:{seconds:02}")
+    }
+}
+
+/// Selection kind shown on the terminal pane's bottom border in copy mode
+fn copy_mode_bar(copy: &CopyMode) -> Line<'static> {
+    let label = match (copy.anchor, copy.kind) {
+        (None, _) => " COPY  v/V/C-v select  y yank  q quit ",
+        (Some(_), SelectionKind::Char) => " VISUAL ",
+        (Some(_), SelectionKind::Line) => " VISUAL LINE ",
+        (Some(_), SelectionKind::Block) => " VISUAL BLOCK ",
+    };
+    Line::from(Span::styled(
+        label,
+        Style::default().fg(Color::Yellow).bold(),
+    ))
+}
+
+/// Query, mode and match position shown on the terminal pane's bottom border
+fn search_bar(search: &Search) -> Line<'static> {
+    let mut spans = vec![Span::styled(
+        format!(" /{}", search.query),
+        Style::default().fg(Color::White).bold(),
+    )];
+    if search.editing {
+        spans.push(Span::styled("_", Style::default().fg(Color::Cyan)));
+    }
+    let status = if search.invalid {
+        " invalid regex ".to_string()
+    } else if search.query.is_empty() {
+        " ".to_string()
+    } else if search.matches.is_empty() {
+        " no matches ".to_string()
+    } else {
+        // Matches are numbered from the bottom, like the n/N direction
+        let current = search.current.map_or(0, |i| search.matches.len() - i);
+        format!(" [{current}/{}] ", search.matches.len())
+    };
+    spans.push(Span::styled(status, Style::default().fg(Color::Yellow)));
+    if search.mode == SearchMode::Regex {
+        spans.push(Span::styled("regex ", Style::default().fg(Color::Magenta)));
+    }
+    Line::from(spans)
+}
+
+/// Set the host terminal's cursor shape. Like images, this is terminal state
+/// outside ratatui's buffer, so it is written after a frame is drawn.
+pub fn write_cursor_style(style: CursorStyle) -> io::Result<()> {
+    execute!(io::stdout(), host_cursor_style(style))
+}
+
+fn host_cursor_style(style: CursorStyle) -> SetCursorStyle {
+    match (style.shape, style.blinking) {
+        (CursorShape::Default, _) => SetCursorStyle::DefaultUserShape,
+        (CursorShape::Block, true) => SetCursorStyle::BlinkingBlock,
+        (CursorShape::Block, false) => SetCursorStyle::SteadyBlock,
+        (CursorShape::Underline, true) => SetCursorStyle::BlinkingUnderScore,
+        (CursorShape::Underline, false) => SetCursorStyle::SteadyUnderScore,
+        (CursorShape::Bar, true) => SetCursorStyle::BlinkingBar,
+        (CursorShape::Bar, false) => SetCursorStyle::SteadyBar,
+    }
+}
+
 pub struct FileTreeWidgetState {
     pub offset: usize,
 }
+
+/// Have the host terminal draw `overlays` at their positions in its own
+/// image protocol. The text cursor is saved and restored around them.
+pub fn write_images(overlays: &[(Position, Placement)], mode: ImageMode) -> io::Result<()> {
+    if overlays.is_empty() {
+        return Ok(());
+    }
+    let mut out = io::stdout().lock();
+    out.write_all(b"\x1b7")?;
+    for (position, placement) in overlays {
+        queue!(out, MoveTo(position.x, position.y))?;
+        out.write_all(&graphics::passthrough_sequence(placement, mode))?;
+    }
+    out.write_all(b"\x1b8")?;
+    out.flush()
+}
+
+/// Remove images drawn with [`write_images`]. Sixel pixels go with the
+/// repaint that follows; kitty keeps placements until told otherwise.
+pub fn clear_images(mode: ImageMode) -> io::Result<()> {
+    if mode == ImageMode::Kitty {
+        let mut out = io::stdout().lock();
+        out.write_all(b"\x1b_Ga=d,d=A,q=2\x1b\\")?;
+        out.flush()?;
+    }
+    Ok(())
+}
@@ -1,79 +1,227 @@
-use ratatui::{prelude::*, widgets::Widget};
+use ratatui::{buffer::CellDiffOption, prelude::*, widgets::Widget};
 
 use crate::app::Selection;
-use crate::terminal::TerminalPane;
+use crate::copy_mode::CopyMode;
+use crate::graphics::{self, ImageMode, Placement};
+use crate::search::{Search, SearchMatch};
+use crate::vterm::VirtualTerminal;
 
 pub struct TerminalWidget<'a> {
-    terminal: &'a TerminalPane,
+    vterm: &'a VirtualTerminal,
     selection: Option<&'a Selection>,
+    search: Option<&'a Search>,
+    copy_mode: Option<&'a CopyMode>,
+    image_mode: ImageMode,
 }
 
 impl<'a> TerminalWidget<'a> {
-    pub fn new(terminal: &'a TerminalPane, selection: Option<&'a Selection>) -> Self {
+    pub fn new(
+        vterm: &'a VirtualTerminal,
+        selection: Option<&'a Selection>,
+        search: Option<&'a Search>,
+        copy_mode: Option<&'a CopyMode>,
+    ) -> Self {
         Self {
-            terminal,
+            vterm,
             selection,
+            search,
+            copy_mode,
+            image_mode: ImageMode::default(),
         }
     }
 
-    /// Check if a given (col, row) is within the selection range.
-    fn is_selected(&self, col: u16, row: u16) -> bool {
-        let sel = match self.selection {
-            Some(s) => s,
-            None => return false,
-        };
+    /// How to draw sixel and kitty images
+    pub fn images(mut self, mode: ImageMode) -> Self {
+        self.image_mode = mode;
+        self
+    }
 
-        // Normalize so start <= end in reading order
-        let (start, end) = if (sel.start.1, sel.start.0) <= (sel.end.1, sel.end.0) {
-            (sel.start, sel.end)
-        } else {
-            (sel.end, sel.start)
+    /// Search matches overlapping lines `top..bottom` (counted like
+    /// [`SearchMatch`] lines), flagged if current
+    fn visible_matches(&self, top: usize, bottom: usize) -> Vec<(SearchMatch, bool)> {
+        let Some(search) = self.search else {
+            return Vec::new();
         };
+        let current = search.current_match();
+        search
+            .matches
+            .iter()
+            .filter(|m| m.end.0 >= top && m.start.0 < bottom)
+            .map(|m| (*m, Some(m) == current))
+            .collect()
+    }
 
-        if row < start.1 || row > end.1 {
-            return false;
-        }
-        if row == start.1 && row == end.1 {
-            return col >= start.0 && col <= end.0;
-        }
-        if row == start.1 {
-            return col >= start.0;
+    /// Draw images over the text. Ones the host terminal will draw itself
+    /// (see [`passthrough_images`]) only reserve their cells.
+    fn render_images(&self, area: Rect, buf: &mut Buffer, top: usize) {
+        let vterm = self.vterm;
+        let mode = self.image_mode;
+        if mode == ImageMode::None {
+            return;
         }
-        if row == end.1 {
-            return col <= end.0;
+        let first = vterm.evicted_lines() + top;
+        let width = (area.width as usize).min(vterm.cols());
+        let background = vterm.color_rgb(None, false);
+        let rgb = |color: Option<(u8, u8, u8)>| {
+            color.map_or(Color::Reset, |(r, g, b)| Color::Rgb(r, g, b))
+        };
+
+        for placement in vterm.images() {
+            let reserved =
+                mode.passes_through(placement) && fully_visible(placement, first, area, width);
+            let (image, cols, rows) = (&placement.image, placement.cols, placement.rows);
+            for row in 0..rows {
+                let Some(y) = (placement.line + row)
+                    .checked_sub(first)
+                    .filter(|&y| y < area.height as usize)
+                else {
+                    continue;
+                };
+                for col in 0..cols.min(width.saturating_sub(placement.col)) {
+                    let position = (area.x + (placement.col + col) as u16, area.y + y as u16);
+                    let Some(cell) = buf.cell_mut(position) else {
+                        continue;
+                    };
+                    if reserved {
+                        cell.set_symbol(" ").set_diff_option(CellDiffOption::Skip);
+                        continue;
+                    }
+                    if mode == ImageMode::Braille {
+                        if let Some((ch, color)) =
+                            graphics::braille(image, cols, rows, col, row, background)
+                        {
+                            cell.set_char(ch).set_fg(rgb(Some(color)));
+                        }
+                        continue;
+                    }
+                    match graphics::half_block(image, cols, rows, col, row, background) {
+                        (None, None) => {}
+                        (Some(upper), None) => {
+                            cell.set_char('▀')
+                                .set_fg(rgb(Some(upper)))
+                                .set_bg(Color::Reset);
+                        }
+                        (upper, lower) if upper == lower => {
+                            cell.set_char(' ').set_bg(rgb(upper));
+                        }
+                        (None, lower) => {
+                            cell.set_char('▄').set_fg(rgb(lower)).set_bg(Color::Reset);
+                        }
+                        (upper, lower) => {
+                            cell.set_char('▀').set_fg(rgb(upper)).set_bg(rgb(lower));
+                        }
+                    }
+                }
+            }
         }
-        true
+    }
+
+    /// Whether a cell is inside the mouse selection or the copy-mode
+    /// selection. `line` is a scrollback+screen index; adding `evicted` gives
+    /// the buffer line both selections are anchored to.
+    fn is_selected(&self, evicted: usize, line: usize, col: usize) -> bool {
+        self.selection
+            .is_some_and(|sel| sel.contains(evicted + line, col))
+            || self
+                .copy_mode
+                .is_some_and(|copy| copy.contains(evicted + line, col))
+    }
+}
+
+/// Whether all of `placement` is on screen when line `first` is at the top
+fn fully_visible(placement: &Placement, first: usize, area: Rect, width: usize) -> bool {
+    placement.line >= first
+        && placement.line + placement.rows <= first + area.height as usize
+        && placement.col + placement.cols <= width
+}
+
+/// Images to hand to the host terminal with `mode`, and the screen position
+/// of their top-left cell. Only images entirely inside the pane qualify;
+/// the rest are approximated with text.
+pub fn passthrough_images(
+    vterm: &VirtualTerminal,
+    area: Rect,
+    mode: ImageMode,
+) -> Vec<(Position, Placement)> {
+    let first = vterm.evicted_lines() + view_top(vterm, area);
+    let width = (area.width as usize).min(vterm.cols());
+    vterm
+        .images()
+        .iter()
+        .filter(|p| mode.passes_through(p) && fully_visible(p, first, area, width))
+        .map(|p| {
+            let position = Position::new(area.x + p.col as u16, area.y + (p.line - first) as u16);
+            (position, p.clone())
+        })
+        .collect()
+}
+
+/// First line shown in a pane of `area`'s height (see [`VirtualTerminal::line`])
+fn view_top(vterm: &VirtualTerminal, area: Rect) -> usize {
+    let scrollback = vterm.scrollback().len();
+    if vterm.scroll_offset() == 0 {
+        scrollback
+    } else {
+        (scrollback + vterm.grid().len())
+            .saturating_sub(vterm.scroll_offset())
+            .saturating_sub(area.height as usize)
+    }
+}
+
+/// Highlight cells covered by a search match; the current match stands out
+fn search_style(style: Style, matches: &[(SearchMatch, bool)], line: usize, col: usize) -> Style {
+    match matches.iter().find(|(m, _)| m.contains(line, col)) {
+        Some((_, true)) => style.fg(Color::Black).bg(Color::LightRed),
+        Some((_, false)) => style.fg(Color::Black).bg(Color::Yellow),
+        None => style,
     }
 }
 
 impl<'a> Widget for TerminalWidget<'a> {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let vterm = self.terminal.vterm_lock();
+        let vterm = self.vterm;
         let grid = vterm.grid();
         let scrollback = vterm.scrollback();
         let scroll_offset = vterm.scroll_offset();
+        let evicted = vterm.evicted_lines();
 
         if scroll_offset == 0 {
             // Normal mode: render the grid directly
             let rows_to_render = (area.height as usize).min(grid.len());
             let cols_to_render = (area.width as usize).min(vterm.cols());
+            let top_line = scrollback.len();
+            let matches =
+                self.visible_matches(evicted + top_line, evicted + top_line + rows_to_render);
 
             for row_idx in 0..rows_to_render {
                 if let Some(row) = grid.get(row_idx) {
                     for (col_idx, cell) in row.iter().enumerate().take(cols_to_render) {
-                        if cell.ch.is_empty() {
-                            continue; // wide char continuation cell
-                        }
                         let x = area.x + col_idx as u16;
                         let y = area.y + row_idx as u16;
+                        if cell.ch.is_empty() {
+                            // Wide char continuation cell: covered by the glyph to its left
+                            if x < area.x + area.width && y < area.y + area.height {
+                                if let Some(buf_cell) = buf.cell_mut((x, y)) {
+                                    buf_cell.set_diff_option(CellDiffOption::Skip);
+                                }
+                            }
+                            continue;
+                        }
                         if x < area.x + area.width && y < area.y + area.height {
                             if let Some(buf_cell) = buf.cell_mut((x, y)) {
                                 buf_cell.set_symbol(&cell.ch);
-                                let style = if self.is_selected(col_idx as u16, row_idx as u16) {
-                                    cell.style.add_modifier(Modifier::REVERSED)
-                                } else {
-                                    cell.style
-                                };
+                                let style = search_style(
+                                    vterm.resolve_style(cell.style),
+                                    &matches,
+                                    evicted + top_line + row_idx,
+                                    col_idx,
+                                );
+                                let style =
+                                    if self.is_selected(evicted, top_line + row_idx, col_idx) {
+                                        style.add_modifier(Modifier::REVERSED)
+                                    } else {
+                                        style
+                                    };
                                 buf_cell.set_style(style);
                             }
                         }
@@ -89,6 +237,7 @@ impl<'a> Widget for TerminalWidget<'a> {
             // scroll_offset is how many lines above the bottom of the grid we are
             let bottom = total_lines.saturating_sub(scroll_offset);
             let top = bottom.saturating_sub(visible_height);
+            let matches = self.visible_matches(evicted + top, evicted + bottom);
 
             for (screen_row, line_idx) in (top..bottom).enumerate() {
                 let row_data = if line_idx < scrollback.len() {
@@ -99,18 +248,30 @@ impl<'a> Widget for TerminalWidget<'a> {
 
                 if let Some(row) = row_data {
                     for (col_idx, cell) in row.iter().enumerate().take(cols_to_render) {
-                        if cell.ch.is_empty() {
-                            continue; // wide char continuation cell
-                        }
                         let x = area.x + col_idx as u16;
                         let y = area.y + screen_row as u16;
+                        if cell.ch.is_empty() {
+                            // Wide char continuation cell: covered by the glyph to its left
+                            if x < area.x + area.width && y < area.y + area.height {
+                                if let Some(buf_cell) = buf.cell_mut((x, y)) {
+                                    buf_cell.set_diff_option(CellDiffOption::Skip);
+                                }
+                            }
+                            continue;
+                        }
                         if x < area.x + area.width && y < area.y + area.height {
                             if let Some(buf_cell) = buf.cell_mut((x, y)) {
                                 buf_cell.set_symbol(&cell.ch);
-                                let style = if self.is_selected(col_idx as u16, screen_row as u16) {
-                                    cell.style.add_modifier(Modifier::REVERSED)
+                                let style = search_style(
+                                    vterm.resolve_style(cell.style),
+                                    &matches,
+                                    evicted + line_idx,
+                                    col_idx,
+                                );
+                                let style = if self.is_selected(evicted, line_idx, col_idx) {
+                                    style.add_modifier(Modifier::REVERSED)
                                 } else {
-                                    cell.style
+                                    style
                                 };
                                 buf_cell.set_style(style);
                             }
@@ -119,5 +280,7 @@ impl<'a> Widget for TerminalWidget<'a> {
                 }
             }
         }
+
+        self.render_images(area, buf, view_top(vterm, area));
     }
 }
@@ -0,0 +1,106 @@
+use ratatui::buffer::CellDiffOption;
+use ratatui::prelude::*;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthChar;
+
+/// How East Asian Ambiguous-width characters (box drawing, `·`, `●`, `①`, …)
+/// are measured. CJK locales usually render them two cells wide.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AmbiguousWidth {
+    #[default]
+    Narrow,
+    Wide,
+}
+
+impl AmbiguousWidth {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "narrow" | "1" => Some(Self::Narrow),
+            "wide" | "2" => Some(Self::Wide),
+            _ => None,
+        }
+    }
+}
+
+const ZWJ: char = '\u{200D}';
+const VS16: char = '\u{FE0F}';
+
+fn is_regional_indicator(ch: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&ch)
+}
+
+/// Display width of a single char (0 for combining marks and controls).
+pub fn char_width(ch: char, policy: AmbiguousWidth) -> usize {
+    let width = match policy {
+        AmbiguousWidth::Narrow => ch.width(),
+        AmbiguousWidth::Wide => ch.width_cjk(),
+    };
+    width.unwrap_or(0)
+}
+
+/// Display width of one extended grapheme cluster.
+///
+/// The base character decides the width, except for emoji presentation
+/// (VS16), ZWJ sequences and regional-indicator flags, which are always wide.
+pub fn grapheme_width(grapheme: &str, policy: AmbiguousWidth) -> usize {
+    let mut chars = grapheme.chars();
+    let Some(first) = chars.next() else {
+        return 0;
+    };
+    let base = char_width(first, policy);
+    let rest: Vec<char> = chars.collect();
+    if rest.is_empty() {
+        return base;
+    }
+    if rest.contains(&VS16)
+        || (rest.contains(&ZWJ) && base > 0)
+        || (is_regional_indicator(first) && rest.iter().copied().any(is_regional_indicator))
+    {
+        return 2;
+    }
+    base
+}
+
+/// Display width of a string, measured grapheme by grapheme.
+pub fn str_width(s: &str, policy: AmbiguousWidth) -> usize {
+    s.graphemes(true).map(|g| grapheme_width(g, policy)).sum()
+}
+
+/// Write `s` into `buf` starting at `(x, y)` using our own width rules, so
+/// wide graphemes claim their trailing cells regardless of how ratatui would
+/// measure them. Stops at the right edge of the buffer. Returns the number of
+/// columns written.
+pub fn set_string(
+    buf: &mut Buffer,
+    x: u16,
+    y: u16,
+    s: &str,
+    style: Style,
+    policy: AmbiguousWidth,
+) -> u16 {
+    let right = buf.area.right();
+    let mut cx = x;
+    for g in s.graphemes(true) {
+        if g.contains(char::is_control) {
+            continue;
+        }
+        let w = grapheme_width(g, policy) as u16;
+        if w == 0 {
+            continue;
+        }
+        if cx + w > right {
+            break;
+        }
+        if let Some(cell) = buf.cell_mut((cx, y)) {
+            cell.set_symbol(g).set_style(style);
+        }
+        for i in 1..w {
+            if let Some(cell) = buf.cell_mut((cx + i, y)) {
+                cell.reset();
+                cell.set_diff_option(CellDiffOption::Skip);
+            }
+        }
+        cx += w;
+    }
+    cx - x
+}
@@ -1,8 +1,15 @@
 use ratatui::prelude::*;
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
+use std::ops::{Deref, DerefMut, Range};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use unicode_segmentation::UnicodeSegmentation;
 use vte::{Params, Perform};
 
+use crate::graphics::{cell_extent, Image, ImageSource, KittyCommand, Placement, SixelDecoder};
+use crate::palette::{ansi_index, format_color_spec, parse_color_spec, Palette, Rgb};
+use crate::unicode::{grapheme_width, AmbiguousWidth};
+
 #[derive(Clone, Debug)]
 pub struct Cell {
     pub ch: String,
@@ -18,6 +25,76 @@ impl Default for Cell {
     }
 }
 
+/// Shell-integration mark set via OSC 133 on the row the cursor was on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SemanticMark {
+    /// `OSC 133 ; A` — a prompt begins
+    PromptStart,
+    /// `OSC 133 ; B` — the prompt ends and the user's command begins
+    CommandStart,
+    /// `OSC 133 ; C` — the command was submitted and its output begins
+    OutputStart,
+    /// `OSC 133 ; D [; exit]` — the command finished
+    CommandEnd(Option<i32>),
+}
+
+/// One line of the screen or scrollback. Derefs to its cells.
+#[derive(Clone, Debug, Default)]
+pub struct Row {
+    cells: Vec<Cell>,
+    /// OSC 133 marks recorded on this row, in arrival order
+    pub marks: Vec<SemanticMark>,
+    /// The line continues on the next row (autowrap rather than a newline)
+    pub wrapped: bool,
+    /// The last cell is blank padding left by a wide character that did not
+    /// fit and wrapped to the next row; it is not part of the text
+    pub padded: bool,
+}
+
+impl Row {
+    pub fn new(cols: usize) -> Self {
+        Self {
+            cells: vec![Cell::default(); cols],
+            marks: Vec::new(),
+            wrapped: false,
+            padded: false,
+        }
+    }
+
+    /// The cells that hold text: all of them except wrap padding
+    pub fn text_cells(&self) -> &[Cell] {
+        let len = self.cells.len() - usize::from(self.padded && !self.cells.is_empty());
+        &self.cells[..len]
+    }
+
+    pub fn has_mark(&self, pred: impl Fn(&SemanticMark) -> bool) -> bool {
+        self.marks.iter().any(pred)
+    }
+}
+
+impl Deref for Row {
+    type Target = Vec<Cell>;
+
+    fn deref(&self) -> &Vec<Cell> {
+        &self.cells
+    }
+}
+
+impl DerefMut for Row {
+    fn deref_mut(&mut self) -> &mut Vec<Cell> {
+        &mut self.cells
+    }
+}
+
+impl<'a> IntoIterator for &'a Row {
+    type Item = &'a Cell;
+    type IntoIter = std::slice::Iter<'a, Cell>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.cells.iter()
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct CursorState {
     pub x: usize,
@@ -35,20 +112,48 @@ impl Default for CursorState {
     }
 }
 
+/// Desktop notification requested via OSC 9 or OSC 777
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Notification {
+    pub title: Option<String>,
+    pub body: String,
+}
+
+/// Cursor shape requested by the child via DECSCUSR (`CSI Ps SP q`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CursorShape {
+    /// Whatever the host terminal's user has configured (`Ps` = 0)
+    #[default]
+    Default,
+    Block,
+    Underline,
+    Bar,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CursorStyle {
+    pub shape: CursorShape,
+    pub blinking: bool,
+}
+
 pub struct VirtualTerminal {
-    grid: Vec<Vec<Cell>>,
+    grid: Vec<Row>,
     cols: usize,
     rows: usize,
     cursor: CursorState,
     current_style: Style,
-    scrollback: VecDeque<Vec<Cell>>,
+    scrollback: VecDeque<Row>,
     scroll_offset: usize,
+    // Lines dropped from the front of the main scrollback since the start
+    evicted_lines: usize,
     saved_cursor: Option<CursorState>,
     // Alternate screen buffer (used by full-screen apps like vim, less, etc.)
-    saved_grid: Option<Vec<Vec<Cell>>>,
-    saved_scrollback: Option<VecDeque<Vec<Cell>>>,
+    saved_grid: Option<Vec<Row>>,
+    saved_scrollback: Option<VecDeque<Row>>,
     saved_main_cursor: Option<CursorState>,
     parser: Option<vte::Parser>,
+    /// Start of a UTF-8 sequence cut off at the end of the last `feed`
+    utf8_tail: Vec<u8>,
     // Scroll region (DECSTBM): top..bottom (0-indexed, bottom is exclusive)
     scroll_top: usize,
     scroll_bottom: usize,
@@ -58,11 +163,63 @@ pub struct VirtualTerminal {
     reported_cwd: Option<PathBuf>,
     // Clipboard requests from OSC 52
     clipboard_requests: Vec<String>,
+    // BEL received since the app last checked
+    bell: bool,
+    // Desktop notifications from OSC 9 / OSC 777
+    notifications: Vec<Notification>,
     // Whether the child process has enabled focus event tracking (DECSET 1004)
     focus_tracking: bool,
+    // Cursor shape/blink requested via DECSCUSR and DECSET 12
+    cursor_style: CursorStyle,
+    // Base colours reported to OSC 4/10/11/12 queries
+    palette: Palette,
+    // Palette entries changed by the child via OSC 4 (reset by OSC 104)
+    color_overrides: Box<[Option<Rgb>; 256]>,
+    // Default foreground/background/cursor colours set via OSC 10/11/12
+    dynamic_colors: [Option<Rgb>; 3],
+    // Width policy for East Asian Ambiguous characters
+    ambiguous_width: AmbiguousWidth,
+    // (row, col) of the cell holding the last printed grapheme, so that
+    // follow-up code points (ZWJ, VS16, flags, jamo) can join its cluster
+    last_printed: Option<(usize, usize)>,
+    // Images shown via sixel or the kitty graphics protocol
+    placements: Vec<Placement>,
+    saved_placements: Option<Vec<Placement>>,
+    next_image_serial: u64,
+    // Kitty images transmitted for later display, by id
+    kitty_images: HashMap<u32, Arc<Image>>,
+    // Kitty transmission split over several APCs (m=1): first command and
+    // the base64 data so far
+    kitty_upload: Option<(KittyCommand, Vec<u8>)>,
+    // Sixel DCS in progress, with the raw sequence kept for passthrough
+    sixel: Option<(SixelDecoder, Option<Vec<u8>>)>,
+    // APC strings (kitty graphics) are cut out of the input before vte,
+    // which discards them
+    apc: ApcScan,
+    // Pixel size of a cell, for sizing images and answering XTWINOPS
+    cell_size: (usize, usize),
 }
 
 const MAX_SCROLLBACK: usize = 1000;
+/// Images kept on screen and in the scrollback; the oldest go first
+const MAX_PLACEMENTS: usize = 256;
+/// Kitty images held for later display (a=t)
+const MAX_KITTY_IMAGES: usize = 64;
+/// Longest APC or raw sixel sequence we buffer
+const MAX_SEQUENCE_BYTES: usize = 32 << 20;
+
+/// Where the APC scanner in [`VirtualTerminal::feed`] is
+#[derive(Debug, Default)]
+enum ApcScan {
+    #[default]
+    Ground,
+    /// Saw ESC, which may start an APC
+    Escape,
+    /// Inside `ESC _ ... `
+    Body(Vec<u8>),
+    /// Saw ESC inside the body, which should be the start of ST
+    BodyEscape(Vec<u8>),
+}
 
 impl VirtualTerminal {
     pub fn new(cols: usize, rows: usize) -> Self {
@@ -74,17 +231,35 @@ impl VirtualTerminal {
             current_style: Style::default(),
             scrollback: VecDeque::new(),
             scroll_offset: 0,
+            evicted_lines: 0,
             saved_cursor: None,
             saved_grid: None,
             saved_scrollback: None,
             saved_main_cursor: None,
             parser: Some(vte::Parser::new()),
+            utf8_tail: Vec::new(),
             scroll_top: 0,
             scroll_bottom: rows,
             response_queue: Vec::new(),
             reported_cwd: None,
             clipboard_requests: Vec::new(),
+            bell: false,
+            notifications: Vec::new(),
             focus_tracking: false,
+            cursor_style: CursorStyle::default(),
+            palette: Palette::default(),
+            color_overrides: Box::new([None; 256]),
+            dynamic_colors: [None; 3],
+            ambiguous_width: AmbiguousWidth::default(),
+            last_printed: None,
+            placements: Vec::new(),
+            saved_placements: None,
+            next_image_serial: 0,
+            kitty_images: HashMap::new(),
+            kitty_upload: None,
+            sixel: None,
+            apc: ApcScan::Ground,
+            cell_size: (10, 20),
         }
     }
 
@@ -98,6 +273,16 @@ impl VirtualTerminal {
         std::mem::take(&mut self.clipboard_requests)
     }
 
+    /// Whether a BEL arrived since the last call (clears the flag)
+    pub fn take_bell(&mut self) -> bool {
+        std::mem::take(&mut self.bell)
+    }
+
+    /// Take pending desktop notifications (from OSC 9 / OSC 777)
+    pub fn take_notifications(&mut self) -> Vec<Notification> {
+        std::mem::take(&mut self.notifications)
+    }
+
     /// Get the CWD reported via OSC 7
     pub fn reported_cwd(&self) -> Option<&Path> {
         self.reported_cwd.as_deref()
@@ -108,19 +293,168 @@ impl VirtualTerminal {
         self.focus_tracking
     }
 
-    fn make_grid(cols: usize, rows: usize) -> Vec<Vec<Cell>> {
-        vec![vec![Cell::default(); cols]; rows]
+    /// Cursor shape and blinking state the child has requested
+    pub fn cursor_style(&self) -> CursorStyle {
+        self.cursor_style
+    }
+
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    /// Set the base colours used to answer the child's colour queries
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
+
+    /// Apply colours the child changed via OSC 4/10/11 to a cell style.
+    /// Colours the child never touched are left for the host terminal to draw.
+    pub fn resolve_style(&self, mut style: Style) -> Style {
+        let resolve = |color: Option<Color>, dynamic: Option<Rgb>| -> Option<Color> {
+            match color {
+                None | Some(Color::Reset) => dynamic.map(|(r, g, b)| Color::Rgb(r, g, b)).or(color),
+                Some(c) => match ansi_index(c).and_then(|i| self.color_overrides[i as usize]) {
+                    Some((r, g, b)) => Some(Color::Rgb(r, g, b)),
+                    None => Some(c),
+                },
+            }
+        };
+        style.fg = resolve(style.fg, self.dynamic_colors[0]);
+        style.bg = resolve(style.bg, self.dynamic_colors[1]);
+        style
+    }
+
+    /// Current value of palette entry `idx`, including OSC 4 overrides
+    fn indexed_color(&self, idx: u8) -> Rgb {
+        self.color_overrides[idx as usize].unwrap_or(self.palette.indexed[idx as usize])
+    }
+
+    /// Current value of dynamic colour `slot` (0 = fg, 1 = bg, 2 = cursor)
+    fn dynamic_color(&self, slot: usize) -> Rgb {
+        self.dynamic_colors[slot].unwrap_or(match slot {
+            0 => self.palette.foreground,
+            1 => self.palette.background,
+            _ => self.palette.cursor,
+        })
+    }
+
+    fn push_osc_reply(&mut self, body: String, bell_terminated: bool) {
+        let terminator = if bell_terminated { "\x07" } else { "\x1b\\" };
+        self.response_queue
+            .push(format!("\x1b]{body}{terminator}").into_bytes());
+    }
+
+    /// Images on the screen and in the scrollback, oldest first
+    pub fn images(&self) -> &[Placement] {
+        &self.placements
+    }
+
+    /// Pixel size of a cell used to size images, `(width, height)`
+    pub fn cell_size(&self) -> (usize, usize) {
+        self.cell_size
+    }
+
+    /// Set the cell size, normally the host terminal's, so images take as
+    /// many cells here as they would there
+    pub fn set_cell_size(&mut self, width: usize, height: usize) {
+        if width > 0 && height > 0 {
+            self.cell_size = (width, height);
+        }
+    }
+
+    pub fn ambiguous_width(&self) -> AmbiguousWidth {
+        self.ambiguous_width
+    }
+
+    /// Set how East Asian Ambiguous characters are measured. Only affects
+    /// output printed after the change.
+    pub fn set_ambiguous_width(&mut self, policy: AmbiguousWidth) {
+        self.ambiguous_width = policy;
     }
 
-    fn make_row(&self) -> Vec<Cell> {
-        vec![Cell::default(); self.cols]
+    fn make_grid(cols: usize, rows: usize) -> Vec<Row> {
+        vec![Row::new(cols); rows]
+    }
+
+    fn make_row(&self) -> Row {
+        Row::new(self.cols)
+    }
+
+    fn mark_wrapped(&mut self) {
+        if let Some(row) = self.grid.get_mut(self.cursor.y) {
+            row.wrapped = true;
+        }
     }
 
     /// Feed raw PTY bytes through the vte parser
     pub fn feed(&mut self, bytes: &[u8]) {
+        // Fast path: nothing that could be or continue an APC string
+        if matches!(self.apc, ApcScan::Ground) && !bytes.contains(&0x1b) {
+            self.advance(bytes);
+            return;
+        }
+
+        let mut plain = Vec::with_capacity(bytes.len());
+        for &byte in bytes {
+            self.apc = match std::mem::take(&mut self.apc) {
+                ApcScan::Ground if byte == 0x1b => ApcScan::Escape,
+                ApcScan::Ground => {
+                    plain.push(byte);
+                    ApcScan::Ground
+                }
+                ApcScan::Escape if byte == b'_' => ApcScan::Body(Vec::new()),
+                ApcScan::Escape => {
+                    plain.push(0x1b);
+                    if byte == 0x1b {
+                        ApcScan::Escape
+                    } else {
+                        plain.push(byte);
+                        ApcScan::Ground
+                    }
+                }
+                ApcScan::Body(body) if byte == 0x1b => ApcScan::BodyEscape(body),
+                ApcScan::Body(mut body) => {
+                    if body.len() < MAX_SEQUENCE_BYTES {
+                        body.push(byte);
+                    }
+                    ApcScan::Body(body)
+                }
+                ApcScan::BodyEscape(body) if byte == b'\\' => {
+                    // Text before the APC decides where an image goes
+                    self.advance(&plain);
+                    plain.clear();
+                    self.apc_dispatch(&body);
+                    ApcScan::Ground
+                }
+                // Not ST: give up on the APC and let vte see the escape
+                ApcScan::BodyEscape(_) => {
+                    plain.extend_from_slice(&[0x1b, byte]);
+                    ApcScan::Ground
+                }
+            };
+        }
+        self.advance(&plain);
+    }
+
+    fn advance(&mut self, bytes: &[u8]) {
         // Take the parser out temporarily to avoid double borrow
         let mut parser = self.parser.take().unwrap_or_default();
-        parser.advance(self, bytes);
+        // vte can lose a byte when a multi-byte character is split across
+        // two `advance` calls, so hold an incomplete one back until the rest
+        // arrives
+        let mut input = std::mem::take(&mut self.utf8_tail);
+        let tail = if input.is_empty() {
+            let keep = incomplete_utf8_len(bytes);
+            parser.advance(self, &bytes[..bytes.len() - keep]);
+            bytes[bytes.len() - keep..].to_vec()
+        } else {
+            input.extend_from_slice(bytes);
+            let keep = incomplete_utf8_len(&input);
+            let tail = input.split_off(input.len() - keep);
+            parser.advance(self, &input);
+            tail
+        };
+        self.utf8_tail = tail;
         self.parser = Some(parser);
     }
 
@@ -129,31 +463,46 @@ impl VirtualTerminal {
             return;
         }
 
-        let mut new_grid = Self::make_grid(cols, rows);
-
-        // Copy existing content
-        let copy_rows = rows.min(self.rows);
-        let copy_cols = cols.min(self.cols);
-        for (r, new_row) in new_grid.iter_mut().enumerate().take(copy_rows) {
-            for (c, new_cell) in new_row.iter_mut().enumerate().take(copy_cols) {
-                *new_cell = self.grid[r][c].clone();
-            }
+        self.grid = Self::resize_grid(std::mem::take(&mut self.grid), cols, rows);
+        // The main screen saved under the alternate screen must match too
+        if let Some(saved) = self.saved_grid.take() {
+            self.saved_grid = Some(Self::resize_grid(saved, cols, rows));
         }
-
-        self.grid = new_grid;
         self.cols = cols;
         self.rows = rows;
+        self.last_printed = None;
 
         // Reset scroll region to full screen
         self.scroll_top = 0;
         self.scroll_bottom = rows;
 
-        // Clamp cursor
-        self.cursor.x = self.cursor.x.min(cols.saturating_sub(1));
-        self.cursor.y = self.cursor.y.min(rows.saturating_sub(1));
+        // Clamp cursor, including the ones waiting to be restored
+        for cursor in [Some(&mut self.cursor), self.saved_cursor.as_mut()]
+            .into_iter()
+            .chain([self.saved_main_cursor.as_mut()])
+            .flatten()
+        {
+            cursor.x = cursor.x.min(cols.saturating_sub(1));
+            cursor.y = cursor.y.min(rows.saturating_sub(1));
+        }
     }
 
-    pub fn grid(&self) -> &Vec<Vec<Cell>> {
+    /// `grid` cut or padded to `cols` x `rows`, keeping content at the top left
+    fn resize_grid(mut grid: Vec<Row>, cols: usize, rows: usize) -> Vec<Row> {
+        let mut new_grid = Self::make_grid(cols, rows);
+        for (new_row, old_row) in new_grid.iter_mut().zip(grid.iter_mut()) {
+            for (new_cell, old_cell) in new_row.iter_mut().zip(old_row.iter()) {
+                *new_cell = old_cell.clone();
+            }
+            new_row.marks = std::mem::take(&mut old_row.marks);
+            new_row.wrapped = old_row.wrapped;
+            // The padding is only the last cell while the width is the same
+            new_row.padded = old_row.padded && old_row.len() == cols;
+        }
+        new_grid
+    }
+
+    pub fn grid(&self) -> &Vec<Row> {
         &self.grid
     }
 
@@ -161,7 +510,7 @@ impl VirtualTerminal {
         &self.cursor
     }
 
-    pub fn scrollback(&self) -> &VecDeque<Vec<Cell>> {
+    pub fn scrollback(&self) -> &VecDeque<Row> {
         &self.scrollback
     }
 
@@ -182,24 +531,152 @@ impl VirtualTerminal {
         if row >= self.rows {
             return String::new();
         }
-        self.grid[row]
-            .iter()
-            .map(|c| {
-                if c.ch.is_empty() || c.ch == " " {
-                    " ".to_string()
-                } else {
-                    c.ch.clone()
-                }
-            })
-            .collect::<String>()
-            .trim_end()
-            .to_string()
+        row_to_string(&self.grid[row])
     }
 
     pub fn rows(&self) -> usize {
         self.rows
     }
 
+    /// Number of lines evicted from the top of the scrollback so far. Adding
+    /// it to a [`Self::line`] index gives a line number that stays valid as
+    /// old lines are dropped.
+    pub fn evicted_lines(&self) -> usize {
+        self.evicted_lines
+    }
+
+    /// Number of lines in scrollback plus the screen
+    pub fn total_lines(&self) -> usize {
+        self.scrollback.len() + self.grid.len()
+    }
+
+    /// Line `idx`, counting through the scrollback and then the screen
+    pub fn line(&self, idx: usize) -> Option<&Row> {
+        if idx < self.scrollback.len() {
+            self.scrollback.get(idx)
+        } else {
+            self.grid.get(idx - self.scrollback.len())
+        }
+    }
+
+    /// Index of the first line currently shown (see [`Self::line`])
+    pub fn view_top(&self) -> usize {
+        self.total_lines()
+            .saturating_sub(self.scroll_offset)
+            .saturating_sub(self.rows)
+    }
+
+    /// Scroll so that `line` is the first visible line, as far as the
+    /// scrollback allows
+    pub fn scroll_to_line(&mut self, line: usize) {
+        let total = self.total_lines();
+        let bottom = (line + self.rows).min(total);
+        self.set_scroll_offset(total - bottom);
+    }
+
+    /// Lines on which a prompt starts (OSC 133 ; A)
+    pub fn prompt_lines(&self) -> Vec<usize> {
+        (0..self.total_lines())
+            .filter(|&i| {
+                self.line(i)
+                    .is_some_and(|row| row.has_mark(|m| *m == SemanticMark::PromptStart))
+            })
+            .collect()
+    }
+
+    /// Command output blocks: each runs from an OSC 133 ; C line up to the
+    /// next prompt or command-end mark. A still-running command's block ends
+    /// at the last non-blank line.
+    pub fn output_blocks(&self) -> Vec<Range<usize>> {
+        let mut blocks = Vec::new();
+        let mut start: Option<usize> = None;
+        for i in 0..self.total_lines() {
+            let Some(row) = self.line(i) else {
+                continue;
+            };
+            for mark in &row.marks {
+                match mark {
+                    SemanticMark::OutputStart => start = Some(i),
+                    SemanticMark::PromptStart | SemanticMark::CommandEnd(_) => {
+                        if let Some(s) = start.take() {
+                            if i > s {
+                                blocks.push(s..i);
+                            }
+                        }
+                    }
+                    SemanticMark::CommandStart => {}
+                }
+            }
+        }
+        if let Some(s) = start {
+            let end = (s..self.total_lines())
+                .rev()
+                .find(|&i| {
+                    self.line(i)
+                        .is_some_and(|row| !row_to_string(row).is_empty())
+                })
+                .map_or(s, |i| i + 1);
+            if end > s {
+                blocks.push(s..end);
+            }
+        }
+        blocks
+    }
+
+    /// Plain text of a range of lines, with soft-wrapped rows joined
+    pub fn lines_text(&self, lines: Range<usize>) -> String {
+        if lines.is_empty() {
+            return String::new();
+        }
+        let end = (lines.end - 1, self.cols.saturating_sub(1));
+        self.logical_lines((lines.start, 0), end)
+            .iter()
+            .map(|line| cells_text(line))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Cells from `start` to `end` (inclusive `(line, col)` positions, see
+    /// [`Self::line`]) grouped into logical lines: soft-wrapped rows are
+    /// joined without their wrap padding, and trailing blanks are trimmed
+    /// only where a line really ends.
+    pub fn logical_lines(&self, start: (usize, usize), end: (usize, usize)) -> Vec<Vec<Cell>> {
+        let mut lines = Vec::new();
+        let mut current: Vec<Cell> = Vec::new();
+        for idx in start.0..=end.0 {
+            let Some(row) = self.line(idx) else {
+                continue;
+            };
+            let cells = row.text_cells();
+            let from = if idx == start.0 { start.1 } else { 0 };
+            let to = if idx == end.0 {
+                (end.1 + 1).min(cells.len())
+            } else {
+                cells.len()
+            };
+            current.extend_from_slice(&cells[from.min(to)..to]);
+            if !row.wrapped || idx == end.0 {
+                trim_trailing_blanks(&mut current);
+                lines.push(std::mem::take(&mut current));
+            }
+        }
+        lines
+    }
+
+    /// Concrete colour a cell is drawn with: OSC overrides first, then the
+    /// palette. `None`/`Reset` mean the default foreground or background.
+    pub fn color_rgb(&self, color: Option<Color>, foreground: bool) -> Rgb {
+        let default_slot = if foreground { 0 } else { 1 };
+        match color {
+            None | Some(Color::Reset) => self.dynamic_color(default_slot),
+            Some(Color::Rgb(r, g, b)) => (r, g, b),
+            Some(c) => match ansi_index(c) {
+                Some(idx) => self.indexed_color(idx),
+                None => self.dynamic_color(default_slot),
+            },
+        }
+    }
+
     /// Scroll within the scroll region up by one line
     fn scroll_up(&mut self) {
         if self.rows == 0 || self.scroll_top >= self.scroll_bottom {
@@ -211,6 +688,13 @@ impl VirtualTerminal {
             self.scrollback.push_back(removed);
             if self.scrollback.len() > MAX_SCROLLBACK {
                 self.scrollback.pop_front();
+                if self.saved_grid.is_none() {
+                    self.evicted_lines += 1;
+                }
+            }
+            // Keep a scrolled-back view on the same content while output arrives
+            if self.scroll_offset > 0 {
+                self.set_scroll_offset(self.scroll_offset + 1);
             }
         }
         // Insert blank row at the bottom of the scroll region
@@ -230,12 +714,54 @@ impl VirtualTerminal {
         self.grid.insert(self.scroll_top, self.make_row());
     }
 
+    /// Try to append `ch` to the grapheme cluster printed just before it.
+    /// Widens the cell in place when the cluster turns into a wide emoji.
+    fn extend_last_grapheme(&mut self, ch: char) -> bool {
+        let Some((y, x)) = self.last_printed else {
+            return false;
+        };
+        if y >= self.rows || x >= self.cols {
+            return false;
+        }
+        let old = &self.grid[y][x].ch;
+        let mut joined = String::with_capacity(old.len() + ch.len_utf8());
+        joined.push_str(old);
+        joined.push(ch);
+        if joined.graphemes(true).count() != 1 {
+            return false;
+        }
+
+        let old_width = grapheme_width(old, self.ambiguous_width);
+        let new_width = grapheme_width(&joined, self.ambiguous_width);
+        self.grid[y][x].ch = joined;
+
+        // Cluster became wide (e.g. "❤" + VS16, or a flag): claim the next cell
+        // if the cursor is still sitting right after it.
+        if old_width < 2
+            && new_width == 2
+            && x + 1 < self.cols
+            && self.cursor.y == y
+            && self.cursor.x == x + 1
+        {
+            self.grid[y][x + 1] = Cell {
+                ch: String::new(),
+                style: self.grid[y][x].style,
+            };
+            self.cursor.x += 1;
+        }
+        true
+    }
+
     fn put_char(&mut self, ch: char) {
-        // Combining/zero-width characters merge into previous cell
-        let char_width = unicode_width::UnicodeWidthChar::width(ch);
-        if char_width == Some(0) || char_width.is_none() {
+        if self.extend_last_grapheme(ch) {
+            return;
+        }
+
+        // Stray zero-width characters merge into the cell before the cursor
+        let w = grapheme_width(ch.encode_utf8(&mut [0u8; 4]), self.ambiguous_width);
+        if w == 0 {
             if self.cursor.x > 0 && self.cursor.y < self.rows {
-                let prev_x = self.cursor.x - 1;
+                let prev_x = (self.cursor.x - 1).min(self.cols.saturating_sub(1));
                 // If previous cell is a continuation cell (empty string from wide char),
                 // merge into the cell before it instead
                 if self.grid[self.cursor.y][prev_x].ch.is_empty() && prev_x > 0 {
@@ -249,6 +775,7 @@ impl VirtualTerminal {
 
         if self.cursor.x >= self.cols {
             // Line wrap
+            self.mark_wrapped();
             self.cursor.x = 0;
             self.cursor.y += 1;
             if self.cursor.y >= self.rows {
@@ -258,14 +785,15 @@ impl VirtualTerminal {
         }
 
         // Wide char boundary check: if a 2-cell char can't fit, pad and wrap
-        let w = char_width.unwrap_or(1);
         if w == 2 && self.cursor.x + 1 >= self.cols {
             if self.cursor.y < self.rows && self.cursor.x < self.cols {
                 self.grid[self.cursor.y][self.cursor.x] = Cell {
                     ch: " ".to_string(),
                     style: self.current_style,
                 };
+                self.grid[self.cursor.y].padded = true;
             }
+            self.mark_wrapped();
             self.cursor.x = 0;
             self.cursor.y += 1;
             if self.cursor.y >= self.rows {
@@ -279,6 +807,10 @@ impl VirtualTerminal {
                 ch: ch.to_string(),
                 style: self.current_style,
             };
+            if self.cursor.x + 1 == self.cols {
+                self.grid[self.cursor.y].padded = false;
+            }
+            self.last_printed = Some((self.cursor.y, self.cursor.x));
         }
 
         self.cursor.x += 1;
@@ -412,6 +944,8 @@ impl VirtualTerminal {
                 for c in self.cursor.x..self.cols {
                     self.grid[self.cursor.y][c] = Cell::default();
                 }
+                self.grid[self.cursor.y].wrapped = false;
+                self.grid[self.cursor.y].padded = false;
                 // Clear all lines below
                 for r in (self.cursor.y + 1)..self.rows {
                     self.grid[r] = self.make_row();
@@ -433,6 +967,7 @@ impl VirtualTerminal {
                 for r in 0..self.rows {
                     self.grid[r] = self.make_row();
                 }
+                self.drop_screen_images();
             }
             _ => {}
         }
@@ -448,6 +983,8 @@ impl VirtualTerminal {
                 for c in self.cursor.x..self.cols {
                     self.grid[self.cursor.y][c] = Cell::default();
                 }
+                self.grid[self.cursor.y].wrapped = false;
+                self.grid[self.cursor.y].padded = false;
             }
             // Erase from start of line to cursor
             1 => {
@@ -455,9 +992,11 @@ impl VirtualTerminal {
                     self.grid[self.cursor.y][c] = Cell::default();
                 }
             }
-            // Erase entire line
+            // Erase entire line (keeps the row's semantic marks)
             2 => {
-                self.grid[self.cursor.y] = self.make_row();
+                self.grid[self.cursor.y].fill(Cell::default());
+                self.grid[self.cursor.y].wrapped = false;
+                self.grid[self.cursor.y].padded = false;
             }
             _ => {}
         }
@@ -532,6 +1071,7 @@ impl VirtualTerminal {
         self.saved_grid = Some(self.grid.clone());
         self.saved_scrollback = Some(self.scrollback.clone());
         self.saved_main_cursor = Some(self.cursor.clone());
+        self.saved_placements = Some(std::mem::take(&mut self.placements));
         self.grid = Self::make_grid(self.cols, self.rows);
         self.scrollback.clear();
         self.cursor = CursorState::default();
@@ -541,6 +1081,9 @@ impl VirtualTerminal {
         if let Some(grid) = self.saved_grid.take() {
             self.grid = grid;
         }
+        if let Some(placements) = self.saved_placements.take() {
+            self.placements = placements;
+        }
         if let Some(scrollback) = self.saved_scrollback.take() {
             self.scrollback = scrollback;
         }
@@ -548,6 +1091,207 @@ impl VirtualTerminal {
             self.cursor = cursor;
         }
     }
+
+    /// Buffer line of screen row `y` (see [`Placement::line`])
+    fn stable_line(&self, y: usize) -> usize {
+        self.evicted_lines + self.scrollback.len() + y
+    }
+
+    /// Show `image` at the cursor, `size` cells large (0 = from its pixel
+    /// size). Returns the size used.
+    fn place_image(
+        &mut self,
+        image: Arc<Image>,
+        source: ImageSource,
+        size: (usize, usize),
+    ) -> (usize, usize) {
+        let natural = cell_extent(&image, self.cell_size);
+        let cols = if size.0 > 0 { size.0 } else { natural.0 };
+        let rows = if size.1 > 0 { size.1 } else { natural.1 };
+        let first_line = self.evicted_lines;
+        self.placements.retain(|p| p.line + p.rows > first_line);
+        if self.placements.len() >= MAX_PLACEMENTS {
+            self.placements.remove(0);
+        }
+        self.placements.push(Placement {
+            image,
+            source,
+            line: self.stable_line(self.cursor.y),
+            col: self.cursor.x,
+            cols,
+            rows,
+            serial: self.next_image_serial,
+        });
+        self.next_image_serial += 1;
+        (cols, rows)
+    }
+
+    /// Forget images that reach onto the screen, as when it is cleared
+    fn drop_screen_images(&mut self) {
+        let top = self.stable_line(0);
+        self.placements.retain(|p| p.line + p.rows <= top);
+    }
+
+    fn index(&mut self, count: usize) {
+        for _ in 0..count {
+            if self.cursor.y + 1 >= self.scroll_bottom {
+                self.scroll_up();
+            } else {
+                self.cursor.y = (self.cursor.y + 1).min(self.rows.saturating_sub(1));
+            }
+        }
+    }
+
+    /// An APC string cut out of the input. Only kitty graphics (`G`) are
+    /// understood.
+    fn apc_dispatch(&mut self, body: &[u8]) {
+        let Some(body) = body.strip_prefix(b"G") else {
+            return;
+        };
+        let (control, payload) = match body.iter().position(|&b| b == b';') {
+            Some(i) => (&body[..i], &body[i + 1..]),
+            None => (body, &[][..]),
+        };
+        let command = KittyCommand::parse(control);
+
+        // Chunked transmission: keys come with the first chunk only
+        if let Some((first, mut data)) = self.kitty_upload.take() {
+            data.extend_from_slice(payload);
+            if command.more && data.len() < MAX_SEQUENCE_BYTES {
+                self.kitty_upload = Some((first, data));
+            } else {
+                self.kitty_command(&first, &data);
+            }
+        } else if command.more {
+            self.kitty_upload = Some((command, payload.to_vec()));
+        } else {
+            self.kitty_command(&command, payload);
+        }
+    }
+
+    fn kitty_command(&mut self, command: &KittyCommand, payload: &[u8]) {
+        let result = match command.action {
+            b'd' => {
+                let free = command.delete.is_ascii_uppercase();
+                match command.delete.to_ascii_lowercase() {
+                    b'i' => {
+                        self.placements.retain(
+                            |p| !matches!(p.source, ImageSource::Kitty(id) if id == command.id),
+                        );
+                        if free {
+                            self.kitty_images.remove(&command.id);
+                        }
+                    }
+                    _ => {
+                        self.drop_screen_images();
+                        if free {
+                            self.kitty_images.clear();
+                        }
+                    }
+                }
+                return;
+            }
+            b'p' => match self.kitty_images.get(&command.id).cloned() {
+                Some(image) => {
+                    self.show_kitty_image(command, image);
+                    Ok(())
+                }
+                None => Err("ENOENT:no such image".to_string()),
+            },
+            b't' | b'T' | b'q' => base64_decode(payload)
+                .ok_or_else(|| "EINVAL:bad base64 data".to_string())
+                .and_then(|data| command.decode(&data))
+                .map(|image| {
+                    let image = Arc::new(image);
+                    if command.action != b'q' && command.id != 0 {
+                        if self.kitty_images.len() >= MAX_KITTY_IMAGES {
+                            self.kitty_images.clear();
+                        }
+                        self.kitty_images.insert(command.id, image.clone());
+                    }
+                    if command.action == b'T' {
+                        self.show_kitty_image(command, image);
+                    }
+                }),
+            _ => Err("EINVAL:unknown action".to_string()),
+        };
+
+        // Replies go only to commands that name an image, unless silenced
+        let reply = match result {
+            Ok(()) if command.quiet == 0 => "OK".to_string(),
+            Err(error) if command.quiet < 2 => error,
+            _ => return,
+        };
+        if command.id != 0 {
+            self.response_queue
+                .push(format!("\x1b_Gi={};{reply}\x1b\\", command.id).into_bytes());
+        }
+    }
+
+    /// Place a kitty image at the cursor and, unless `C=1`, move the cursor
+    /// past it: right of the image, on its last row
+    fn show_kitty_image(&mut self, command: &KittyCommand, image: Arc<Image>) {
+        let (cols, rows) = self.place_image(
+            image,
+            ImageSource::Kitty(command.id),
+            (command.cols, command.rows),
+        );
+        if !command.hold_cursor {
+            self.index(rows - 1);
+            self.cursor.x = (self.cursor.x + cols).min(self.cols.saturating_sub(1));
+        }
+    }
+}
+
+/// Length of an unfinished UTF-8 sequence at the end of `bytes` (0 if the
+/// input ends on a character boundary or in invalid data)
+fn incomplete_utf8_len(bytes: &[u8]) -> usize {
+    let continuation = bytes
+        .iter()
+        .rev()
+        .take(3)
+        .take_while(|&&b| b & 0xC0 == 0x80)
+        .count();
+    let Some(&lead) = bytes.len().checked_sub(continuation + 1).map(|i| &bytes[i]) else {
+        return 0;
+    };
+    let needed = match lead {
+        0xC2..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF4 => 4,
+        _ => return 0,
+    };
+    if continuation + 1 < needed {
+        continuation + 1
+    } else {
+        0
+    }
+}
+
+fn row_to_string(row: &Row) -> String {
+    row.iter()
+        .map(|c| {
+            if c.ch.is_empty() || c.ch == " " {
+                " ".to_string()
+            } else {
+                c.ch.clone()
+            }
+        })
+        .collect::<String>()
+        .trim_end()
+        .to_string()
+}
+
+/// Text of a run of cells; wide-char continuation cells contribute nothing
+pub fn cells_text(cells: &[Cell]) -> String {
+    cells.iter().map(|c| c.ch.as_str()).collect()
+}
+
+/// Drop trailing blank cells (the padding after a line's last character)
+pub fn trim_trailing_blanks(cells: &mut Vec<Cell>) {
+    while cells.last().is_some_and(|c| c.ch == " " || c.ch.is_empty()) {
+        cells.pop();
+    }
 }
 
 fn percent_decode(input: &str) -> String {
@@ -568,7 +1312,7 @@ fn percent_decode(input: &str) -> String {
     String::from_utf8_lossy(&result).into_owned()
 }
 
-fn base64_decode(input: &str) -> Option<String> {
+fn base64_decode(input: &[u8]) -> Option<Vec<u8>> {
     fn decode_char(c: u8) -> Option<u8> {
         match c {
             b'A'..=b'Z' => Some(c - b'A'),
@@ -581,7 +1325,8 @@ fn base64_decode(input: &str) -> Option<String> {
     }
 
     let input: Vec<u8> = input
-        .bytes()
+        .iter()
+        .copied()
         .filter(|&b| b != b'=' && b != b'\n' && b != b'\r')
         .collect();
     let mut output = Vec::with_capacity(input.len() * 3 / 4);
@@ -602,7 +1347,7 @@ fn base64_decode(input: &str) -> Option<String> {
         }
     }
 
-    String::from_utf8(output).ok()
+    Some(output)
 }
 
 impl Perform for VirtualTerminal {
@@ -611,9 +1356,12 @@ impl Perform for VirtualTerminal {
     }
 
     fn execute(&mut self, byte: u8) {
+        self.last_printed = None;
         match byte {
             // BEL
-            7 => {}
+            7 => {
+                self.bell = true;
+            }
             // Backspace
             8 => {
                 self.cursor.x = self.cursor.x.saturating_sub(1);
@@ -640,20 +1388,122 @@ impl Perform for VirtualTerminal {
         }
     }
 
-    fn hook(&mut self, _params: &Params, _intermediates: &[u8], _ignore: bool, _action: char) {
-        // DCS sequences - not needed for basic terminal emulation
+    fn hook(&mut self, params: &Params, intermediates: &[u8], _ignore: bool, action: char) {
+        // Sixel graphics are the only DCS we act on
+        if action != 'q' || !intermediates.is_empty() {
+            return;
+        }
+        let p: Vec<u16> = params.iter().map(|p| p[0]).collect();
+        let mut raw = b"\x1bP".to_vec();
+        // All-default parameters are left out, as most encoders do
+        if p.iter().any(|&v| v != 0) {
+            let list: Vec<String> = p.iter().map(u16::to_string).collect();
+            raw.extend_from_slice(list.join(";").as_bytes());
+        }
+        raw.push(b'q');
+        self.sixel = Some((SixelDecoder::new(&p), Some(raw)));
     }
 
-    fn put(&mut self, _byte: u8) {
-        // DCS data bytes
+    fn put(&mut self, byte: u8) {
+        if let Some((decoder, raw)) = self.sixel.as_mut() {
+            decoder.put(byte);
+            if raw.as_ref().is_some_and(|r| r.len() >= MAX_SEQUENCE_BYTES) {
+                *raw = None;
+            } else if let Some(raw) = raw {
+                raw.push(byte);
+            }
+        }
     }
 
     fn unhook(&mut self) {
-        // End of DCS sequence
-    }
-
-    fn osc_dispatch(&mut self, params: &[&[u8]], _bell_terminated: bool) {
+        let Some((decoder, raw)) = self.sixel.take() else {
+            return;
+        };
+        let Some(image) = decoder.finish() else {
+            return;
+        };
+        let raw = raw.map(|mut raw| {
+            raw.extend_from_slice(b"\x1b\\");
+            raw
+        });
+        // Without the raw sequence the image can only be approximated
+        let source = ImageSource::Sixel(raw.unwrap_or_default().into());
+        let (_, rows) = self.place_image(Arc::new(image), source, (0, 0));
+        // Like the VT340: text continues on the line below the image
+        self.index(rows);
+    }
+
+    fn osc_dispatch(&mut self, params: &[&[u8]], bell_terminated: bool) {
         if let Some(first) = params.first() {
+            // OSC 4: Set/query palette entries
+            // Format: OSC 4 ; index ; spec [; index ; spec ...] ST (spec "?" queries)
+            if *first == b"4" {
+                for pair in params[1..].chunks(2) {
+                    let (Some(idx), Some(spec)) = (
+                        pair.first()
+                            .and_then(|p| std::str::from_utf8(p).ok())
+                            .and_then(|p| p.parse::<u8>().ok()),
+                        pair.get(1).and_then(|p| std::str::from_utf8(p).ok()),
+                    ) else {
+                        break;
+                    };
+                    if spec == "?" {
+                        let color = format_color_spec(self.indexed_color(idx));
+                        self.push_osc_reply(format!("4;{idx};{color}"), bell_terminated);
+                    } else if let Some(rgb) = parse_color_spec(spec) {
+                        self.color_overrides[idx as usize] = Some(rgb);
+                    }
+                }
+            }
+
+            // OSC 104: Reset palette entries (all of them when no index is given)
+            if *first == b"104" {
+                let indices: Vec<u8> = params[1..]
+                    .iter()
+                    .filter_map(|p| std::str::from_utf8(p).ok()?.parse().ok())
+                    .collect();
+                if indices.is_empty() {
+                    *self.color_overrides = [None; 256];
+                } else {
+                    for idx in indices {
+                        self.color_overrides[idx as usize] = None;
+                    }
+                }
+            }
+
+            // OSC 10/11/12: Set/query default foreground, background, cursor colour.
+            // Extra parameters address the following slots (OSC 10 ; fg ; bg ST).
+            if let Some(base) = match *first {
+                b"10" => Some(0),
+                b"11" => Some(1),
+                b"12" => Some(2),
+                _ => None,
+            } {
+                for (offset, spec) in params[1..].iter().enumerate() {
+                    let slot = base + offset;
+                    if slot > 2 {
+                        break;
+                    }
+                    let Ok(spec) = std::str::from_utf8(spec) else {
+                        continue;
+                    };
+                    if spec == "?" {
+                        let color = format_color_spec(self.dynamic_color(slot));
+                        self.push_osc_reply(format!("{};{color}", 10 + slot), bell_terminated);
+                    } else if let Some(rgb) = parse_color_spec(spec) {
+                        self.dynamic_colors[slot] = Some(rgb);
+                    }
+                }
+            }
+
+            // OSC 110/111/112: Reset default foreground, background, cursor colour
+            match *first {
+                b"110" => self.dynamic_colors[0] = None,
+                b"111" => self.dynamic_colors[1] = None,
+                b"112" => self.dynamic_colors[2] = None,
+                _ => {}
+            }
+
             // OSC 7: Current working directory reporting
             // Format: OSC 7 ; file://hostname/path ST
             if *first == b"7" {
@@ -671,12 +1521,66 @@ impl Perform for VirtualTerminal {
                 }
             }
 
+            // OSC 133: Semantic prompt marks (FinalTerm shell integration)
+            // Format: OSC 133 ; A|B|C|D [; exit-code] ST
+            if *first == b"133" && self.saved_grid.is_none() {
+                let mark = match params.get(1).and_then(|p| p.first()) {
+                    Some(b'A') => Some(SemanticMark::PromptStart),
+                    Some(b'B') => Some(SemanticMark::CommandStart),
+                    Some(b'C') => Some(SemanticMark::OutputStart),
+                    Some(b'D') => Some(SemanticMark::CommandEnd(
+                        params
+                            .get(2)
+                            .and_then(|p| std::str::from_utf8(p).ok())
+                            .and_then(|p| p.parse().ok()),
+                    )),
+                    _ => None,
+                };
+                if let (Some(mark), Some(row)) = (mark, self.grid.get_mut(self.cursor.y)) {
+                    row.marks.push(mark);
+                }
+            }
+
+            // OSC 9: iTerm2-style notification
+            // Format: OSC 9 ; message ST
+            // (ConEmu reuses OSC 9 ; <number> ; ... for progress etc. — ignore those)
+            if *first == b"9" && params.len() >= 2 {
+                let is_conemu = params.len() > 2 && params[1].iter().all(u8::is_ascii_digit);
+                if !is_conemu {
+                    let body = params[1..]
+                        .iter()
+                        .map(|p| String::from_utf8_lossy(p))
+                        .collect::<Vec<_>>()
+                        .join(";");
+                    self.notifications.push(Notification { title: None, body });
+                }
+            }
+
+            // OSC 777: rxvt/urxvt-style notification
+            // Format: OSC 777 ; notify ; title ; body ST
+            if *first == b"777" && params.get(1) == Some(&&b"notify"[..]) {
+                let title = params
+                    .get(2)
+                    .map(|p| String::from_utf8_lossy(p).into_owned())
+                    .filter(|t| !t.is_empty());
+                let body = params
+                    .get(3..)
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|p| String::from_utf8_lossy(p))
+                    .collect::<Vec<_>>()
+                    .join(";");
+                self.notifications.push(Notification { title, body });
+            }
+
             // OSC 52: Clipboard manipulation
             // Format: OSC 52 ; <selection> ; <base64-data> ST
             if *first == b"52" {
                 if let Some(data_bytes) = params.get(2) {
                     if let Ok(data_str) = std::str::from_utf8(data_bytes) {
-                        if let Some(decoded) = base64_decode(data_str) {
+                        if let Some(decoded) = base64_decode(data_str.as_bytes())
+                            .and_then(|data| String::from_utf8(data).ok())
+                        {
                             self.clipboard_requests.push(decoded);
                         }
                     }
@@ -685,8 +1589,14 @@ impl Perform for VirtualTerminal {
         }
     }
 
+    // Arms check their intermediates inside, so a sequence with other
+    // intermediates is ignored rather than reaching a later arm
+    #[allow(clippy::collapsible_match)]
     fn csi_dispatch(&mut self, params: &Params, intermediates: &[u8], _ignore: bool, action: char) {
         let p: Vec<u16> = params.iter().map(|p| p[0]).collect();
+        if action != 'm' {
+            self.last_printed = None;
+        }
 
         match action {
             // CUP / HVP - Cursor Position
@@ -821,11 +1731,14 @@ impl Perform for VirtualTerminal {
                             1004 => {
                                 self.focus_tracking = set;
                             }
+                            // 12 = Blinking cursor (att610)
+                            12 => {
+                                self.cursor_style.blinking = set;
+                            }
                             // Modes we acknowledge but don't need special handling for:
                             // 1 = DECCKM (cursor key mode), 7 = DECAWM (auto-wrap),
-                            // 12 = blinking cursor, 1000/1002/1003/1006 = mouse modes,
-                            // 2004 = bracketed paste
-                            1 | 7 | 12 | 1000 | 1002 | 1003 | 1006 | 2004 => {
+                            // 1000/1002/1003/1006 = mouse modes, 2004 = bracketed paste
+                            1 | 7 | 1000 | 1002 | 1003 | 1006 | 2004 => {
                                 // Silently accept — these affect input handling,
                                 // not our grid rendering
                             }
@@ -834,6 +1747,20 @@ impl Perform for VirtualTerminal {
                     }
                 }
             }
+            // DECSCUSR - Set Cursor Style (CSI Ps SP q)
+            'q' if intermediates == b" " => {
+                let (shape, blinking) = match p.first().copied().unwrap_or(0) {
+                    0 => (CursorShape::Default, false),
+                    1 => (CursorShape::Block, true),
+                    2 => (CursorShape::Block, false),
+                    3 => (CursorShape::Underline, true),
+                    4 => (CursorShape::Underline, false),
+                    5 => (CursorShape::Bar, true),
+                    6 => (CursorShape::Bar, false),
+                    _ => return,
+                };
+                self.cursor_style = CursorStyle { shape, blinking };
+            }
             // DECSC / DECRC via CSI s / CSI u
             's' => {
                 self.saved_cursor = Some(self.cursor.clone());
@@ -847,14 +1774,34 @@ impl Perform for VirtualTerminal {
             'r' => {
                 if intermediates.is_empty() {
                     let top = p.first().copied().unwrap_or(1).max(1) as usize - 1;
-                    let bottom = p.get(1).copied().unwrap_or(self.rows as u16) as usize;
-                    self.scroll_top = top.min(self.rows);
-                    self.scroll_bottom = bottom.min(self.rows).max(self.scroll_top + 1);
-                    // DECSTBM resets cursor to home
-                    self.cursor.x = 0;
-                    self.cursor.y = 0;
+                    let bottom = match p.get(1).copied() {
+                        None | Some(0) => self.rows,
+                        Some(bottom) => (bottom as usize).min(self.rows),
+                    };
+                    // A region needs at least two lines; anything else is ignored
+                    if top + 1 < bottom {
+                        self.scroll_top = top;
+                        self.scroll_bottom = bottom;
+                        // DECSTBM resets cursor to home
+                        self.cursor.x = 0;
+                        self.cursor.y = 0;
+                    }
                 }
             }
+            // DA1 - Primary Device Attributes: VT220 with sixel graphics
+            'c' if intermediates.is_empty() && p.first().copied().unwrap_or(0) == 0 => {
+                self.response_queue.push(b"\x1b[?62;4;22c".to_vec());
+            }
+            // XTWINOPS - pixel size reports, so image tools can size output
+            't' if intermediates.is_empty() => {
+                let (width, height) = self.cell_size;
+                let reply = match p.first().copied().unwrap_or(0) {
+                    14 => format!("\x1b[4;{};{}t", self.rows * height, self.cols * width),
+                    16 => format!("\x1b[6;{height};{width}t"),
+                    _ => return,
+                };
+                self.response_queue.push(reply.into_bytes());
+            }
             // DSR - Device Status Report
             'n' => {
                 let code = p.first().copied().unwrap_or(0);
@@ -877,6 +1824,7 @@ impl Perform for VirtualTerminal {
     }
 
     fn esc_dispatch(&mut self, _intermediates: &[u8], _ignore: bool, byte: u8) {
+        self.last_printed = None;
         match byte {
             // IND - Index (move down, scroll if at bottom of scroll region)
             b'D' => {
@@ -909,8 +1857,21 @@ impl Perform for VirtualTerminal {
                 let cols = self.cols;
                 let rows = self.rows;
                 let parser = self.parser.take();
+                let ambiguous_width = self.ambiguous_width;
+                let palette = std::mem::take(&mut self.palette);
+                let cell_size = self.cell_size;
+                // Input the APC scanner is holding back is still to come
+                let apc = std::mem::take(&mut self.apc);
+                // Every line before the reset is gone, so buffer lines
+                // anchored to it (selections, search matches) find nothing
+                let evicted_lines = self.stable_line(rows);
                 *self = Self::new(cols, rows);
+                self.evicted_lines = evicted_lines;
                 self.parser = parser;
+                self.apc = apc;
+                self.ambiguous_width = ambiguous_width;
+                self.palette = palette;
+                self.cell_size = cell_size;
             }
             _ => {}
         }
@@ -0,0 +1,83 @@
+use cltree::asciicast::{Cast, EventKind, Recorder};
+
+#[test]
+fn test_asciicast_parse() {
+    let data = concat!(
+        "{\"version\": 2, \"width\": 80, \"height\": 24, \"idle_time_limit\": 1.0}\n",
+        "[0.5, \"o\", \"hello\\r\\n\"]\n",
+        "[0.6, \"i\", \"x\"]\n",
+        "[4.5, \"r\", \"100x30\"]\n",
+        "\n",
+        "[5.0, \"o\", \"\\u001b[1mbold\"]\n",
+    );
+    let cast = Cast::parse(data.as_bytes()).unwrap();
+    assert_eq!((cast.width, cast.height), (80, 24));
+    let kinds: Vec<_> = cast.events.iter().map(|e| e.kind.clone()).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            EventKind::Output("hello\r\n".to_string()),
+            EventKind::Resize(100, 30),
+            EventKind::Output("\x1b[1mbold".to_string()),
+        ]
+    );
+    // The 4s pause is capped at idle_time_limit
+    let times: Vec<f64> = cast.events.iter().map(|e| e.time).collect();
+    assert_eq!(times, vec![0.5, 1.5, 2.0]);
+    assert_eq!(cast.duration(), 2.0);
+
+    assert!(Cast::parse("{\"version\": 1}\n".as_bytes()).is_err());
+    assert!(Cast::parse("".as_bytes()).is_err());
+}
+
+#[test]
+fn test_asciicast_record_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("session.cast");
+    let recorder = Recorder::create(&path, "xterm-256color").unwrap();
+    recorder.resize(40, 10);
+    recorder.output(b"caf\xc3");
+    recorder.output(b"\xa9 \"quoted\"\n");
+    recorder.resize(60, 20);
+    drop(recorder);
+
+    let cast = Cast::load(&path).unwrap();
+    assert_eq!((cast.width, cast.height), (40, 10));
+    let kinds: Vec<_> = cast.events.into_iter().map(|e| e.kind).collect();
+    // The split UTF-8 sequence is held back rather than mangled
+    assert_eq!(
+        kinds,
+        vec![
+            EventKind::Output("caf".to_string()),
+            EventKind::Output("é \"quoted\"\n".to_string()),
+            EventKind::Resize(60, 20),
+        ]
+    );
+}
+
+#[test]
+fn test_recording_header_has_the_first_real_size_and_term() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("session.cast");
+    let recorder = Recorder::create(&path, "screen-256color").unwrap();
+    // Output that arrives before the pane is laid out is kept, in order
+    recorder.output(b"early");
+    recorder.resize(132, 43);
+    recorder.resize(132, 43);
+    recorder.output(b"late");
+    drop(recorder);
+
+    let cast = Cast::load(&path).unwrap();
+    assert_eq!((cast.width, cast.height), (132, 43));
+    let kinds: Vec<_> = cast.events.into_iter().map(|e| e.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            EventKind::Output("early".to_string()),
+            EventKind::Output("late".to_string()),
+        ]
+    );
+    let header = std::fs::read_to_string(&path).unwrap();
+    let header: serde_json::Value = serde_json::from_str(header.lines().next().unwrap()).unwrap();
+    assert_eq!(header["env"]["TERM"], "screen-256color");
+}
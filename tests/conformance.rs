@@ -0,0 +1,85 @@
+//! Escape-sequence conformance fixtures.
+//!
+//! Every `tests/fixtures/<name>.cast` (asciicast v2, as written by
+//! `cltree --record`) is replayed through a fresh `VirtualTerminal`, and the
+//! resulting scrollback+screen dump is compared with `<name>.dump`. To add a
+//! case, drop in a recording and run `UPDATE_GOLDEN=1 cargo test --test
+//! conformance`, then review the new dump.
+
+use cltree::asciicast::{Cast, EventKind};
+use cltree::snapshot::{assert_golden, Scope};
+use cltree::vterm::VirtualTerminal;
+use std::path::{Path, PathBuf};
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+fn replay(cast: &Cast) -> VirtualTerminal {
+    let mut vt = VirtualTerminal::new(cast.width as usize, cast.height as usize);
+    for event in &cast.events {
+        match &event.kind {
+            EventKind::Output(data) => vt.feed(data.as_bytes()),
+            EventKind::Resize(cols, rows) => vt.resize(*cols as usize, *rows as usize),
+        }
+    }
+    vt
+}
+
+#[test]
+fn test_fixtures_match_expected_dumps() {
+    let mut casts: Vec<PathBuf> = std::fs::read_dir(fixtures_dir())
+        .expect("tests/fixtures is missing")
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "cast"))
+        .collect();
+    casts.sort();
+    assert!(!casts.is_empty(), "no fixtures found");
+
+    let mut failures = Vec::new();
+    for path in &casts {
+        let cast = Cast::load(path).unwrap_or_else(|e| panic!("{}: {e:#}", path.display()));
+        let dump = replay(&cast).snapshot(Scope::All).dump();
+        let expected = path.with_extension("dump");
+        if std::panic::catch_unwind(|| assert_golden(&expected, &dump)).is_err() {
+            failures.push(path.file_name().unwrap_or_default().to_string_lossy());
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "fixtures differ: {}",
+        failures.join(", ")
+    );
+}
+
+#[test]
+fn test_fixtures_are_chunking_independent() {
+    // The same bytes fed one at a time must give the same result: the
+    // parser and grapheme handling may not depend on read boundaries.
+    for entry in std::fs::read_dir(fixtures_dir()).expect("tests/fixtures is missing") {
+        let path = entry.expect("unreadable fixture").path();
+        if path.extension().is_none_or(|ext| ext != "cast") {
+            continue;
+        }
+        let cast = Cast::load(&path).expect("invalid fixture");
+        let whole = replay(&cast).snapshot(Scope::All).dump();
+
+        let mut vt = VirtualTerminal::new(cast.width as usize, cast.height as usize);
+        for event in &cast.events {
+            match &event.kind {
+                EventKind::Output(data) => {
+                    for byte in data.as_bytes() {
+                        vt.feed(std::slice::from_ref(byte));
+                    }
+                }
+                EventKind::Resize(cols, rows) => vt.resize(*cols as usize, *rows as usize),
+            }
+        }
+        assert_eq!(
+            vt.snapshot(Scope::All).dump(),
+            whole,
+            "{} differs when fed byte by byte",
+            path.display()
+        );
+    }
+}
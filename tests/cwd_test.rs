@@ -0,0 +1,137 @@
+use cltree::cwd::{CwdDetector, CwdTracker, PaneState, RegexDetector, ScreenDetector, Verdict};
+use cltree::vterm::VirtualTerminal;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+
+/// Reports whatever path it is given, as a guess or not
+struct FixedDetector {
+    path: std::sync::Mutex<Option<PathBuf>>,
+    guesses: bool,
+}
+
+impl CwdDetector for FixedDetector {
+    fn name(&self) -> &'static str {
+        if self.guesses {
+            "guess"
+        } else {
+            "fixed"
+        }
+    }
+
+    fn detect(&self, _pane: &PaneState) -> Option<PathBuf> {
+        self.path.lock().unwrap().clone()
+    }
+
+    fn guesses(&self) -> bool {
+        self.guesses
+    }
+}
+
+fn fixed(path: &Path, guesses: bool) -> Box<FixedDetector> {
+    Box::new(FixedDetector {
+        path: std::sync::Mutex::new(Some(path.to_path_buf())),
+        guesses,
+    })
+}
+
+#[test]
+fn test_cwd_tracker_follows_deeper_and_settles_shallower_guesses() {
+    let vt = VirtualTerminal::new(10, 2);
+    let pane = PaneState {
+        vterm: &vt,
+        pid: None,
+    };
+    let root = Path::new("/a");
+    let deep = Path::new("/a/b/c");
+
+    let mut tracker = CwdTracker::new(vec![fixed(deep, true)], 3);
+    assert_eq!(tracker.tick(root, &pane), Some(deep.to_path_buf()));
+    assert_eq!(tracker.winner(), Some("guess"));
+
+    let mut tracker = CwdTracker::new(vec![fixed(root, true)], 3);
+    assert_eq!(tracker.tick(deep, &pane), None);
+    assert_eq!(tracker.readings()[0].verdict, Verdict::Settling(1));
+    assert_eq!(tracker.tick(deep, &pane), None);
+    assert_eq!(tracker.tick(deep, &pane), Some(root.to_path_buf()));
+    assert_eq!(tracker.readings()[0].verdict, Verdict::Followed);
+
+    // A guess at the same depth is ignored; a report is followed
+    let other = Path::new("/a/b/d");
+    let mut tracker = CwdTracker::new(vec![fixed(other, true)], 3);
+    assert_eq!(tracker.tick(deep, &pane), None);
+    assert_eq!(tracker.readings()[0].verdict, Verdict::Ignored);
+    let mut tracker = CwdTracker::new(vec![fixed(other, false)], 3);
+    assert_eq!(tracker.tick(deep, &pane), Some(other.to_path_buf()));
+}
+
+#[test]
+fn test_cwd_tracker_priority_order() {
+    let vt = VirtualTerminal::new(10, 2);
+    let pane = PaneState {
+        vterm: &vt,
+        pid: None,
+    };
+    let current = Path::new("/a/b");
+
+    // A shallower report passes over to the next detector
+    let mut tracker = CwdTracker::new(
+        vec![
+            fixed(Path::new("/a"), false),
+            fixed(Path::new("/a/b/c"), true),
+        ],
+        8,
+    );
+    assert_eq!(tracker.tick(current, &pane), Some(PathBuf::from("/a/b/c")));
+    let verdicts: Vec<_> = tracker.readings().iter().map(|r| r.verdict).collect();
+    assert_eq!(verdicts, [Verdict::Ignored, Verdict::Followed]);
+
+    // Once one detector moves the directory, the rest are skipped
+    let mut tracker = CwdTracker::new(
+        vec![
+            fixed(Path::new("/a/b/x"), false),
+            fixed(Path::new("/a/b/c"), true),
+        ],
+        8,
+    );
+    assert_eq!(tracker.tick(current, &pane), Some(PathBuf::from("/a/b/x")));
+    assert_eq!(tracker.readings()[1].verdict, Verdict::Skipped);
+    assert_eq!(tracker.winner(), Some("fixed"));
+}
+
+#[test]
+fn test_screen_detectors_read_the_header() {
+    let dir = tempfile::tempdir().unwrap();
+    let project = dir.path().canonicalize().unwrap().join("project");
+    std::fs::create_dir_all(project.join("src")).unwrap();
+
+    let mut vt = VirtualTerminal::new(60, 4);
+    vt.feed(format!("Welcome\r\ncwd: {}\r\n", project.display()).as_bytes());
+    vt.feed(format!("see {}", project.join("src").display()).as_bytes());
+    let pane = PaneState {
+        vterm: &vt,
+        pid: None,
+    };
+
+    // The heuristic picks the deepest path; a pattern picks the labelled one
+    let screen = ScreenDetector { rows: 8 };
+    assert_eq!(screen.detect(&pane), Some(project.join("src")));
+    let regex = RegexDetector {
+        pattern: Regex::new(r"cwd: (\S+)").unwrap(),
+        rows: 8,
+    };
+    assert_eq!(regex.detect(&pane), Some(project.clone()));
+    assert_eq!(
+        ScreenDetector { rows: 1 }.detect(&pane),
+        None,
+        "only the given rows are read"
+    );
+
+    // Once output scrolls, the top rows are the conversation, not the header
+    vt.feed(b"\r\nmore\r\nand more\r\n");
+    let pane = PaneState {
+        vterm: &vt,
+        pid: None,
+    };
+    assert_eq!(screen.detect(&pane), None);
+    assert_eq!(regex.detect(&pane), None);
+}
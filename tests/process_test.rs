@@ -0,0 +1,147 @@
+use cltree::process::{
+    parse_stat, subprocess_dirs, ProcessInfo, ProcessMonitor, ProcessSampler, Stat,
+};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// `sh` with a `sleep` below it, in a process group of their own so both
+/// are killed when the test ends
+#[cfg(target_os = "linux")]
+struct ShellWithChild(std::process::Child);
+
+#[cfg(target_os = "linux")]
+impl ShellWithChild {
+    fn spawn(dir: &Path) -> Self {
+        use std::os::unix::process::CommandExt;
+        let child = std::process::Command::new("sh")
+            .args(["-c", "sleep 5 & wait"])
+            .current_dir(dir)
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        Self(child)
+    }
+
+    fn id(&self) -> u32 {
+        self.0.id()
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for ShellWithChild {
+    fn drop(&mut self) {
+        // SAFETY: kill has no memory-safety preconditions
+        unsafe { libc::kill(-(self.0.id() as libc::pid_t), libc::SIGKILL) };
+        let _ = self.0.wait();
+    }
+}
+
+#[test]
+fn test_parse_stat_handles_odd_names() {
+    let rest = "0 -1 4194560 100 0 0 0 30 12 0 0 20 0 1 0 5000 1000000 250";
+    assert_eq!(
+        parse_stat(&format!("42 (cargo) S 7 42 42 {rest}")),
+        Some(Stat {
+            pid: 42,
+            name: "cargo".to_string(),
+            ppid: 7,
+            cpu_ticks: 42,
+            start_ticks: 5000,
+            rss_pages: 250,
+        })
+    );
+    // comm may contain spaces and parentheses
+    let stat = parse_stat(&format!("9 (a (b) c) R 1 9 9 {rest}")).unwrap();
+    assert_eq!((stat.pid, stat.name.as_str(), stat.ppid), (9, "a (b) c", 1));
+    assert_eq!(parse_stat("9 (short) R 1 9"), None);
+    assert_eq!(parse_stat("garbage"), None);
+}
+
+#[test]
+fn test_subprocess_dirs_skip_the_roots_and_duplicates() {
+    let process = |pid, depth, cwd: &str| ProcessInfo {
+        pid,
+        ppid: 1,
+        depth,
+        name: "p".to_string(),
+        cmdline: Vec::new(),
+        cwd: Some(PathBuf::from(cwd)),
+        cpu_percent: 0.0,
+        rss: 0,
+        elapsed: Duration::ZERO,
+    };
+    // Two roots, as when the tab is split
+    let tree = [
+        process(1, 0, "/repo"),
+        process(2, 1, "/repo/app"),
+        process(3, 2, "/repo/lib"),
+        process(4, 0, "/home"),
+        process(5, 1, "/repo/app"),
+    ];
+    assert_eq!(
+        subprocess_dirs(&tree),
+        [PathBuf::from("/repo/app"), PathBuf::from("/repo/lib")]
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_process_tree_finds_descendants_and_their_cwds() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path().canonicalize().unwrap();
+    let child = ShellWithChild::spawn(&dir);
+
+    // Give the shell a moment to start its own child
+    let mut sampler = ProcessSampler::default();
+    let mut tree = Vec::new();
+    for _ in 0..50 {
+        tree = sampler.sample(&[child.id()]);
+        if tree.len() >= 2 {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+
+    assert_eq!(tree[0].pid, child.id());
+    let sleep = tree.iter().find(|p| p.name == "sleep").expect("sleep");
+    assert_eq!(sleep.ppid, child.id());
+    assert_eq!(sleep.cmdline, ["sleep", "5"]);
+    assert_eq!(sleep.cwd.as_deref(), Some(dir.as_path()));
+    assert_eq!((tree[0].depth, sleep.depth), (0, 1));
+    assert_eq!(sleep.command(), "sleep 5");
+    assert!(sleep.rss > 0);
+    assert!(sleep.elapsed < Duration::from_secs(5));
+    assert_eq!(sampler.sample(&[u32::MAX]), []);
+}
+
+/// Ask `monitor` for `roots` until it publishes a tree `done` accepts
+#[cfg(target_os = "linux")]
+fn wait_for_tree(
+    monitor: &mut ProcessMonitor,
+    roots: &[u32],
+    done: impl Fn(&[ProcessInfo]) -> bool,
+) -> bool {
+    for _ in 0..100 {
+        monitor.refresh(roots.to_vec());
+        std::thread::sleep(Duration::from_millis(20));
+        if monitor.latest().is_some_and(|tree| done(&tree)) {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_process_monitor_samples_in_the_background() {
+    let dir = tempfile::tempdir().unwrap();
+    let child = ShellWithChild::spawn(dir.path());
+
+    // Far longer than the test, so every tree comes from a refresh
+    let mut monitor = ProcessMonitor::spawn(Duration::from_secs(60));
+    assert!(wait_for_tree(&mut monitor, &[child.id()], |tree| {
+        tree.iter().any(|p| p.name == "sleep")
+    }));
+    // Trees of roots no longer watched are not handed out
+    assert!(wait_for_tree(&mut monitor, &[], |tree| tree.is_empty()));
+}
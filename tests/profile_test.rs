@@ -0,0 +1,125 @@
+use cltree::profile::Profiles;
+
+fn args(list: &[&str]) -> Vec<String> {
+    list.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn test_builtin_profiles() {
+    let profiles = Profiles::builtin();
+    let claude = profiles.get("claude").unwrap();
+    assert_eq!(claude.command, "claude");
+    assert_eq!(claude.title, "Claude Code");
+    assert_eq!(claude.env_remove, args(&["CLAUDECODE"]));
+    assert_eq!(claude.cwd.detectors, args(&["osc7", "process", "screen"]));
+    assert!(profiles.get("aider").is_ok());
+    assert!(profiles.get("codex").is_ok());
+    assert!(profiles.get("shell").unwrap().continue_args.is_empty());
+
+    let err = profiles.get("nope").unwrap_err().to_string();
+    assert!(err.contains("unknown profile 'nope'"), "{err}");
+    assert!(err.contains("claude, aider, codex, shell"), "{err}");
+}
+
+#[test]
+fn test_profiles_json_merges_over_builtins() {
+    let mut profiles = Profiles::builtin();
+    profiles
+        .merge_json(
+            r#"{
+                "claude": { "args": ["--model", "opus"], "env": { "FOO": "1" } },
+                "gemini": {
+                    "title": "Gemini",
+                    "env_remove": ["DEBUG"],
+                    "term": "xterm",
+                    "cwd": { "detectors": ["regex", "osc7"], "pattern": "dir: (\\S+)" }
+                }
+            }"#,
+        )
+        .unwrap();
+
+    let claude = profiles.get("claude").unwrap();
+    assert_eq!(claude.args, args(&["--model", "opus"]));
+    assert_eq!(claude.env, vec![("FOO".to_string(), "1".to_string())]);
+    // Fields the file leaves out keep their built-in values
+    assert_eq!(claude.title, "Claude Code");
+    assert_eq!(claude.env_remove, args(&["CLAUDECODE"]));
+
+    let gemini = profiles.get("gemini").unwrap();
+    assert_eq!(gemini.command, "gemini");
+    assert_eq!(gemini.title, "Gemini");
+    assert_eq!(gemini.term, "xterm");
+    assert_eq!(gemini.cwd.detectors, args(&["regex", "osc7"]));
+    assert_eq!(gemini.cwd.pattern.as_deref(), Some("dir: (\\S+)"));
+
+    let err = Profiles::builtin()
+        .merge_json(r#"{ "claude": { "comand": "x" } }"#)
+        .unwrap_err();
+    assert!(err.to_string().contains("unknown field 'comand'"), "{err}");
+
+    // Detectors are checked when the file is read, not when a tab opens
+    let err = Profiles::builtin()
+        .merge_json(r#"{ "claude": { "cwd": { "detectors": ["regex"] } } }"#)
+        .unwrap_err();
+    assert!(format!("{err:#}").contains("needs a pattern"), "{err:#}");
+}
+
+#[test]
+fn test_profile_without_session_flags() {
+    let claude = Profiles::builtin().get("claude").unwrap();
+    assert_eq!(
+        claude.without_session_flags(&args(&[
+            "--resume",
+            "abc",
+            "--model",
+            "opus",
+            "-c",
+            "prompt",
+            "--resume=def"
+        ])),
+        args(&["--model", "opus", "prompt"])
+    );
+    assert_eq!(
+        claude.without_session_flags(&args(&["-r", "--verbose"])),
+        args(&["--verbose"])
+    );
+
+    let codex = Profiles::builtin().get("codex").unwrap();
+    assert_eq!(
+        codex.without_session_flags(&args(&["resume", "--last", "--full-auto"])),
+        args(&["--full-auto"])
+    );
+}
+
+#[test]
+fn test_claude_command_override_leaves_other_profiles() {
+    let mut profiles = Profiles::builtin();
+    profiles.override_claude_command("/opt/claude/bin/claude");
+    assert_eq!(
+        profiles.get("claude").unwrap().command,
+        "/opt/claude/bin/claude"
+    );
+    assert_eq!(profiles.get("aider").unwrap().command, "aider");
+    assert_eq!(profiles.get("codex").unwrap().command, "codex");
+}
+
+#[test]
+fn test_legacy_cwd_switches_map_onto_detectors() {
+    let mut profiles = Profiles::builtin();
+    profiles
+        .merge_json(
+            r#"{
+                "claude": { "cwd": { "osc7": false } },
+                "shell": { "cwd": { "detectors": ["osc7"], "process": true } }
+            }"#,
+        )
+        .unwrap();
+    assert_eq!(
+        profiles.get("claude").unwrap().cwd.detectors,
+        args(&["process", "screen"])
+    );
+    assert_eq!(
+        profiles.get("shell").unwrap().cwd.detectors,
+        args(&["osc7", "process"])
+    );
+}
@@ -0,0 +1,74 @@
+use cltree::palette::Palette;
+use cltree::screenshot::{render, ScreenshotFormat};
+use cltree::unicode::AmbiguousWidth;
+use ratatui::buffer::Buffer;
+use ratatui::prelude::*;
+use std::path::Path;
+
+#[test]
+fn test_screenshot_svg() {
+    let mut buf = Buffer::empty(Rect::new(0, 0, 10, 2));
+    buf.set_string(0, 0, "ab<", Style::default().fg(Color::Red));
+    buf.set_string(4, 0, "  ", Style::default().bg(Color::Blue));
+    buf.set_string(0, 1, "日x", Style::default().add_modifier(Modifier::BOLD));
+    let svg = render(
+        &buf,
+        &Palette::default(),
+        AmbiguousWidth::Narrow,
+        ScreenshotFormat::Svg,
+    );
+
+    assert!(svg.starts_with("<svg "));
+    assert!(svg.contains("width=\"84.0\" height=\"36.0\""));
+    // Red text with one x per cell, markup escaped
+    assert!(svg.contains("<text x=\"0.0 8.4 16.8\" y=\"14.0\" fill=\"#cd0000\">ab&lt;</text>"));
+    // Blue background run
+    assert!(
+        svg.contains("<rect x=\"33.6\" y=\"0.0\" width=\"16.8\" height=\"18\" fill=\"#0000ee\"/>")
+    );
+    // The wide glyph covers two cells, so "x" sits in the third
+    assert!(svg.contains(
+        "<text x=\"0.0 16.8\" y=\"32.0\" fill=\"#e5e5e5\" font-weight=\"bold\">日x</text>"
+    ));
+}
+
+#[test]
+fn test_screenshot_wide_ambiguous_glyphs() {
+    let mut buf = Buffer::empty(Rect::new(0, 0, 4, 1));
+    buf.set_string(0, 0, "○", Style::default());
+    buf.set_string(2, 0, "x", Style::default());
+    // Drawn wide, the circle covers the cell after it
+    let svg = render(
+        &buf,
+        &Palette::default(),
+        AmbiguousWidth::Wide,
+        ScreenshotFormat::Svg,
+    );
+    assert!(
+        svg.contains("<text x=\"0.0 16.8\" y=\"14.0\" fill=\"#e5e5e5\">○x</text>"),
+        "{svg}"
+    );
+}
+
+#[test]
+fn test_screenshot_html_and_format() {
+    assert_eq!(
+        ScreenshotFormat::from_path(Path::new("shot.HTML")),
+        ScreenshotFormat::Html
+    );
+    assert_eq!(
+        ScreenshotFormat::from_path(Path::new("shot.svg")),
+        ScreenshotFormat::Svg
+    );
+
+    let mut buf = Buffer::empty(Rect::new(0, 0, 6, 1));
+    buf.set_string(0, 0, "a&b", Style::default().fg(Color::Green));
+    let html = render(
+        &buf,
+        &Palette::default(),
+        AmbiguousWidth::Narrow,
+        ScreenshotFormat::Html,
+    );
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<span style=\"color:#00cd00\">a&amp;b</span>   </pre>"));
+}
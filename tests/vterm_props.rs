@@ -0,0 +1,196 @@
+//! Property tests: random byte streams, biased towards escape sequences the
+//! emulator handles, must never panic and must leave the terminal in a
+//! consistent state.
+
+use cltree::vterm::VirtualTerminal;
+use proptest::prelude::*;
+
+/// One piece of input: text, a control, an escape sequence or a resize
+#[derive(Clone, Debug)]
+enum Op {
+    Bytes(Vec<u8>),
+    Resize(usize, usize),
+}
+
+fn text() -> impl Strategy<Value = Vec<u8>> {
+    prop_oneof![
+        "[ -~]{1,20}".prop_map(String::into_bytes),
+        // Wide, combining, ZWJ, variation selector, flags, ambiguous width
+        prop::sample::select(vec![
+            "日本語",
+            "e\u{301}",
+            "👨\u{200D}👩\u{200D}👧",
+            "\u{2764}\u{FE0F}",
+            "🇯🇵",
+            "±§",
+            "\u{200B}",
+        ])
+        .prop_map(|s| s.as_bytes().to_vec()),
+    ]
+}
+
+fn control() -> impl Strategy<Value = Vec<u8>> {
+    prop::sample::select(vec![
+        b"\r".to_vec(),
+        b"\n".to_vec(),
+        b"\r\n".to_vec(),
+        b"\x08".to_vec(),
+        b"\t".to_vec(),
+        b"\x07".to_vec(),
+        b"\x1b7".to_vec(),
+        b"\x1b8".to_vec(),
+        b"\x1bM".to_vec(),
+        b"\x1bD".to_vec(),
+        b"\x1bE".to_vec(),
+        b"\x1bc".to_vec(),
+    ])
+}
+
+fn csi() -> impl Strategy<Value = Vec<u8>> {
+    let finals = prop::sample::select(b"@ABCDEFGHIJKLMPSTXZ`abdefghlmnrsu".to_vec());
+    let param = prop_oneof![Just(0u32), 0u32..5, 0u32..300, Just(u16::MAX as u32 + 7)];
+    (any::<bool>(), prop::collection::vec(param, 0..4), finals).prop_map(
+        |(private, params, fin)| {
+            let params: Vec<String> = params.iter().map(u32::to_string).collect();
+            format!(
+                "\x1b[{}{}{}",
+                if private { "?" } else { "" },
+                params.join(";"),
+                fin as char
+            )
+            .into_bytes()
+        },
+    )
+}
+
+fn private_mode() -> impl Strategy<Value = Vec<u8>> {
+    (
+        prop::sample::select(vec![
+            1u32, 6, 7, 12, 25, 47, 1000, 1004, 1047, 1048, 1049, 2004, 2026,
+        ]),
+        any::<bool>(),
+    )
+        .prop_map(|(mode, set)| format!("\x1b[?{mode}{}", if set { 'h' } else { 'l' }).into_bytes())
+}
+
+fn osc() -> impl Strategy<Value = Vec<u8>> {
+    prop::sample::select(vec![
+        "\x1b]0;title\x07",
+        "\x1b]7;file://host/tmp\x07",
+        "\x1b]133;A\x07",
+        "\x1b]133;C\x07",
+        "\x1b]133;D;1\x07",
+        "\x1b]4;1;rgb:ff/00/00\x1b\\",
+        "\x1b]10;?\x07",
+        "\x1b]52;c;aGVsbG8=\x07",
+        "\x1b]9;done\x07",
+    ])
+    .prop_map(|s| s.as_bytes().to_vec())
+}
+
+/// Sixel DCS and kitty APC images, whole or cut off by other input
+fn image() -> impl Strategy<Value = Vec<u8>> {
+    prop::sample::select(vec![
+        "\x1bPq\"1;1;4;12#1;2;100;0;0#1!4~-!4~\x1b\\",
+        "\x1bP0;1q#2!30@$-~~\x1b\\",
+        "\x1bPq!9999~",
+        "\x1b_Ga=T,f=24,s=1,v=1,i=5;AAAA\x1b\\",
+        "\x1b_Ga=t,i=6,f=24,s=2,v=1,m=1;/wAA\x1b\\",
+        "\x1b_Gm=0;AAD/\x1b\\",
+        "\x1b_Ga=p,i=6,c=3,r=2\x1b\\",
+        "\x1b_Ga=d,d=A\x1b\\",
+        "\x1b_Ga=q,i=1;",
+    ])
+    .prop_map(|s| s.as_bytes().to_vec())
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        4 => text().prop_map(Op::Bytes),
+        2 => control().prop_map(Op::Bytes),
+        4 => csi().prop_map(Op::Bytes),
+        1 => private_mode().prop_map(Op::Bytes),
+        1 => osc().prop_map(Op::Bytes),
+        1 => image().prop_map(Op::Bytes),
+        1 => prop::collection::vec(any::<u8>(), 1..16).prop_map(Op::Bytes),
+        1 => (1usize..40, 1usize..15).prop_map(|(c, r)| Op::Resize(c, r)),
+    ]
+}
+
+fn assert_consistent(vt: &VirtualTerminal) {
+    let (cols, rows) = (vt.cols(), vt.rows());
+    assert_eq!(vt.grid().len(), rows, "grid height");
+    for (i, row) in vt.grid().iter().enumerate() {
+        assert_eq!(row.len(), cols, "width of row {i}");
+    }
+    let cursor = vt.cursor();
+    // x == cols is the deferred-wrap position after writing the last column
+    assert!(
+        cursor.x <= cols,
+        "cursor x {} beyond {cols} columns",
+        cursor.x
+    );
+    assert!(cursor.y < rows, "cursor y {} beyond {rows} rows", cursor.y);
+    assert!(vt.scroll_offset() <= vt.scrollback().len());
+    assert_eq!(vt.total_lines(), vt.scrollback().len() + rows);
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    #[test]
+    fn random_streams_keep_the_terminal_consistent(
+        cols in 1usize..40,
+        rows in 1usize..15,
+        ops in prop::collection::vec(op(), 1..80),
+    ) {
+        let mut vt = VirtualTerminal::new(cols, rows);
+        for op in ops {
+            match op {
+                Op::Bytes(bytes) => vt.feed(&bytes),
+                Op::Resize(c, r) => vt.resize(c, r),
+            }
+            assert_consistent(&vt);
+        }
+        // The read-side API must cope with whatever state we ended in
+        let _ = vt.prompt_lines();
+        let _ = vt.output_blocks();
+        let last = vt.total_lines() - 1;
+        let _ = vt.logical_lines((0, 0), (last, cols));
+        let _ = vt.take_responses();
+    }
+
+    #[test]
+    fn split_points_do_not_change_the_result(
+        bytes in prop::collection::vec(op(), 1..40).prop_map(|ops| {
+            ops.into_iter()
+                .filter_map(|op| match op {
+                    Op::Bytes(b) => Some(b),
+                    Op::Resize(..) => None,
+                })
+                .flatten()
+                .collect::<Vec<u8>>()
+        }),
+        split in any::<prop::sample::Index>(),
+    ) {
+        let mut whole = VirtualTerminal::new(20, 6);
+        whole.feed(&bytes);
+
+        let at = split.index(bytes.len() + 1);
+        let mut parts = VirtualTerminal::new(20, 6);
+        parts.feed(&bytes[..at]);
+        parts.feed(&bytes[at..]);
+
+        let text = |vt: &VirtualTerminal| -> Vec<String> {
+            (0..vt.total_lines())
+                .map(|i| vt.lines_text(i..i + 1))
+                .collect()
+        };
+        prop_assert_eq!(text(&parts), text(&whole));
+        prop_assert_eq!(parts.images().len(), whole.images().len());
+        prop_assert_eq!(
+            (parts.cursor().x, parts.cursor().y),
+            (whole.cursor().x, whole.cursor().y)
+        );
+    }
+}
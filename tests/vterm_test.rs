@@ -1,3 +1,7 @@
+use cltree::export::CopyFormat;
+use cltree::graphics::{self, Image, ImageMode, ImageSource};
+use cltree::snapshot::{assert_golden, assert_screen, Scope};
+use cltree::unicode::AmbiguousWidth;
 use cltree::vterm::VirtualTerminal;
 use ratatui::prelude::*;
 
@@ -319,7 +323,7 @@ fn test_scroll_offset() {
         vt.feed(format!("{}\r\n", i).as_bytes());
     }
 
-    assert!(vt.scrollback().len() > 0);
+    assert!(!vt.scrollback().is_empty());
 
     vt.set_scroll_offset(3);
     assert_eq!(vt.scroll_offset(), 3);
@@ -387,3 +391,1142 @@ fn test_variation_selector() {
     assert!(vt.grid()[0][0].ch.contains('⚛'));
     assert!(vt.grid()[0][0].ch.contains('\u{FE0F}'));
 }
+
+#[test]
+fn test_zwj_emoji_sequence_is_one_cell() {
+    let mut vt = VirtualTerminal::new(20, 5);
+    // Family: man + ZWJ + woman + ZWJ + girl
+    vt.feed("👨\u{200D}👩\u{200D}👧X".as_bytes());
+    assert_eq!(vt.grid()[0][0].ch, "👨\u{200D}👩\u{200D}👧");
+    assert_eq!(vt.grid()[0][1].ch, ""); // continuation cell
+    assert_eq!(vt.grid()[0][2].ch, "X");
+    assert_eq!(vt.cursor().x, 3);
+}
+
+#[test]
+fn test_flag_pair_is_wide() {
+    let mut vt = VirtualTerminal::new(20, 5);
+    vt.feed("🇰🇷🇯🇵".as_bytes());
+    assert_eq!(vt.grid()[0][0].ch, "🇰🇷");
+    assert_eq!(vt.grid()[0][1].ch, "");
+    assert_eq!(vt.grid()[0][2].ch, "🇯🇵");
+    assert_eq!(vt.cursor().x, 4);
+}
+
+#[test]
+fn test_emoji_presentation_selector_widens_cell() {
+    let mut vt = VirtualTerminal::new(20, 5);
+    // U+2764 is narrow on its own; VS16 requests a 2-cell emoji glyph
+    vt.feed("\u{2764}\u{FE0F}A".as_bytes());
+    assert_eq!(vt.grid()[0][0].ch, "\u{2764}\u{FE0F}");
+    assert_eq!(vt.grid()[0][1].ch, "");
+    assert_eq!(vt.grid()[0][2].ch, "A");
+}
+
+#[test]
+fn test_ambiguous_width_policy() {
+    let mut narrow = VirtualTerminal::new(20, 5);
+    narrow.feed("①A".as_bytes());
+    assert_eq!(narrow.grid()[0][1].ch, "A");
+
+    let mut wide = VirtualTerminal::new(20, 5);
+    wide.set_ambiguous_width(AmbiguousWidth::Wide);
+    wide.feed("①A".as_bytes());
+    assert_eq!(wide.grid()[0][0].ch, "①");
+    assert_eq!(wide.grid()[0][1].ch, "");
+    assert_eq!(wide.grid()[0][2].ch, "A");
+}
+
+#[test]
+fn test_cursor_move_breaks_grapheme_cluster() {
+    let mut vt = VirtualTerminal::new(20, 5);
+    // A regional indicator printed after a cursor move must not join the earlier one
+    vt.feed("🇰\x1b[1;10H🇷".as_bytes());
+    assert_eq!(vt.grid()[0][0].ch, "🇰");
+    assert_eq!(vt.grid()[0][9].ch, "🇷");
+}
+
+#[test]
+fn test_decscusr_cursor_shape() {
+    use cltree::vterm::{CursorShape, CursorStyle};
+
+    let mut vt = VirtualTerminal::new(20, 5);
+    assert_eq!(vt.cursor_style(), CursorStyle::default());
+
+    vt.feed(b"\x1b[6 q");
+    assert_eq!(vt.cursor_style().shape, CursorShape::Bar);
+    assert!(!vt.cursor_style().blinking);
+
+    vt.feed(b"\x1b[3 q");
+    assert_eq!(vt.cursor_style().shape, CursorShape::Underline);
+    assert!(vt.cursor_style().blinking);
+
+    // DECSET/DECRST 12 toggles blinking without changing the shape
+    vt.feed(b"\x1b[?12l");
+    assert_eq!(vt.cursor_style().shape, CursorShape::Underline);
+    assert!(!vt.cursor_style().blinking);
+
+    // Ps = 0 restores the user's default
+    vt.feed(b"\x1b[0 q");
+    assert_eq!(vt.cursor_style().shape, CursorShape::Default);
+
+    // Nothing printed to the grid
+    assert_eq!(vt.grid()[0][0].ch, " ");
+}
+
+#[test]
+fn test_osc_10_11_color_queries() {
+    let mut vt = VirtualTerminal::new(20, 5);
+    vt.feed(b"\x1b]11;?\x1b\\");
+    vt.feed(b"\x1b]10;?\x07");
+    let responses = vt.take_responses();
+    assert_eq!(responses.len(), 2);
+    assert_eq!(responses[0], b"\x1b]11;rgb:0000/0000/0000\x1b\\".to_vec());
+    // Reply uses the same terminator as the query
+    assert_eq!(responses[1], b"\x1b]10;rgb:e5e5/e5e5/e5e5\x07".to_vec());
+
+    // Child-set default colours are reported back and applied to unstyled cells
+    vt.feed(b"\x1b]11;#102030\x1b\\\x1b]11;?\x1b\\");
+    assert_eq!(
+        vt.take_responses()[0],
+        b"\x1b]11;rgb:1010/2020/3030\x1b\\".to_vec()
+    );
+    let resolved = vt.resolve_style(Style::default());
+    assert_eq!(resolved.bg, Some(Color::Rgb(0x10, 0x20, 0x30)));
+
+    vt.feed(b"\x1b]111\x1b\\");
+    assert_eq!(vt.resolve_style(Style::default()).bg, None);
+}
+
+#[test]
+fn test_osc_4_palette_set_query_reset() {
+    use cltree::palette::Palette;
+
+    let mut vt = VirtualTerminal::new(20, 5);
+    let mut palette = Palette::default();
+    palette.indexed[1] = (200, 10, 10);
+    vt.set_palette(palette);
+
+    vt.feed(b"\x1b]4;1;?\x1b\\");
+    assert_eq!(
+        vt.take_responses()[0],
+        b"\x1b]4;1;rgb:c8c8/0a0a/0a0a\x1b\\".to_vec()
+    );
+
+    // Override entry 1 and 200; both named and indexed colours pick it up
+    vt.feed(b"\x1b]4;1;rgb:ff/80/00;200;rgb:0/0/f\x1b\\");
+    let red = vt.resolve_style(Style::default().fg(Color::Red));
+    assert_eq!(red.fg, Some(Color::Rgb(255, 128, 0)));
+    let idx = vt.resolve_style(Style::default().bg(Color::Indexed(200)));
+    assert_eq!(idx.bg, Some(Color::Rgb(0, 0, 255)));
+    // Untouched entries are left to the host terminal
+    let green = vt.resolve_style(Style::default().fg(Color::Green));
+    assert_eq!(green.fg, Some(Color::Green));
+
+    vt.feed(b"\x1b]104;1\x1b\\");
+    assert_eq!(
+        vt.resolve_style(Style::default().fg(Color::Red)).fg,
+        Some(Color::Red)
+    );
+    assert_eq!(
+        vt.resolve_style(Style::default().bg(Color::Indexed(200)))
+            .bg,
+        Some(Color::Rgb(0, 0, 255))
+    );
+    vt.feed(b"\x1b]104\x1b\\");
+    assert_eq!(
+        vt.resolve_style(Style::default().bg(Color::Indexed(200)))
+            .bg,
+        Some(Color::Indexed(200))
+    );
+}
+
+#[test]
+fn test_host_palette_replies_keep_typed_keys() {
+    use cltree::palette::{parse_host_replies, Palette};
+
+    let mut palette = Palette::default();
+    let reply = b"l\x1b]10;rgb:ffff/ffff/ffff\x1b\\s\x1b]11;rgb:1010/2020/3030\x07\
+                  \x1b]4;1;rgb:c8c8/0a0a/0a0a\x1b\\\x1b[A\x1b[?62;4c\r";
+    let rest = parse_host_replies(reply, &mut palette);
+
+    assert_eq!(palette.foreground, (255, 255, 255));
+    assert_eq!(palette.cursor, (255, 255, 255));
+    assert_eq!(palette.background, (0x10, 0x20, 0x30));
+    assert_eq!(palette.indexed[1], (200, 10, 10));
+    assert_eq!(palette.indexed[2], Palette::default().indexed[2]);
+    // Keys typed meanwhile, including an arrow key, come back in order
+    assert_eq!(rest, b"ls\x1b[A\r".to_vec());
+
+    // A reply cut off by the timeout is passed on rather than lost
+    let mut palette = Palette::default();
+    assert_eq!(
+        parse_host_replies(b"\x1b]10;rgb:ff", &mut palette),
+        b"\x1b]10;rgb:ff".to_vec()
+    );
+}
+
+#[test]
+fn test_bell_flag() {
+    let mut vt = VirtualTerminal::new(20, 5);
+    assert!(!vt.take_bell());
+    vt.feed(b"done\x07");
+    assert!(vt.take_bell());
+    // Cleared after reading
+    assert!(!vt.take_bell());
+    // BEL terminating an OSC is not a bell
+    vt.feed(b"\x1b]0;title\x07");
+    assert!(!vt.take_bell());
+}
+
+#[test]
+fn test_osc_9_and_777_notifications() {
+    use cltree::vterm::Notification;
+
+    let mut vt = VirtualTerminal::new(20, 5);
+    vt.feed(b"\x1b]9;Task finished; 3 files changed\x07");
+    vt.feed(b"\x1b]777;notify;Claude;Waiting for input\x1b\\");
+    // ConEmu progress report, not a notification
+    vt.feed(b"\x1b]9;4;1;50\x07");
+    assert_eq!(
+        vt.take_notifications(),
+        vec![
+            Notification {
+                title: None,
+                body: "Task finished; 3 files changed".to_string(),
+            },
+            Notification {
+                title: Some("Claude".to_string()),
+                body: "Waiting for input".to_string(),
+            },
+        ]
+    );
+    assert!(vt.take_notifications().is_empty());
+    assert_eq!(vt.grid()[0][0].ch, " ");
+}
+
+/// Feed one shell-integrated command: prompt, command line, output, exit code
+fn feed_command(vt: &mut VirtualTerminal, cmd: &str, output: &[&str], status: i32) {
+    vt.feed(format!("\x1b]133;A\x07$ \x1b]133;B\x07{cmd}\r\n\x1b]133;C\x07").as_bytes());
+    for line in output {
+        vt.feed(format!("{line}\r\n").as_bytes());
+    }
+    vt.feed(format!("\x1b]133;D;{status}\x07").as_bytes());
+}
+
+#[test]
+fn test_osc_133_marks() {
+    use cltree::vterm::SemanticMark;
+
+    let mut vt = VirtualTerminal::new(20, 10);
+    feed_command(&mut vt, "ls", &["a.txt", "b.txt"], 0);
+    feed_command(&mut vt, "false", &[], 1);
+    vt.feed(b"\x1b]133;A\x07$ ");
+
+    // `false` prints nothing, so the next prompt shares its output line
+    assert_eq!(vt.prompt_lines(), vec![0, 3, 4]);
+    assert_eq!(
+        vt.grid()[0].marks,
+        vec![SemanticMark::PromptStart, SemanticMark::CommandStart]
+    );
+    assert_eq!(vt.grid()[1].marks, vec![SemanticMark::OutputStart]);
+    assert_eq!(vt.grid()[3].marks[0], SemanticMark::CommandEnd(Some(0)));
+    assert_eq!(
+        vt.grid()[4].marks,
+        vec![
+            SemanticMark::OutputStart,
+            SemanticMark::CommandEnd(Some(1)),
+            SemanticMark::PromptStart
+        ]
+    );
+    // Marks are invisible
+    assert_eq!(vt.row_text(0), "$ ls");
+}
+
+#[test]
+fn test_output_blocks() {
+    let mut vt = VirtualTerminal::new(20, 10);
+    feed_command(&mut vt, "ls", &["a.txt", "b.txt"], 0);
+    // No output: the command ends on the line it started
+    feed_command(&mut vt, "true", &[], 0);
+    feed_command(&mut vt, "cat x", &["hello"], 0);
+
+    let blocks = vt.output_blocks();
+    assert_eq!(blocks, vec![1..3, 5..6]);
+    assert_eq!(vt.lines_text(blocks[0].clone()), "a.txt\nb.txt");
+
+    // A still-running command's block ends at its last printed line
+    vt.feed(b"\x1b]133;A\x07$ \x1b]133;B\x07make\r\n\x1b]133;C\x07building\r\n");
+    assert_eq!(vt.output_blocks().last(), Some(&(7..8)));
+}
+
+#[test]
+fn test_prompt_lines_span_scrollback() {
+    let mut vt = VirtualTerminal::new(20, 4);
+    for i in 0..5 {
+        feed_command(&mut vt, &format!("echo {i}"), &[&i.to_string()], 0);
+    }
+    vt.feed(b"\x1b]133;A\x07$ ");
+    // Each command takes two lines; older prompts are in scrollback
+    assert_eq!(vt.prompt_lines(), vec![0, 2, 4, 6, 8, 10]);
+    assert_eq!(vt.total_lines(), 11);
+    assert_eq!(vt.view_top(), 7);
+
+    vt.scroll_to_line(2);
+    assert_eq!(vt.view_top(), 2);
+    assert_eq!(vt.line(2).map(|_| ()), Some(()));
+    // Cannot scroll past the live view
+    vt.scroll_to_line(10);
+    assert_eq!(vt.scroll_offset(), 0);
+}
+
+#[test]
+fn test_osc_133_ignored_in_alternate_screen() {
+    let mut vt = VirtualTerminal::new(20, 5);
+    vt.feed(b"\x1b[?1049h\x1b]133;A\x07");
+    vt.feed(b"\x1b[?1049l");
+    assert!(vt.prompt_lines().is_empty());
+}
+
+#[test]
+fn test_soft_wrap_flag() {
+    let mut vt = VirtualTerminal::new(5, 4);
+    vt.feed(b"abcdefg\r\nxy");
+    assert!(vt.grid()[0].wrapped);
+    assert!(!vt.grid()[1].wrapped);
+    assert!(!vt.grid()[2].wrapped);
+    // Erasing to the end of the line drops the continuation
+    vt.feed(b"\x1b[1;3H\x1b[K");
+    assert!(!vt.grid()[0].wrapped);
+}
+
+#[test]
+fn test_search_across_scrollback_and_wraps() {
+    use cltree::search::{build_regex, find_matches, SearchMatch, SearchMode};
+
+    let mut vt = VirtualTerminal::new(10, 3);
+    vt.feed(b"error one\r\nok\r\nok\r\nsee https://x.io/errors\r\nError two");
+    let regex = build_regex("error", SearchMode::Plain).unwrap();
+    let matches = find_matches(&vt, &regex);
+    // Line 0 has scrolled into the scrollback; the URL wraps over lines 3-5,
+    // so its "err|ors" match spans a soft wrap
+    assert_eq!(
+        matches,
+        vec![
+            SearchMatch {
+                start: (0, 0),
+                end: (0, 4)
+            },
+            SearchMatch {
+                start: (4, 7),
+                end: (5, 1)
+            },
+            SearchMatch {
+                start: (6, 0),
+                end: (6, 4)
+            },
+        ]
+    );
+    let regex = build_regex("https://x", SearchMode::Plain).unwrap();
+    let m = find_matches(&vt, &regex);
+    assert_eq!(m.len(), 1);
+    assert_eq!((m[0].start, m[0].end), ((3, 4), (4, 2)));
+    assert!(m[0].contains(3, 9) && m[0].contains(4, 0) && !m[0].contains(4, 3));
+
+    // Uppercase in the query makes it case-sensitive
+    let regex = build_regex("Error", SearchMode::Plain).unwrap();
+    assert_eq!(find_matches(&vt, &regex).len(), 1);
+
+    let regex = build_regex(r"\bo[nk]", SearchMode::Regex).unwrap();
+    assert_eq!(find_matches(&vt, &regex).len(), 3);
+    assert!(build_regex("(", SearchMode::Regex).is_err());
+    assert!(build_regex("(", SearchMode::Plain).is_ok());
+}
+
+#[test]
+fn test_search_stepping() {
+    use cltree::search::Search;
+
+    let mut vt = VirtualTerminal::new(10, 3);
+    for i in 0..6 {
+        vt.feed(format!("hit {i}\r\n").as_bytes());
+    }
+    let mut search = Search::new();
+    search.query = "hit".to_string();
+    search.update(&vt);
+    assert_eq!(search.matches.len(), 6);
+    // Starts at the newest match
+    assert_eq!(search.current, Some(5));
+    search.step(true);
+    assert_eq!(search.current, Some(4));
+    search.step(false);
+    search.step(false);
+    // Wraps around to the oldest
+    assert_eq!(search.current, Some(0));
+
+    search.query = "(".to_string();
+    search.mode = cltree::search::SearchMode::Regex;
+    search.update(&vt);
+    assert!(search.invalid);
+    assert!(search.current_match().is_none());
+}
+
+#[test]
+fn test_search_matches_survive_scrollback_eviction() {
+    use cltree::search::Search;
+
+    let mut vt = VirtualTerminal::new(20, 3);
+    for i in 0..1060 {
+        vt.feed(format!("line{i}\r\n").as_bytes());
+    }
+    let mut search = Search::new();
+    search.query = "line1050".to_string();
+    search.update(&vt);
+    let m = *search.current_match().unwrap();
+    let text_at = |vt: &VirtualTerminal, line: usize| {
+        vt.lines_text(line - vt.evicted_lines()..line - vt.evicted_lines() + 1)
+    };
+    assert_eq!(text_at(&vt, m.start.0), "line1050");
+
+    // Ten more lines push ten old ones out of the full scrollback; the match
+    // still points at the same text
+    for i in 1060..1070 {
+        vt.feed(format!("line{i}\r\n").as_bytes());
+    }
+    assert_eq!(text_at(&vt, m.start.0), "line1050");
+}
+
+#[test]
+fn test_search_skips_wide_char_wrap_padding() {
+    use cltree::search::{build_regex, find_matches, SearchMatch, SearchMode};
+
+    // 界 doesn't fit in the last column, so it wraps and leaves padding
+    let mut vt = VirtualTerminal::new(5, 3);
+    vt.feed("abcd界x".as_bytes());
+    assert!(vt.grid()[0].padded);
+    let regex = build_regex("abcd界", SearchMode::Plain).unwrap();
+    assert_eq!(
+        find_matches(&vt, &regex),
+        vec![SearchMatch {
+            start: (0, 0),
+            end: (1, 0)
+        }]
+    );
+
+    // Printing over the padding makes it text again
+    vt.feed(b"\x1b[1;5HZ");
+    assert!(!vt.grid()[0].padded);
+    let regex = build_regex("abcdZ", SearchMode::Plain).unwrap();
+    assert_eq!(find_matches(&vt, &regex).len(), 1);
+}
+
+#[test]
+fn test_scrolled_view_stays_put_during_output() {
+    let mut vt = VirtualTerminal::new(10, 3);
+    for i in 0..6 {
+        vt.feed(format!("line {i}\r\n").as_bytes());
+    }
+    vt.set_scroll_offset(2);
+    let top = vt.view_top();
+    let text = vt.line(top).map(|r| r[5].ch.clone());
+    vt.feed(b"more\r\nmore\r\n");
+    assert_eq!(vt.view_top(), top);
+    assert_eq!(vt.line(top).map(|r| r[5].ch.clone()), text);
+    assert_eq!(vt.scroll_offset(), 4);
+}
+
+#[test]
+fn test_evicted_lines_counter() {
+    let mut vt = VirtualTerminal::new(10, 2);
+    for i in 0..1005 {
+        vt.feed(format!("{i}\r\n").as_bytes());
+    }
+    assert_eq!(vt.scrollback().len(), 1000);
+    assert_eq!(vt.evicted_lines(), 4);
+    // Line 0 of the buffer is the fifth line ever written
+    assert_eq!(vt.line(0).map(|r| r[0].ch.clone()), Some("4".to_string()));
+}
+
+#[test]
+fn test_copy_mode_motions() {
+    use cltree::copy_mode::CopyMode;
+
+    let mut vt = VirtualTerminal::new(20, 4);
+    vt.feed(b"foo bar.baz  qux\r\n  indented\r\n$ ");
+    let mut copy = CopyMode::new(&vt);
+    // Starts at the child's cursor
+    assert_eq!(copy.cursor, (2, 2));
+
+    copy.move_by(&vt, 0, -2);
+    copy.line_start();
+    copy.word_forward(&vt);
+    assert_eq!(copy.cursor, (0, 4));
+    copy.word_forward(&vt);
+    assert_eq!(copy.cursor, (0, 7)); // punctuation is its own word
+    copy.word_end(&vt);
+    assert_eq!(copy.cursor, (0, 10));
+    copy.word_forward(&vt);
+    assert_eq!(copy.cursor, (0, 13));
+    // Past the end of the line, onto the next line's first word
+    copy.word_forward(&vt);
+    assert_eq!(copy.cursor, (1, 2));
+    copy.word_backward(&vt);
+    assert_eq!(copy.cursor, (0, 13));
+
+    copy.move_by(&vt, 0, 1);
+    copy.line_end(&vt);
+    assert_eq!(copy.cursor, (1, 9));
+    copy.first_non_blank(&vt);
+    assert_eq!(copy.cursor, (1, 2));
+
+    // Movement is clamped to the buffer
+    copy.move_by(&vt, -50, -50);
+    assert_eq!(copy.cursor, (0, 0));
+    copy.buffer_bottom(&vt);
+    assert_eq!(copy.cursor, (3, 0));
+}
+
+#[test]
+fn test_copy_mode_selections() {
+    use cltree::copy_mode::{CopyMode, SelectionKind};
+
+    let mut vt = VirtualTerminal::new(20, 4);
+    vt.feed(b"alpha beta\r\ngamma delta\r\nepsilon");
+    let mut copy = CopyMode::new(&vt);
+    copy.cursor = (0, 6);
+    assert_eq!(copy.selected_text(&vt, CopyFormat::Plain), None);
+
+    copy.toggle_selection(SelectionKind::Char);
+    copy.move_by(&vt, 0, 1);
+    copy.move_by(&vt, -2, 0);
+    assert_eq!(
+        copy.selected_text(&vt, CopyFormat::Plain).as_deref(),
+        Some("beta\ngamma")
+    );
+    assert!(copy.contains(0, 19) && copy.contains(1, 0) && !copy.contains(1, 5));
+
+    copy.toggle_selection(SelectionKind::Line);
+    assert_eq!(
+        copy.selected_text(&vt, CopyFormat::Plain).as_deref(),
+        Some("alpha beta\ngamma delta")
+    );
+
+    copy.toggle_selection(SelectionKind::Block);
+    copy.move_by(&vt, 0, 1);
+    copy.move_by(&vt, 4, 0);
+    // Columns 6..=8 of lines 0..=2
+    assert_eq!(
+        copy.selected_text(&vt, CopyFormat::Plain).as_deref(),
+        Some("bet\ndel\nn")
+    );
+
+    // Same kind again clears the selection
+    copy.toggle_selection(SelectionKind::Block);
+    assert_eq!(copy.anchor, None);
+}
+
+#[test]
+fn test_copy_mode_survives_eviction() {
+    use cltree::copy_mode::{CopyMode, SelectionKind};
+
+    let mut vt = VirtualTerminal::new(10, 2);
+    for i in 0..1000 {
+        vt.feed(format!("{i}\r\n").as_bytes());
+    }
+    let mut copy = CopyMode::new(&vt);
+    copy.cursor = (vt.evicted_lines() + 500, 0);
+    copy.toggle_selection(SelectionKind::Line);
+    assert_eq!(
+        copy.selected_text(&vt, CopyFormat::Plain).as_deref(),
+        Some("500")
+    );
+    // More output evicts old lines; the selection still covers the same text
+    for i in 0..10 {
+        vt.feed(format!("x{i}\r\n").as_bytes());
+    }
+    assert!(vt.evicted_lines() > 0);
+    assert_eq!(
+        copy.selected_text(&vt, CopyFormat::Plain).as_deref(),
+        Some("500")
+    );
+}
+
+#[test]
+fn test_word_bounds() {
+    use cltree::copy_mode::word_bounds;
+
+    let mut vt = VirtualTerminal::new(30, 3);
+    vt.feed("see src/main.rs 日本語 ok".as_bytes());
+    // Word characters
+    assert_eq!(word_bounds(&vt, (0, 1)), ((0, 0), (0, 2)));
+    assert_eq!(word_bounds(&vt, (0, 8)), ((0, 8), (0, 11)));
+    // A punctuation run is its own word
+    assert_eq!(word_bounds(&vt, (0, 7)), ((0, 7), (0, 7)));
+    // Wide characters include their trailing cell
+    assert_eq!(word_bounds(&vt, (0, 18)), ((0, 16), (0, 21)));
+    // Words never extend onto the next row
+    vt.feed(b"\r\nnext");
+    assert_eq!(word_bounds(&vt, (1, 0)), ((1, 0), (1, 3)));
+}
+
+#[test]
+fn test_copy_skips_wide_char_wrap_padding() {
+    use cltree::copy_mode::range_lines;
+    use cltree::export::{self, CopyFormat};
+
+    let mut vt = VirtualTerminal::new(5, 3);
+    vt.feed("abcd界x\r\n日本語".as_bytes());
+    assert_eq!(vt.lines_text(0..3), "abcd界x\n日本");
+    let lines = range_lines(&vt, (0, 0), (1, 4));
+    assert_eq!(export::render(&vt, &lines, CopyFormat::Plain), "abcd界x");
+    assert!(export::render(&vt, &lines, CopyFormat::Html).contains("abcd界x"));
+}
+
+#[test]
+fn test_line_bounds_use_the_vterm_width() {
+    use cltree::copy_mode::line_bounds;
+
+    let vt = VirtualTerminal::new(30, 3);
+    assert_eq!(line_bounds(&vt, (7, 12)), ((7, 0), (7, 29)));
+}
+
+#[test]
+fn test_selection_text_stays_anchored_while_lines_are_evicted() {
+    use cltree::copy_mode::{line_bounds, range_lines};
+    use cltree::export::{self, CopyFormat};
+
+    let mut vt = VirtualTerminal::new(20, 3);
+    for i in 0..1005 {
+        vt.feed(format!("line{i}\r\n").as_bytes());
+    }
+    // A triple click on the top screen row, anchored the way the app
+    // anchors mouse positions
+    let pos = (vt.evicted_lines() + vt.view_top(), 3);
+    let (start, end) = line_bounds(&vt, pos);
+    let text =
+        |vt: &VirtualTerminal| export::render(vt, &range_lines(vt, start, end), CopyFormat::Plain);
+    assert_eq!(text(&vt), "line1003");
+
+    // New output evicts old lines; the selection still covers the same text
+    for i in 1005..1010 {
+        vt.feed(format!("line{i}\r\n").as_bytes());
+    }
+    assert_eq!(text(&vt), "line1003");
+
+    // Once the line itself has gone there is nothing left to copy
+    for i in 1010..2100 {
+        vt.feed(format!("line{i}\r\n").as_bytes());
+    }
+    assert_eq!(text(&vt), "");
+}
+
+#[test]
+fn test_selection_across_a_full_reset_finds_nothing() {
+    use cltree::copy_mode::{line_bounds, range_lines};
+    use cltree::export::{self, CopyFormat};
+
+    let mut vt = VirtualTerminal::new(20, 3);
+    vt.feed(b"old0\r\nold1\r\nold2\r\nold3");
+    let pos = (vt.evicted_lines() + vt.view_top() + 1, 0);
+    let (start, end) = line_bounds(&vt, pos);
+    let text =
+        |vt: &VirtualTerminal| export::render(vt, &range_lines(vt, start, end), CopyFormat::Plain);
+    assert_eq!(text(&vt), "old2");
+
+    // RIS wipes the screen and scrollback; new output must not take the
+    // selected line's place
+    vt.feed(b"\x1bcnew0\r\nnew1\r\nnew2\r\nnew3\r\nnew4");
+    assert_eq!(text(&vt), "");
+    // The four lines before the reset count as evicted
+    assert_eq!(vt.evicted_lines(), 4);
+}
+
+#[test]
+fn test_logical_lines_join_soft_wraps() {
+    let mut vt = VirtualTerminal::new(10, 5);
+    vt.feed(b"https://example.com/a/b  \r\nshort   \r\n");
+    // The URL wrapped over three rows but comes back as one line
+    assert_eq!(vt.lines_text(0..4), "https://example.com/a/b\nshort");
+    // Trailing padding of a hard line is trimmed, spaces inside a wrap kept.
+    // "jk" wraps at the bottom row, pushing one line into the scrollback.
+    vt.feed(b"abcdefghi jk");
+    assert!(vt.line(4).is_some_and(|row| row.wrapped));
+    let lines = vt.logical_lines((4, 0), (5, 9));
+    let text: Vec<String> = lines.iter().map(|l| cltree::vterm::cells_text(l)).collect();
+    assert_eq!(text, vec!["abcdefghi jk"]);
+}
+
+#[test]
+fn test_copy_mode_joins_wrapped_rows() {
+    use cltree::copy_mode::{CopyMode, SelectionKind};
+
+    let mut vt = VirtualTerminal::new(8, 4);
+    vt.feed(b"$ cargo build --release\r\nok");
+    let mut copy = CopyMode::new(&vt);
+    copy.cursor = (0, 2);
+    copy.toggle_selection(SelectionKind::Char);
+    copy.cursor = (2, 6);
+    assert_eq!(
+        copy.selected_text(&vt, CopyFormat::Plain).as_deref(),
+        Some("cargo build --release")
+    );
+    // Block selections stay one line per row
+    copy.toggle_selection(SelectionKind::Block);
+    copy.cursor = (1, 4);
+    assert_eq!(
+        copy.selected_text(&vt, CopyFormat::Plain).as_deref(),
+        Some("car\nild")
+    );
+}
+
+#[test]
+fn test_copy_as_ansi_and_html() {
+    use cltree::export::render;
+
+    let mut vt = VirtualTerminal::new(20, 3);
+    vt.feed(b"\x1b[1;31mError\x1b[0m: a<b\r\n\x1b[38;5;208mx\x1b[0m");
+    let lines = vt.logical_lines((0, 0), (1, 19));
+
+    assert_eq!(render(&vt, &lines, CopyFormat::Plain), "Error: a<b\nx");
+    assert_eq!(
+        render(&vt, &lines, CopyFormat::Ansi),
+        "\x1b[0;1;31mError\x1b[0m: a<b\n\x1b[0;38;5;208mx\x1b[0m"
+    );
+    let html = render(&vt, &lines, CopyFormat::Html);
+    assert!(html.starts_with("<pre style=\"background:#000000;color:#e5e5e5;"));
+    assert!(html.contains("<span style=\"color:#cd0000;font-weight:bold\">Error</span>: a&lt;b\n"));
+    assert!(html.contains("<span style=\"color:#ff8700\">x</span></pre>"));
+}
+
+// ============================================================================
+// Snapshots
+// ============================================================================
+
+fn golden(name: &str) -> std::path::PathBuf {
+    std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(name)
+}
+
+/// A small styled screen with a wide character, a soft wrap and scrollback
+fn styled_terminal() -> VirtualTerminal {
+    let mut vt = VirtualTerminal::new(14, 4);
+    vt.feed(b"$ ls\r\n");
+    vt.feed(b"\x1b[1;34msrc\x1b[0m  \x1b[32mbuild.sh\x1b[0m\r\n");
+    vt.feed("\x1b[38;5;208m警告\x1b[0m: \x1b[3;4mwrapped text\x1b[0m\r\n".as_bytes());
+    vt.feed(b"\x1b[7m$ \x1b[0m\x1b[?25l");
+    vt
+}
+
+#[test]
+fn test_snapshot_text_and_ansi() {
+    let vt = styled_terminal();
+    assert_screen(
+        &vt.snapshot(Scope::Screen).text(),
+        "src  build.sh\n警告: wrapped\ntext\n$",
+    );
+    assert_eq!(
+        vt.snapshot(Scope::Screen)
+            .ansi()
+            .lines()
+            .next()
+            .unwrap_or_default(),
+        "\x1b[0;1;34msrc\x1b[0m  \x1b[0;32mbuild.sh\x1b[0m"
+    );
+}
+
+#[test]
+fn test_snapshot_golden_dump() {
+    let vt = styled_terminal();
+    assert_golden(golden("styled_screen.txt"), &vt.snapshot(Scope::All).dump());
+}
+
+#[test]
+fn test_snapshot_dump_keeps_visible_trailing_blanks() {
+    let mut vt = VirtualTerminal::new(8, 2);
+    vt.feed(b"\x1b[7m$ \x1b[0m\r\n\x1b[4mab \x1b[0m  ");
+    // Text trims every trailing blank
+    assert_screen(&vt.snapshot(Scope::Screen).text(), "$\nab");
+    // The dump keeps the reversed and underlined blanks, not the plain ones
+    let dump = vt.snapshot(Scope::Screen).dump();
+    assert!(dump.contains("  0 |$ |\n      0..2 reversed"), "{dump}");
+    assert!(dump.contains("  1 |ab |\n      0..3 underlined"), "{dump}");
+}
+
+#[test]
+#[should_panic(expected = "line 2")]
+fn test_assert_screen_reports_first_difference() {
+    let mut vt = VirtualTerminal::new(5, 2);
+    vt.feed(b"A\r\nB");
+    assert_screen(&vt.snapshot(Scope::Screen).text(), "A\nC");
+}
+
+// ============================================================================
+// Transcripts
+// ============================================================================
+
+#[test]
+fn test_transcript_text_joins_wraps_and_strips_ansi() {
+    use cltree::transcript::{render, TranscriptFormat, TranscriptOptions};
+
+    let mut vt = VirtualTerminal::new(10, 4);
+    vt.feed(b"\x1b[1;32mhello\x1b[0m there, world\r\n\r\nbye");
+    let text = render(
+        &vt,
+        TranscriptOptions {
+            format: TranscriptFormat::Text,
+            sections: false,
+        },
+    );
+    assert_eq!(text, "hello there, world\n\nbye\n");
+}
+
+#[test]
+fn test_transcript_markdown_fences_code_like_blocks() {
+    use cltree::transcript::{render, TranscriptOptions};
+
+    let mut vt = VirtualTerminal::new(30, 12);
+    vt.feed(b"Here is the fix:\r\n\r\n");
+    vt.feed(b"    fn main() {\r\n\r\n        run();\r\n    }\r\n");
+    vt.feed("\u{256d}\u{2500}\u{2500}\u{256e}\r\n\u{2570}\u{2500}\u{2500}\u{256f}\r\n".as_bytes());
+    vt.feed(b"Done.\r\nAll good.");
+    let md = render(&vt, TranscriptOptions::default());
+    assert_eq!(
+        md,
+        "# Transcript\n\
+         \n\
+         Here is the fix:\n\
+         \n\
+         ```\n    fn main() {\n\n        run();\n    }\n╭──╮\n╰──╯\n```\n\
+         \n\
+         Done.\n\
+         All good.\n"
+    );
+}
+
+#[test]
+fn test_transcript_markdown_sections_per_prompt() {
+    use cltree::transcript::{render, TranscriptFormat, TranscriptOptions};
+
+    let mut vt = VirtualTerminal::new(30, 10);
+    vt.feed(b"welcome\r\n");
+    feed_command(&mut vt, "ls", &["a.txt", "b.txt"], 0);
+    feed_command(&mut vt, "echo ```", &["```"], 0);
+    vt.feed(b"\x1b]133;A\x07$ ");
+    let md = render(
+        &vt,
+        TranscriptOptions {
+            format: TranscriptFormat::Markdown,
+            sections: true,
+        },
+    );
+    assert_eq!(
+        md,
+        "# Transcript\n\
+         \n\
+         welcome\n\
+         \n\
+         ## $ ls\n\
+         \n\
+         a.txt\n\
+         b.txt\n\
+         \n\
+         ## $ echo ```\n\
+         \n\
+         ````\n```\n````\n\
+         \n\
+         ## $\n"
+    );
+}
+
+// ============================================================================
+// Images
+// ============================================================================
+
+/// A 4x12 sixel in colour 1 set to pure red
+const RED_SIXEL: &[u8] = b"\x1bPq\"1;1;4;12#1;2;100;0;0#1!4~-!4~\x1b\\";
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    data.chunks(3)
+        .flat_map(|chunk| {
+            let n = chunk
+                .iter()
+                .enumerate()
+                .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+            (0..4).map(move |i| {
+                if i <= chunk.len() {
+                    ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char
+                } else {
+                    '='
+                }
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn test_sixel_is_decoded_and_placed() {
+    let mut vt = VirtualTerminal::new(10, 4);
+    vt.feed(b"ab");
+    vt.feed(RED_SIXEL);
+    vt.feed(b"c");
+
+    let images = vt.images();
+    assert_eq!(images.len(), 1);
+    let placement = &images[0];
+    assert_eq!((placement.image.width, placement.image.height), (4, 12));
+    assert_eq!(placement.image.pixel(0, 0), [255, 0, 0, 255]);
+    assert_eq!(placement.image.pixel(3, 11), [255, 0, 0, 255]);
+    // 10x20 px cells by default: one cell, at the cursor
+    assert_eq!((placement.line, placement.col), (0, 2));
+    assert_eq!((placement.cols, placement.rows), (1, 1));
+    match &placement.source {
+        ImageSource::Sixel(raw) => assert_eq!(&raw[..], RED_SIXEL),
+        other => panic!("expected a sixel, got {other:?}"),
+    }
+    // Text continues below the image; none of the payload leaks
+    assert_screen(&vt.snapshot(Scope::Screen).text(), "ab\n  c\n\n");
+}
+
+#[test]
+fn test_sixel_size_follows_cell_size() {
+    let mut vt = VirtualTerminal::new(10, 4);
+    vt.set_cell_size(2, 5);
+    vt.feed(RED_SIXEL);
+    let placement = &vt.images()[0];
+    assert_eq!((placement.cols, placement.rows), (2, 3));
+    assert_eq!((vt.cursor().x, vt.cursor().y), (0, 3));
+}
+
+#[test]
+fn test_kitty_transmit_and_display() {
+    let mut vt = VirtualTerminal::new(10, 4);
+    vt.feed(b"x\x1b_Ga=T,f=24,s=2,v=1,i=7;/wAAAAD/\x1b\\y");
+    assert_eq!(vt.take_responses(), vec![b"\x1b_Gi=7;OK\x1b\\".to_vec()]);
+
+    let placement = &vt.images()[0];
+    assert!(matches!(placement.source, ImageSource::Kitty(7)));
+    assert_eq!(placement.image.pixel(0, 0), [255, 0, 0, 255]);
+    assert_eq!(placement.image.pixel(1, 0), [0, 0, 255, 255]);
+    assert_eq!((placement.line, placement.col), (0, 1));
+    // The cursor moves past the image
+    assert_eq!(vt.row_text(0), "x y");
+}
+
+#[test]
+fn test_kitty_apc_never_reaches_the_screen() {
+    let input = b"a\x1b_Ga=T,f=32,s=1,v=1,C=1,q=2;AP8A/w==\x1b\\b";
+    let mut whole = VirtualTerminal::new(10, 2);
+    whole.feed(input);
+    let mut bytewise = VirtualTerminal::new(10, 2);
+    for byte in input {
+        bytewise.feed(std::slice::from_ref(byte));
+    }
+    for vt in [&whole, &bytewise] {
+        assert_eq!(vt.row_text(0), "ab");
+        assert_eq!(vt.images().len(), 1);
+        assert_eq!(vt.images()[0].image.pixel(0, 0), [0, 255, 0, 255]);
+    }
+    assert!(whole.take_responses().is_empty());
+}
+
+#[test]
+fn test_kitty_chunked_transmission_then_put() {
+    let mut vt = VirtualTerminal::new(10, 4);
+    vt.feed(b"\x1b_Ga=t,f=24,s=2,v=1,i=3,m=1;/wAA\x1b\\\x1b_Gm=0;AAD/\x1b\\");
+    assert_eq!(vt.take_responses(), vec![b"\x1b_Gi=3;OK\x1b\\".to_vec()]);
+    assert!(vt.images().is_empty());
+
+    vt.feed(b"\x1b_Ga=p,i=3,c=4,r=2\x1b\\");
+    let placement = &vt.images()[0];
+    assert_eq!((placement.cols, placement.rows), (4, 2));
+    assert_eq!(placement.image.pixel(1, 0), [0, 0, 255, 255]);
+    assert_eq!((vt.cursor().x, vt.cursor().y), (4, 1));
+    assert_eq!(vt.take_responses(), vec![b"\x1b_Gi=3;OK\x1b\\".to_vec()]);
+
+    vt.feed(b"\x1b_Ga=p,i=9\x1b\\");
+    let reply = String::from_utf8(vt.take_responses().concat()).unwrap();
+    assert!(reply.starts_with("\x1b_Gi=9;ENOENT"), "{reply:?}");
+}
+
+#[test]
+fn test_kitty_query_and_errors() {
+    let mut vt = VirtualTerminal::new(10, 4);
+    vt.feed(b"\x1b_Ga=q,i=31,s=1,v=1,f=24;AAAA\x1b\\");
+    assert_eq!(vt.take_responses(), vec![b"\x1b_Gi=31;OK\x1b\\".to_vec()]);
+    assert!(vt.images().is_empty());
+
+    // Not enough pixel data
+    vt.feed(b"\x1b_Ga=T,i=2,s=4,v=4,f=32;AAAA\x1b\\");
+    let reply = String::from_utf8(vt.take_responses().concat()).unwrap();
+    assert!(reply.starts_with("\x1b_Gi=2;ENODATA"), "{reply:?}");
+    assert!(vt.images().is_empty());
+}
+
+#[test]
+fn test_kitty_png() {
+    let mut png_bytes = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut png_bytes, 2, 2);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer
+            .write_image_data(&[255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255])
+            .unwrap();
+    }
+    let mut vt = VirtualTerminal::new(10, 4);
+    vt.feed(format!("\x1b_Ga=T,f=100,q=1;{}\x1b\\", base64(&png_bytes)).as_bytes());
+    let image = &vt.images()[0].image;
+    assert_eq!((image.width, image.height), (2, 2));
+    assert_eq!(image.pixel(1, 0), [0, 255, 0, 255]);
+    assert_eq!(image.pixel(1, 1), [255, 255, 255, 255]);
+}
+
+#[test]
+fn test_images_scroll_and_clear() {
+    let mut vt = VirtualTerminal::new(10, 3);
+    vt.feed(RED_SIXEL);
+    vt.feed(b"1\r\n2\r\n3\r\n4");
+    // The image scrolled into the scrollback with its line
+    assert_eq!(vt.images()[0].line, 0);
+    assert_eq!(vt.scrollback().len(), 2);
+    let dump = vt.snapshot(Scope::All).dump();
+    assert!(dump.contains("image s0,0 1x1 cells 4x12px sixel"), "{dump}");
+
+    // Clearing the screen leaves scrollback images alone
+    vt.feed(b"\x1b[2J");
+    assert_eq!(vt.images().len(), 1);
+    vt.feed(RED_SIXEL);
+    assert_eq!(vt.images().len(), 2);
+    vt.feed(b"\x1b[2J");
+    assert_eq!(vt.images().len(), 1);
+
+    // The alternate screen has its own images
+    vt.feed(b"\x1b[?1049h");
+    assert!(vt.images().is_empty());
+    vt.feed(b"\x1b_Ga=T,f=24,s=1,v=1;AAAA\x1b\\");
+    assert_eq!(vt.images().len(), 1);
+    vt.feed(b"\x1b[?1049l");
+    assert!(matches!(vt.images()[0].source, ImageSource::Sixel(_)));
+}
+
+#[test]
+fn test_device_attributes_and_cell_size_reports() {
+    let mut vt = VirtualTerminal::new(80, 24);
+    vt.set_cell_size(9, 18);
+    vt.feed(b"\x1b[c\x1b[16t\x1b[14t");
+    assert_eq!(
+        vt.take_responses(),
+        vec![
+            b"\x1b[?62;4;22c".to_vec(),
+            b"\x1b[6;18;9t".to_vec(),
+            b"\x1b[4;432;720t".to_vec(),
+        ]
+    );
+}
+
+#[test]
+fn test_half_block_and_braille_approximations() {
+    // Red over blue, then a transparent bottom half
+    let image = Image {
+        width: 1,
+        height: 2,
+        rgba: vec![255, 0, 0, 255, 0, 0, 255, 255],
+    };
+    assert_eq!(
+        graphics::half_block(&image, 1, 1, 0, 0, (0, 0, 0)),
+        (Some((255, 0, 0)), Some((0, 0, 255)))
+    );
+    let image = Image {
+        width: 1,
+        height: 2,
+        rgba: vec![255, 255, 255, 255, 0, 0, 0, 0],
+    };
+    assert_eq!(
+        graphics::half_block(&image, 1, 1, 0, 0, (0, 0, 0)),
+        (Some((255, 255, 255)), None)
+    );
+
+    // Left column lit: dots 1, 2, 3 and 7
+    let mut rgba = Vec::new();
+    for _ in 0..4 {
+        rgba.extend_from_slice(&[255, 255, 255, 255, 0, 0, 0, 255]);
+    }
+    let image = Image {
+        width: 2,
+        height: 4,
+        rgba,
+    };
+    assert_eq!(
+        graphics::braille(&image, 1, 1, 0, 0, (0, 0, 0)),
+        Some(('\u{2847}', (255, 255, 255)))
+    );
+    assert_eq!(
+        graphics::braille(&image, 1, 1, 0, 0, (255, 255, 255)).map(|b| b.0),
+        Some('\u{28b8}')
+    );
+}
+
+#[test]
+fn test_image_passthrough_sequences() {
+    let mut vt = VirtualTerminal::new(10, 4);
+    vt.feed(RED_SIXEL);
+    let placement = &vt.images()[0];
+    assert!(ImageMode::Sixel.passes_through(placement));
+    assert!(!ImageMode::HalfBlock.passes_through(placement));
+    assert_eq!(
+        graphics::passthrough_sequence(placement, ImageMode::Sixel),
+        RED_SIXEL
+    );
+    let kitty =
+        String::from_utf8(graphics::passthrough_sequence(placement, ImageMode::Kitty)).unwrap();
+    assert!(
+        kitty.starts_with("\x1b_Ga=T,f=32,s=4,v=12,c=1,r=1,C=1,q=2,m=0;/wAA/"),
+        "{kitty:?}"
+    );
+    assert!(kitty.ends_with("\x1b\\"));
+    assert_eq!(ImageMode::parse("Braille"), Some(ImageMode::Braille));
+}
+
+// ============================================================================
+// Regressions found by the property tests
+// ============================================================================
+
+#[test]
+fn test_decstbm_ignores_regions_outside_the_screen() {
+    let mut vt = VirtualTerminal::new(10, 6);
+    vt.feed(b"A\x1b[7r\x1bM");
+    // The bogus region is ignored: no cursor homing, RI just moves up
+    assert_eq!((vt.cursor().x, vt.cursor().y), (1, 0));
+    assert_eq!(vt.grid().len(), 6);
+}
+
+#[test]
+fn test_resize_under_alternate_screen_resizes_main_screen() {
+    let mut vt = VirtualTerminal::new(4, 1);
+    vt.feed(b"main\x1b7\x1b[?1049h");
+    vt.resize(3, 3);
+    vt.feed(b"\x1b[?1049l\x1b8");
+    assert_eq!(vt.grid().len(), 3);
+    assert!(vt.grid().iter().all(|row| row.len() == 3));
+    assert_screen(&vt.snapshot(Scope::Screen).text(), "mai\n\n");
+    assert!(vt.cursor().x < 3);
+}
+
+#[test]
+fn test_utf8_split_across_feeds() {
+    // U+0080 split between two reads must not swallow the following byte
+    let mut vt = VirtualTerminal::new(10, 2);
+    vt.feed(b"\xc2");
+    vt.feed(b"\x80 A");
+    assert_eq!(vt.row_text(0), " A");
+
+    let mut vt = VirtualTerminal::new(10, 2);
+    vt.feed("日".as_bytes().split_at(1).0);
+    vt.feed("日".as_bytes().split_at(1).1);
+    vt.feed(b"x");
+    // row_text shows the wide glyph's second cell as a space
+    assert_eq!(vt.row_text(0), "日 x");
+}